@@ -0,0 +1,77 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    check::{check_activate, program_exists},
+    constants::EOF_PREFIX_NO_DICT,
+    deploy::{send_activation, write_activation_calldata},
+    macros::*,
+    wallet, ActivateConfig,
+};
+use alloy_primitives::U256;
+use cargo_stylus_util::color::{Color, DebugColor};
+use ethers::middleware::{Middleware, SignerMiddleware};
+use eyre::{bail, Result, WrapErr};
+
+/// Activates a program that has already been deployed, without redeploying its code.
+pub async fn activate_program(cfg: &ActivateConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?;
+    cfg.common_cfg.check_chain(chain_id.as_u64())?;
+
+    let code = provider
+        .get_code(cfg.address, None)
+        .await
+        .wrap_err("failed to fetch program code")?;
+    if code.is_empty() {
+        bail!(
+            "no code at address {}, please deploy the program first",
+            cfg.address.debug_red()
+        );
+    }
+    if !code.starts_with(hex::decode(EOF_PREFIX_NO_DICT).unwrap().as_slice()) {
+        bail!(
+            "code at {} does not start with Stylus prefix {EOF_PREFIX_NO_DICT}",
+            cfg.address.debug_red()
+        );
+    }
+
+    let codehash = alloy_primitives::keccak256(&code);
+    if program_exists(codehash, &provider).await? {
+        greyln!("wasm already activated!");
+        return Ok(());
+    }
+
+    let fee = check_activate(code, cfg.address, &provider).await?;
+    let data_fee = fee * U256::from(100 + cfg.data_fee_bump_percent) / U256::from(100);
+    if cfg.common_cfg.verbose {
+        greyln!(
+            "data fee {} bumped by {}% to {}",
+            format!("{fee} wei").mint(),
+            cfg.data_fee_bump_percent,
+            format!("{data_fee} wei").mint()
+        );
+    }
+
+    if let Some(path) = &cfg.emit_activation_calldata {
+        return write_activation_calldata(path, cfg.address, data_fee);
+    }
+
+    let wallet = wallet::resolve_signer(&cfg.auth, chain_id.as_u64()).await?;
+    let client = SignerMiddleware::new(provider, wallet);
+
+    send_activation(
+        &cfg.common_cfg,
+        cfg.address,
+        data_fee,
+        cfg.activation_gas_limit,
+        cfg.estimate_gas,
+        cfg.check_reverts_with_trace,
+        &client,
+    )
+    .await?;
+    Ok(())
+}
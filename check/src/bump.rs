@@ -0,0 +1,146 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{config, deploy, macros::*, wallet, BumpConfig};
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    sys,
+};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::Middleware,
+    signers::Signer,
+    types::{Eip1559TransactionRequest, U256},
+    utils::format_units,
+};
+use eyre::{bail, Result, WrapErr};
+use std::io::{self, IsTerminal};
+
+/// How much both fee caps must rise for nodes to accept a replacement, in percent.
+const MIN_BUMP_PERCENT: u64 = 10;
+
+/// Replaces a pending tx with one sending the same data at the same nonce, but with higher fees.
+pub async fn bump(cfg: &BumpConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?
+        .as_u64();
+    cfg.common_cfg.check_chain(chain_id)?;
+    cfg.common_cfg.check_mainnet(chain_id)?;
+    let common_cfg = &config::network(chain_id)?.apply_fees(&cfg.common_cfg);
+    let Some(max_fee) = common_cfg.max_fee_per_gas()? else {
+        bail!("pass --max-fee-per-gas-gwei with the replacement's max fee");
+    };
+
+    let Some(stuck) = provider
+        .get_transaction(cfg.tx)
+        .await
+        .wrap_err("failed to get tx")?
+    else {
+        bail!(
+            "tx {} not found, it may have been dropped or replaced",
+            cfg.tx.debug_red()
+        );
+    };
+    if let Some(block) = stuck.block_number {
+        bail!(
+            "tx {} was already mined in block {block}",
+            cfg.tx.debug_red()
+        );
+    }
+
+    let wallet = wallet::resolve_signer(&cfg.auth, chain_id).await?;
+    if wallet.address() != stuck.from {
+        bail!(
+            "tx was sent by {}, but the wallet is {}",
+            stuck.from.debug_red(),
+            wallet.address().debug_red()
+        );
+    }
+
+    // Legacy txs pay their gas price as both the cap and the tip.
+    let old_max_fee = stuck
+        .max_fee_per_gas
+        .or(stuck.gas_price)
+        .unwrap_or_default();
+    let old_tip = stuck
+        .max_priority_fee_per_gas
+        .or(stuck.gas_price)
+        .unwrap_or_default();
+    let tip = common_cfg.max_priority_fee_per_gas()?.unwrap_or(old_tip);
+    if max_fee <= old_max_fee {
+        bail!(
+            "max fee per gas {} doesn't exceed the stuck tx's {}",
+            gwei(max_fee)?,
+            gwei(old_max_fee)?
+        );
+    }
+    if tip > max_fee {
+        bail!(
+            "the stuck tx's priority fee {} exceeds the new max fee, pass a lower \
+             --max-priority-fee-per-gas-gwei",
+            gwei(tip)?
+        );
+    }
+    let min_bump = |old: U256| old * (100 + MIN_BUMP_PERCENT) / 100;
+    if max_fee < min_bump(old_max_fee) || tip < min_bump(old_tip) {
+        greyln!(
+            "{}",
+            format!(
+                "nodes often reject replacements that don't raise both fees by {MIN_BUMP_PERCENT}%"
+            )
+            .yellow()
+        );
+    }
+
+    greyln!(
+        "replacing tx {} with nonce {}",
+        cfg.tx.debug_lavender(),
+        stuck.nonce
+    );
+    greyln!(
+        "max fee per gas: {} -> {}",
+        gwei(old_max_fee)?,
+        gwei(max_fee)?.mint()
+    );
+    greyln!(
+        "max priority fee per gas: {} -> {}",
+        gwei(old_tip)?,
+        gwei(tip)?.mint()
+    );
+    if io::stdin().is_terminal() && !sys::confirm("send the replacement?")? {
+        bail!("replacement not sent");
+    }
+
+    let mut tx = Eip1559TransactionRequest::new()
+        .from(stuck.from)
+        .data(stuck.input)
+        .value(stuck.value)
+        .nonce(stuck.nonce)
+        .gas(stuck.gas)
+        .max_fee_per_gas(max_fee)
+        .max_priority_fee_per_gas(tip)
+        .access_list(stuck.access_list.unwrap_or_default())
+        .chain_id(chain_id);
+    if let Some(to) = stuck.to {
+        tx = tx.to(to);
+    }
+    let client = SignerMiddleware::new(provider, wallet);
+    let tx_hash = deploy::send_tx("replacement", tx, None, common_cfg, &client).await?;
+    greyln!("sent replacement tx {}", tx_hash.debug_lavender());
+    let receipt = deploy::wait_for_tx("replacement", tx_hash, &client).await?;
+    if let Some(contract) = receipt.contract_address {
+        greyln!("deployed code at address: {}", contract.debug_lavender());
+    }
+    greyln!(
+        "replacement mined in block {}",
+        receipt.block_number.unwrap_or_default()
+    );
+    Ok(())
+}
+
+fn gwei(wei: U256) -> Result<String> {
+    Ok(format!("{} gwei", format_units(wei, "gwei")?))
+}
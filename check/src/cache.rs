@@ -1,81 +1,167 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/stylus/licenses/COPYRIGHT.md
 
+use alloy_json_abi::{Function, JsonAbi};
 use alloy_primitives::FixedBytes;
 use alloy_sol_macro::sol;
 use alloy_sol_types::{SolCall, SolInterface};
-use cargo_stylus_util::color::{Color, DebugColor};
-use cargo_stylus_util::sys;
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    rpc::RpcClient,
+    sys,
+};
 use ethers::middleware::{Middleware, SignerMiddleware};
-use ethers::signers::Signer;
+use ethers::providers::Provider;
 use ethers::types::spoof::State;
-use ethers::types::{Eip1559TransactionRequest, U256};
+use ethers::types::{BlockNumber, Eip1559TransactionRequest, H160, H256, U256};
 use ethers::utils::keccak256;
-use eyre::{bail, Context, Result};
+use eyre::{bail, eyre, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
+};
 
 use crate::check::{eth_call, EthCallError};
-use crate::constants::{CACHE_MANAGER_H160, EOF_PREFIX_NO_DICT};
-use crate::deploy::{format_gas, run_tx};
-use crate::macros::greyln;
-use crate::CacheConfig;
+use crate::config;
+use crate::constants::{
+    ARB_WASM_CACHE_H160, ARB_WASM_H160, CACHE_MANAGER_H160, EOF_PREFIX_NO_DICT,
+};
+use crate::deploy::{fill_fees, format_gas, run_tx, SignerClient};
+use crate::macros::*;
+use crate::output;
+use crate::wallet;
+use crate::{CacheConfig, CacheForecastConfig, CacheStatusConfig};
 
 sol! {
     interface CacheManager {
+        struct Entry {
+            bytes32 code;
+            uint64 size;
+            uint192 bid;
+        }
+
         function placeBid(bytes32 codehash) external payable;
+        function getMinBid(bytes32 codehash) external view returns (uint192 min);
+        function getSmallestEntries(uint256 k) external view returns (Entry[] memory result);
+        function getEntries() external view returns (Entry[] memory);
+        function cacheSize() external view returns (uint64);
+        function queueSize() external view returns (uint64);
+        function isPaused() external view returns (bool);
 
         error AsmTooLarge(uint256 asm, uint256 queueSize, uint256 cacheSize);
         error AlreadyCached(bytes32 codehash);
         error BidTooSmall(uint192 bid, uint192 min);
         error BidsArePaused();
     }
+
+    interface ArbWasm {
+        function codehashAsmSize(bytes32 codehash) external view returns (uint32 size);
+    }
+
+    interface ArbWasmCache {
+        function codehashIsCached(bytes32 codehash) external view returns (bool);
+    }
+}
+
+// Kept apart from `CacheManager`, whose other `getMinBid` would otherwise rename both overloads.
+sol! {
+    interface CacheManagerParams {
+        function decay() external view returns (uint64);
+        function getMinBid(uint64 size) external view returns (uint192 min);
+    }
 }
 
+/// Most cache entries the preview looks at when working out which would be evicted.
+const MAX_PREVIEWED_EVICTIONS: u64 = 32;
+
+/// CacheManager functions the bid preview calls.
+const PREVIEW_FUNCTIONS: &[&str] = &[
+    CacheManager::getMinBidCall::SIGNATURE,
+    CacheManager::getSmallestEntriesCall::SIGNATURE,
+    CacheManager::cacheSizeCall::SIGNATURE,
+    CacheManager::queueSizeCall::SIGNATURE,
+];
+
 pub async fn cache_program(cfg: &CacheConfig) -> Result<()> {
-    let provider = sys::new_provider(&cfg.common_cfg.endpoint)?;
+    let provider = cfg.common_cfg.provider()?;
     let chain_id = provider
         .get_chainid()
         .await
         .wrap_err("failed to get chain id")?;
+    cfg.common_cfg.check_chain(chain_id.as_u64())?;
 
-    let wallet = cfg.auth.wallet().wrap_err("failed to load wallet")?;
-    let wallet = wallet.with_chain_id(chain_id.as_u64());
+    let wallet = wallet::resolve_signer(&cfg.auth, chain_id.as_u64()).await?;
     let client = SignerMiddleware::new(provider.clone(), wallet);
+    let network = config::network(chain_id.as_u64())?;
+    let cache_manager = network.cache_manager.unwrap_or(*CACHE_MANAGER_H160);
 
-    let program_code = client
-        .get_code(cfg.program_address, None)
-        .await
-        .wrap_err("failed to fetch program code")?;
+    let program_address = cfg.program_address.unwrap_or_default();
+    let codehash = program_codehash(program_address, &provider).await?;
 
-    if !program_code.starts_with(hex::decode(EOF_PREFIX_NO_DICT).unwrap().as_slice()) {
-        bail!(
-            "program code does not start with Stylus prefix {}",
-            EOF_PREFIX_NO_DICT
-        );
-    }
-    let codehash = FixedBytes::<32>::from(keccak256(&program_code));
-    greyln!(
-        "Program codehash {}",
-        hex::encode(codehash).debug_lavender()
-    );
-    let codehash = FixedBytes::<32>::from(keccak256(&program_code));
+    let abi = match &cfg.cache_manager_abi {
+        Some(path) => Some(load_cache_manager_abi(path)?),
+        None => None,
+    };
 
-    let data = CacheManager::placeBidCall { codehash }.abi_encode();
+    let data = match &abi {
+        Some(abi) => encode_call(find_function(abi, "placeBid")?, codehash, program_address),
+        None => CacheManager::placeBidCall { codehash }.abi_encode(),
+    };
     let mut tx = Eip1559TransactionRequest::new()
-        .to(*CACHE_MANAGER_H160)
+        .to(cache_manager)
         .data(data);
 
-    // If a bid is set, specify it. Otherwise, a zero bid will be sent.
-    if let Some(bid) = cfg.bid {
-        tx = tx.value(U256::from(bid));
+    // If a bid is set, specify it. Otherwise, bid the current minimum, or zero if it's unknown.
+    let bid = cfg.bid.map(U256::from).or(cfg.bid_eth);
+    let bid = bid.or(network.bid.map(U256::from));
+    let bid = match bid {
+        Some(bid) => Some(bid),
+        None => {
+            let buffer_percent = cfg.bid_buffer_percent.or(network.bid_buffer_percent);
+            let program = (program_address, codehash);
+            auto_bid(
+                cache_manager,
+                program,
+                abi.as_ref(),
+                buffer_percent,
+                &provider,
+            )
+            .await?
+        }
+    };
+    if let Some(bid) = bid {
+        tx = tx.value(bid);
         greyln!("Setting bid value of {} wei", bid.debug_mint());
     }
 
+    let missing = abi.as_ref().and_then(|abi| {
+        let mut missing = PREVIEW_FUNCTIONS.iter().filter(|f| !has_function(abi, f));
+        missing.next()
+    });
+    if let Some(function) = missing {
+        greyln!("not previewing bid: the CacheManager ABI lacks {function}");
+    } else if let Err(error) =
+        preview_bid(cache_manager, codehash, bid.unwrap_or_default(), &provider).await
+    {
+        greyln!("could not preview bid: {error}");
+    }
+
     if let Err(EthCallError { data, msg }) =
         eth_call(tx.clone(), State::default(), &provider).await?
     {
         let error = match CacheManager::CacheManagerErrors::abi_decode(&data, true) {
             Ok(err) => err,
-            Err(err_details) => bail!("unknown CacheManager error: {msg} and {:?}", err_details),
+            Err(err_details) => {
+                let mut known = abi.iter().flat_map(|abi| abi.errors());
+                if let Some(error) = known.find(|e| data.starts_with(&e.selector()[..])) {
+                    bail!("CacheManager error {}: {msg}", error.signature());
+                }
+                bail!("unknown CacheManager error: {msg} and {:?}", err_details)
+            }
         };
         use CacheManager::CacheManagerErrors as C;
         match error {
@@ -85,22 +171,23 @@ pub async fn cache_program(cfg: &CacheConfig) -> Result<()> {
                 bail!("bidding is currently paused for the Stylus cache manager")
             }
             C::BidTooSmall(_) => {
-                bail!("bid amount {} (wei) too small", cfg.bid.unwrap_or_default())
+                bail!("bid amount {} (wei) too small", bid.unwrap_or_default())
             }
         }
     }
+    if let Some(min_balance) = cfg.min_balance_wei.or(cfg.min_balance_eth) {
+        // Settle the fees now so the check prices gas the way the bid will be sent.
+        fill_fees(&mut tx, &cfg.common_cfg, &client).await?;
+        check_min_balance(&tx, min_balance, &client).await?;
+    }
+    if !cfg.yes && io::stdin().is_terminal() && !sys::confirm("Place bid?")? {
+        bail!("bid not placed");
+    }
+
     let verbose = cfg.common_cfg.verbose;
-    let receipt = run_tx(
-        "cache",
-        tx,
-        None,
-        cfg.common_cfg.max_fee_per_gas_gwei,
-        &client,
-        verbose,
-    )
-    .await?;
+    let receipt = run_tx("cache", tx, None, &cfg.common_cfg, &client).await?;
 
-    let address = cfg.program_address.debug_lavender();
+    let address = program_address.debug_lavender();
 
     if verbose {
         let gas = format_gas(receipt.gas_used.unwrap_or_default());
@@ -115,3 +202,512 @@ pub async fn cache_program(cfg: &CacheConfig) -> Result<()> {
     greyln!("Sent Stylus cache tx with hash: {tx_hash}");
     Ok(())
 }
+
+/// Fetches a Stylus program's code, returning its codehash.
+async fn program_codehash(program: H160, provider: &Provider<RpcClient>) -> Result<FixedBytes<32>> {
+    let program_code = provider
+        .get_code(program, None)
+        .await
+        .wrap_err("failed to fetch program code")?;
+
+    if program_code.is_empty() {
+        bail!("no program deployed at {}", program.debug_red());
+    }
+    if !program_code.starts_with(hex::decode(EOF_PREFIX_NO_DICT).unwrap().as_slice()) {
+        bail!(
+            "program code does not start with Stylus prefix {}",
+            EOF_PREFIX_NO_DICT
+        );
+    }
+    let codehash = FixedBytes::<32>::from(keccak256(&program_code));
+    greyln!(
+        "Program codehash {}",
+        hex::encode(codehash).debug_lavender()
+    );
+    Ok(codehash)
+}
+
+/// The bid needed to stay cached at some point in a forecast.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BidProjection {
+    /// Seconds from now.
+    pub after_secs: u64,
+    /// Bid in wei that would keep the program ahead of a new entry bidding today's minimum.
+    pub bid: String,
+}
+
+/// How the cost of keeping a program cached develops, as printed by `cache forecast --json`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheForecast {
+    #[schemars(with = "String")]
+    pub program_address: H160,
+    /// Hex-encoded codehash of the program.
+    pub codehash: String,
+    #[schemars(with = "String")]
+    pub cache_manager: H160,
+    /// Wei by which every bid loses value each second.
+    pub decay_per_second: String,
+    /// Minimum bid in wei for an entry of the program's size right now.
+    pub min_bid: String,
+    /// Forecast horizon in seconds.
+    pub horizon_secs: u64,
+    /// Projected bids at each quarter of the horizon.
+    pub projections: Vec<BidProjection>,
+}
+
+/// Forecasts the bid needed to keep a program cached over a horizon, without sending anything.
+///
+/// The CacheManager stores each bid as its value plus the time it was placed multiplied by the
+/// decay rate, so a bid placed now is worth `decay * t` less than one placed `t` seconds later.
+/// Staying ahead of a new entry paying today's minimum then takes that minimum plus the decay
+/// over the horizon.
+pub async fn forecast(cfg: &CacheForecastConfig) -> Result<()> {
+    if cfg.json {
+        output::diagnostics_to_stderr();
+    }
+    let provider = cfg.common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?;
+    cfg.common_cfg.check_chain(chain_id.as_u64())?;
+    let network = config::network(chain_id.as_u64())?;
+    let cache_manager = network.cache_manager.unwrap_or(*CACHE_MANAGER_H160);
+    let codehash = program_codehash(cfg.program_address, &provider).await?;
+
+    let size = view(
+        *ARB_WASM_H160,
+        ArbWasm::codehashAsmSizeCall { codehash },
+        &provider,
+    )
+    .await
+    .wrap_err("failed to get the program's size, is it activated?")?
+    .size;
+    let decay = view(cache_manager, CacheManagerParams::decayCall {}, &provider)
+        .await?
+        ._0;
+    let size = size.into();
+    let min_bid = view(
+        cache_manager,
+        CacheManagerParams::getMinBidCall { size },
+        &provider,
+    )
+    .await?
+    .min;
+    let min_bid = U256::from_little_endian(&min_bid.as_le_bytes());
+    let decay = U256::from(decay);
+
+    let horizon_secs = cfg.horizon_days * 24 * 60 * 60;
+    let projections = (1..=4)
+        .map(|quarter| {
+            let after_secs = horizon_secs * quarter / 4;
+            let bid = min_bid + decay * U256::from(after_secs);
+            BidProjection {
+                after_secs,
+                bid: bid.to_string(),
+            }
+        })
+        .collect();
+    let forecast = CacheForecast {
+        program_address: cfg.program_address,
+        codehash: hex::encode(codehash),
+        cache_manager,
+        decay_per_second: decay.to_string(),
+        min_bid: min_bid.to_string(),
+        horizon_secs,
+        projections,
+    };
+    if cfg.json {
+        println!("{}", serde_json::to_string_pretty(&forecast)?);
+        return Ok(());
+    }
+
+    let per_day = decay * U256::from(24 * 60 * 60);
+    println!("decay rate: {decay} wei/s ({per_day} wei/day)");
+    println!("minimum bid now: {min_bid} wei");
+    println!("bid needed to stay cached for:");
+    for projection in &forecast.projections {
+        let days = projection.after_secs as f64 / (24.0 * 60.0 * 60.0);
+        println!("  {days:>6.2} days: {} wei", projection.bid);
+    }
+    greyln!("assuming competing bids stay at today's minimum");
+    Ok(())
+}
+
+/// Prints whether a program is cached, what its bid is worth now, and what it would take to
+/// cache it, without sending anything.
+pub async fn status(cfg: &CacheStatusConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?;
+    cfg.common_cfg.check_chain(chain_id.as_u64())?;
+    let network = config::network(chain_id.as_u64())?;
+    let cache_manager = network.cache_manager.unwrap_or(*CACHE_MANAGER_H160);
+    let codehash = program_codehash(cfg.program_address, &provider).await?;
+
+    let size = view(
+        *ARB_WASM_H160,
+        ArbWasm::codehashAsmSizeCall { codehash },
+        &provider,
+    )
+    .await
+    .wrap_err("failed to get the program's size, is it activated?")?
+    .size;
+    let cached = view(
+        *ARB_WASM_CACHE_H160,
+        ArbWasmCache::codehashIsCachedCall { codehash },
+        &provider,
+    )
+    .await?
+    ._0;
+    let decay = view(cache_manager, CacheManagerParams::decayCall {}, &provider)
+        .await?
+        ._0;
+    let decay = U256::from(decay);
+    let min_bid = view(
+        cache_manager,
+        CacheManager::getMinBidCall { codehash },
+        &provider,
+    )
+    .await?
+    .min;
+    let min_bid = U256::from_little_endian(&min_bid.as_le_bytes());
+    let cache_size = view(cache_manager, CacheManager::cacheSizeCall {}, &provider)
+        .await?
+        ._0;
+    let queue_size = view(cache_manager, CacheManager::queueSizeCall {}, &provider)
+        .await?
+        ._0;
+
+    match cached {
+        true => println!("cached: {}", "yes".mint()),
+        false => println!("cached: {}", "no".pink()),
+    }
+    // The CacheManager only keeps bids in its list of entries, whose size grows with the
+    // cache, so only read it when asked.
+    if cfg.list_entries {
+        let entries = view(cache_manager, CacheManager::getEntriesCall {}, &provider)
+            .await?
+            ._0;
+        let now = provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .wrap_err("failed to get latest block")?
+            .map(|block| block.timestamp)
+            .unwrap_or_default();
+        // Bids are stored with the decay up to when they were placed added on, so what one is
+        // worth now is what's left after subtracting the decay up to now.
+        let worth = |entry: &CacheManager::Entry| {
+            let stored = U256::from_little_endian(&entry.bid.as_le_bytes());
+            (stored.saturating_sub(decay * now), stored)
+        };
+        println!("entries: {}", entries.len());
+        for entry in &entries {
+            let (bid, _) = worth(entry);
+            let ours = match entry.code == codehash {
+                true => " (this program)".mint().to_string(),
+                false => String::new(),
+            };
+            println!("  {} {} bytes, {bid} wei{ours}", entry.code, entry.size);
+        }
+        match entries.iter().find(|entry| entry.code == codehash) {
+            Some(entry) => {
+                let (bid, stored) = worth(entry);
+                println!("bid: {bid} wei after decay ({stored} wei as stored)");
+            }
+            None if cached => println!("bid: none, so it wasn't cached through this CacheManager"),
+            None => {}
+        }
+    } else if cached {
+        println!("bid: pass --list-entries to look it up in the CacheManager's entries");
+    }
+    println!("size: {size} bytes");
+    println!("minimum bid to cache this program: {min_bid} wei");
+    println!("decay rate: {decay} wei/s");
+    println!("cache usage: {queue_size} of {cache_size} bytes");
+    // Older CacheManagers may lack the getter, so leave it out rather than fail.
+    if let Ok(paused) = view(cache_manager, CacheManager::isPausedCall {}, &provider).await {
+        println!("bids paused: {}", paused._0);
+    }
+    Ok(())
+}
+
+/// The CacheManager's current minimum bid for the program, given by its address and codehash,
+/// raised by `buffer_percent` to stay ahead of competing bids. It's queried through the
+/// `--cache-manager-abi` override when there is one. Warns and returns `None` if it couldn't
+/// be queried.
+async fn auto_bid(
+    cache_manager: H160,
+    (program, codehash): (H160, FixedBytes<32>),
+    abi: Option<&JsonAbi>,
+    buffer_percent: Option<u64>,
+    provider: &Provider<RpcClient>,
+) -> Result<Option<U256>> {
+    let min_bid = match abi {
+        Some(abi) => override_min_bid(cache_manager, program, codehash, abi, provider).await,
+        None => {
+            let call = CacheManager::getMinBidCall { codehash };
+            let min_bid = view(cache_manager, call, provider).await;
+            min_bid.map(|min_bid| U256::from_little_endian(&min_bid.min.as_le_bytes()))
+        }
+    };
+    let min_bid = match min_bid {
+        Ok(min_bid) => min_bid,
+        Err(error) => {
+            warnln!(
+                MinBidUnknown,
+                "could not query the minimum bid, sending a zero bid: {error}"
+            )?;
+            return Ok(None);
+        }
+    };
+    let buffer_percent = buffer_percent.unwrap_or_default();
+    let bid = min_bid + min_bid * buffer_percent / 100;
+    greyln!(
+        "minimum bid is {} wei, bidding it plus {buffer_percent}%",
+        min_bid.debug_mint()
+    );
+    Ok(Some(bid))
+}
+
+/// Fails if paying for the bid tx, including its gas, would leave the account's balance below
+/// `min_balance`, so that unattended bidders can't drain the account. Gas is priced at the
+/// tx's max fee, or the node's estimate of it when the tx leaves fees to the node.
+async fn check_min_balance(
+    tx: &Eip1559TransactionRequest,
+    min_balance: U256,
+    client: &SignerClient,
+) -> Result<()> {
+    let balance = client
+        .get_balance(client.address(), None)
+        .await
+        .wrap_err("failed to get balance")?;
+    let typed = tx.clone().from(client.address()).into();
+    let gas = client
+        .estimate_gas(&typed, None)
+        .await
+        .wrap_err("failed to estimate bid gas")?;
+    let gas_price = match tx.max_fee_per_gas {
+        Some(max_fee) => max_fee,
+        None => {
+            let (max_fee, _) = client
+                .estimate_eip1559_fees(None)
+                .await
+                .wrap_err("failed to estimate fees")?;
+            max_fee
+        }
+    };
+    let gas_cost = gas.saturating_mul(gas_price);
+    let cost = tx.value.unwrap_or_default().saturating_add(gas_cost);
+    if balance < cost.saturating_add(min_balance) {
+        bail!(
+            "the bid and its gas would cost up to {cost} wei, leaving the balance of {} wei \
+             below the minimum of {min_balance} wei",
+            balance.debug_red()
+        );
+    }
+    Ok(())
+}
+
+/// Shows the current minimum bid, how ours compares, and which entries it would evict.
+async fn preview_bid(
+    cache_manager: H160,
+    codehash: FixedBytes<32>,
+    bid: U256,
+    provider: &Provider<RpcClient>,
+) -> Result<()> {
+    let min_bid = view(
+        cache_manager,
+        CacheManager::getMinBidCall { codehash },
+        provider,
+    )
+    .await?
+    .min;
+    let min_bid = U256::from_little_endian(&min_bid.as_le_bytes());
+    greyln!("minimum bid: {} wei", min_bid.debug_mint());
+    greyln!("our bid: {} wei", bid.debug_mint());
+    if bid < min_bid {
+        greyln!("{}", "bid is below the minimum and would be rejected".red());
+        return Ok(());
+    }
+
+    let asm_size = view(
+        *ARB_WASM_H160,
+        ArbWasm::codehashAsmSizeCall { codehash },
+        provider,
+    )
+    .await?
+    .size;
+    let cache_size = view(cache_manager, CacheManager::cacheSizeCall {}, provider)
+        .await?
+        ._0;
+    let queue_size = view(cache_manager, CacheManager::queueSizeCall {}, provider)
+        .await?
+        ._0;
+
+    let needed = (queue_size + u64::from(asm_size)).saturating_sub(cache_size);
+    if needed == 0 {
+        greyln!("bid would succeed without evicting any program");
+        return Ok(());
+    }
+    let k = alloy_primitives::U256::from(MAX_PREVIEWED_EVICTIONS);
+    let entries = view(
+        cache_manager,
+        CacheManager::getSmallestEntriesCall { k },
+        provider,
+    )
+    .await?
+    .result;
+
+    greyln!("bid would succeed, evicting:");
+    let mut freed = 0;
+    for entry in &entries {
+        if freed >= needed {
+            break;
+        }
+        freed += entry.size;
+        greyln!(
+            "  {} ({} bytes, bid {})",
+            hex::encode(entry.code).debug_lavender(),
+            entry.size,
+            entry.bid
+        );
+    }
+    if freed < needed {
+        greyln!("  and more");
+    }
+    Ok(())
+}
+
+/// Calls a view function, decoding its return value.
+async fn view<C: SolCall>(to: H160, call: C, provider: &Provider<RpcClient>) -> Result<C::Return> {
+    let tx = Eip1559TransactionRequest::new()
+        .to(to)
+        .data(call.abi_encode());
+    match eth_call(tx, State::default(), provider).await? {
+        Ok(outs) => Ok(C::abi_decode_returns(&outs, true)?),
+        Err(EthCallError { msg, .. }) => bail!("{} reverted: {msg}", C::SIGNATURE),
+    }
+}
+
+/// Queries the program's minimum bid with the `getMinBid` of a CacheManager ABI override.
+async fn override_min_bid(
+    cache_manager: H160,
+    program: H160,
+    codehash: FixedBytes<32>,
+    abi: &JsonAbi,
+    provider: &Provider<RpcClient>,
+) -> Result<U256> {
+    let function = find_function(abi, "getMinBid")?;
+    let signature = function.signature();
+    if !matches!(function.outputs.as_slice(), [min] if min.ty.starts_with("uint")) {
+        bail!("{signature} doesn't return a single uint");
+    }
+    let tx = Eip1559TransactionRequest::new()
+        .to(cache_manager)
+        .data(encode_call(function, codehash, program));
+    match eth_call(tx, State::default(), provider).await? {
+        Ok(outs) if outs.len() >= 32 => Ok(U256::from_big_endian(&outs[..32])),
+        Ok(_) => bail!("{signature} returned too little data"),
+        Err(EthCallError { msg, .. }) => bail!("{signature} reverted: {msg}"),
+    }
+}
+
+/// Finds the overload of a function in a CacheManager ABI override whose parameters can all be
+/// filled in, which are those of type `bytes32` for the codehash and `address` for the program.
+fn find_function<'a>(abi: &'a JsonAbi, name: &str) -> Result<&'a Function> {
+    let supported = |f: &&Function| {
+        let mut types = f.inputs.iter().map(|param| param.ty.as_str());
+        types.all(|ty| ty == "bytes32" || ty == "address")
+    };
+    let mut overloads = abi.function(name).into_iter().flatten();
+    overloads.find(supported).ok_or_else(|| {
+        eyre!("CacheManager ABI has no {name} taking only a bytes32 codehash or a program address")
+    })
+}
+
+/// ABI-encodes a call found by [`find_function`], passing the codehash to each `bytes32`
+/// parameter and the program's address to each `address` one.
+fn encode_call(function: &Function, codehash: FixedBytes<32>, program: H160) -> Vec<u8> {
+    let mut data = function.selector().to_vec();
+    for param in &function.inputs {
+        match param.ty.as_str() {
+            "bytes32" => data.extend(codehash.as_slice()),
+            _ => data.extend(H256::from(program).as_bytes()),
+        }
+    }
+    data
+}
+
+/// Loads a CacheManager ABI, either a plain JSON ABI or an artifact with an `abi` field,
+/// checking that it has a `placeBid` that can be called.
+fn load_cache_manager_abi(path: &Path) -> Result<JsonAbi> {
+    let json = fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read CacheManager ABI {path:?}"))?;
+    let value: Value = serde_json::from_str(&json)
+        .wrap_err_with(|| format!("CacheManager ABI {path:?} is not JSON"))?;
+    let abi = value.get("abi").unwrap_or(&value);
+    let abi =
+        JsonAbi::deserialize(abi).wrap_err_with(|| format!("invalid CacheManager ABI {path:?}"))?;
+
+    find_function(&abi, "placeBid").wrap_err_with(|| format!("can't bid with {path:?}"))?;
+    Ok(abi)
+}
+
+fn has_function(abi: &JsonAbi, signature: &str) -> bool {
+    abi.functions().any(|f| f.signature() == signature)
+}
+
+/// Parses an ETH-denominated amount, such as `0.05`, into wei.
+pub fn parse_eth(amount: &str) -> Result<U256, String> {
+    if amount.starts_with('-') {
+        return Err(format!("ETH amount {amount} must not be negative"));
+    }
+    if let Some((_, decimals)) = amount.split_once('.') {
+        if decimals.len() > 18 {
+            return Err(format!("ETH amount {amount} is more precise than 1 wei"));
+        }
+    }
+    ethers::utils::parse_ether(amount).map_err(|e| format!("invalid ETH amount {amount}: {e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_override_call() -> Result<()> {
+        let abi: JsonAbi = serde_json::from_str(
+            r#"[
+                {"type": "function", "name": "placeBid", "stateMutability": "payable",
+                 "inputs": [{"name": "program", "type": "address"},
+                            {"name": "size", "type": "uint64"}], "outputs": []},
+                {"type": "function", "name": "placeBid", "stateMutability": "payable",
+                 "inputs": [{"name": "program", "type": "address"}], "outputs": []},
+                {"type": "function", "name": "getMinBid", "stateMutability": "view",
+                 "inputs": [{"name": "codehash", "type": "bytes32"}],
+                 "outputs": [{"name": "min", "type": "uint192"}]}
+            ]"#,
+        )?;
+        let codehash = FixedBytes::repeat_byte(0xab);
+        let program = H160::repeat_byte(0xcd);
+
+        let place_bid = find_function(&abi, "placeBid")?;
+        assert_eq!(place_bid.signature(), "placeBid(address)");
+        let data = encode_call(place_bid, codehash, program);
+        assert_eq!(data[..4], keccak256("placeBid(address)")[..4]);
+        assert_eq!(data[4..], *H256::from(program).as_bytes());
+
+        // Matching the built-in signature encodes exactly what the built-in bindings do.
+        let get_min_bid = find_function(&abi, "getMinBid")?;
+        let expected = CacheManager::getMinBidCall { codehash }.abi_encode();
+        assert_eq!(encode_call(get_min_bid, codehash, program), expected);
+
+        assert!(find_function(&abi, "cacheSize").is_err());
+        Ok(())
+    }
+}
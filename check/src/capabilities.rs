@@ -0,0 +1,55 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{schema, Opts};
+use clap::CommandFactory;
+use eyre::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Ways of loading the wallet that signs txs, as given to `AuthOpts`, besides a Ledger, which
+/// needs the `ledger` feature.
+const SIGNERS: &[&str] = &["private-key", "keystore", "mnemonic"];
+
+/// What the installed cargo stylus supports, as printed by `capabilities --json`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Version of cargo stylus.
+    pub version: String,
+    /// Subcommands of `cargo stylus`, including hidden ones.
+    pub subcommands: Vec<String>,
+    /// Ways of loading the wallet that signs txs.
+    pub signers: Vec<String>,
+    /// JSON outputs with a schema, as passed to `json-schema`.
+    pub json_outputs: Vec<String>,
+}
+
+/// Prints what this version supports, without touching the network.
+pub fn print_capabilities(json: bool) -> Result<()> {
+    let capabilities = Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        subcommands: Opts::command()
+            .get_subcommands()
+            .map(|command| command.get_name().to_string())
+            .collect(),
+        signers: SIGNERS
+            .iter()
+            .chain(cfg!(feature = "ledger").then_some(&"ledger"))
+            .map(|signer| signer.to_string())
+            .collect(),
+        json_outputs: schema::OUTPUTS
+            .iter()
+            .map(|output| output.to_string())
+            .collect(),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&capabilities)?);
+        return Ok(());
+    }
+    println!("version: {}", capabilities.version);
+    println!("subcommands: {}", capabilities.subcommands.join(", "));
+    println!("signers: {}", capabilities.signers.join(", "));
+    println!("json outputs: {}", capabilities.json_outputs.join(", "));
+    Ok(())
+}
@@ -3,16 +3,25 @@
 
 use crate::{
     check::ArbWasm::ArbWasmErrors,
-    constants::{ARB_WASM_H160, ONE_ETH},
+    constants::{
+        ChainPreset, ARB_WASM_H160, CHAIN_PRESETS, DEFAULT_ENDPOINT, MAX_PROGRAM_SIZE, ONE_ETH,
+    },
+    deploy,
     macros::*,
+    output,
     project::{self, BuildConfig},
-    CheckConfig,
+    size::SizeReport,
+    warnings, CheckConfig, CommonConfig,
 };
 use alloy_primitives::{Address, B256, U256};
 use alloy_sol_macro::sol;
-use alloy_sol_types::{SolCall, SolInterface};
+use alloy_sol_types::{Revert, SolCall, SolError, SolInterface};
 use bytesize::ByteSize;
-use cargo_stylus_util::{color::Color, sys, text};
+use cargo_stylus_util::{
+    color::Color,
+    rpc::{RpcClient, RpcPolicy},
+    sys, text,
+};
 use ethers::{
     core::types::spoof,
     prelude::*,
@@ -21,7 +30,7 @@ use ethers::{
 };
 use eyre::{bail, eyre, ErrReport, Result, WrapErr};
 use serde_json::Value;
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 sol! {
     interface ArbWasm {
@@ -34,6 +43,8 @@ sol! {
 
         function codehashVersion(bytes32 codehash) external view returns (uint16 version);
 
+        function maxCodeSize() external view returns (uint32 size);
+
         error ProgramNotWasm();
         error ProgramNotActivated();
         error ProgramNeedsUpgrade(uint16 version, uint16 stylusVersion);
@@ -47,13 +58,32 @@ sol! {
 /// Checks that a program is valid and can be deployed onchain.
 /// Returns whether the WASM is already up-to-date and activated onchain, and the data fee.
 pub async fn check(cfg: &CheckConfig) -> Result<ProgramCheck> {
-    if cfg.common_cfg.endpoint == "https://stylus-testnet.arbitrum.io/rpc" {
+    check_program(cfg, true).await
+}
+
+/// Like [`check`], but without simulating the activation unless `simulate_activation` is set.
+/// A program that isn't active yet is then reported ready with a zero data fee.
+pub async fn check_program(cfg: &CheckConfig, simulate_activation: bool) -> Result<ProgramCheck> {
+    if cfg.common_cfg.endpoint() == "https://stylus-testnet.arbitrum.io/rpc" {
         let version = "cargo stylus version 0.2.1".to_string().red();
         bail!("The old Stylus testnet is no longer supported.\nPlease downgrade to {version}",);
     }
 
+    if cfg.size_report_json {
+        output::diagnostics_to_stderr();
+    }
     let verbose = cfg.common_cfg.verbose;
     let (wasm, project_hash) = cfg.build_wasm().wrap_err("failed to build wasm")?;
+    if let Some(expected) = cfg.expect_hash {
+        if project_hash != expected {
+            bail!(
+                "project hash mismatch\nexpected {}\ncomputed {}",
+                hex::encode(expected).mint(),
+                hex::encode(project_hash).red()
+            );
+        }
+        greyln!("project hash matches {}", hex::encode(expected).mint());
+    }
 
     if verbose {
         greyln!("reading wasm file at {}", wasm.to_string_lossy().lavender());
@@ -65,32 +95,145 @@ pub async fn check(cfg: &CheckConfig) -> Result<ProgramCheck> {
     // ignored by WASM runtimes, so it will only exist in the file
     // for metadata purposes.
     // add_project_hash_to_wasm_file(wasm, project_hash)
-    let (wasm_file_bytes, code) =
-        project::compress_wasm(&wasm, project_hash).wrap_err("failed to compress WASM")?;
+    let (wasm_file_bytes, code) = project::compress_wasm(
+        &wasm,
+        project_hash,
+        cfg.common_cfg.contract_version.as_ref(),
+    )
+    .wrap_err("failed to compress WASM")?;
 
-    greyln!("contract size: {}", format_file_size(code.len(), 16, 24));
+    // Neither of these needs the chain, so a broken program fails without an RPC.
+    project::check_structure(&wasm_file_bytes, &cfg.entrypoint_symbol)
+        .wrap_err("wasm is not a valid Stylus program")?;
+    let init_code_len = deploy::program_deployment_calldata(&code).len();
+    if init_code_len > cfg.max_init_code_size {
+        bail!(
+            "deployment init code is {} bytes, which exceeds the init code size limit of {} bytes",
+            init_code_len,
+            cfg.max_init_code_size
+        );
+    }
+
+    if verbose {
+        greyln!(
+            "connecting to RPC: {}",
+            cfg.common_cfg.endpoint().lavender()
+        );
+    }
+    let provider = cfg.common_cfg.provider()?;
+
+    let (limit, source) = match max_program_size(&provider).await {
+        Some(limit) => (limit, "reported by the chain"),
+        None => (MAX_PROGRAM_SIZE, "default"),
+    };
+    let (mid, max) = (limit * 2 / 3 / 1024, limit / 1024);
+    greyln!(
+        "contract size: {} (limit {}, {source})",
+        format_file_size(code.len(), mid, max),
+        ByteSize::b(limit)
+    );
+    if code.len() as u64 > ByteSize::kib(mid).as_u64() {
+        warnln!(
+            ProgramSize,
+            "contract size {} is approaching the {} limit",
+            ByteSize::b(code.len() as u64),
+            ByteSize::b(limit)
+        )?;
+    }
+
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?
+        .as_u64();
+    let features = cfg
+        .allow_wasm_feature
+        .iter()
+        .fold(project::chain_wasm_features(chain_id), |all, feature| {
+            all | *feature
+        });
+    project::check_wasm_features(&wasm_file_bytes, features)?;
+
+    if cfg.dump_wasm_sections {
+        greyln!("wasm sections:");
+        for (name, size) in project::wasm_section_sizes(&wasm_file_bytes)? {
+            greyln!("  {name}: {}", ByteSize::b(size as u64));
+        }
+    }
+    if cfg.size_report || cfg.size_report_json {
+        let report = SizeReport::new(&wasm_file_bytes, &code, limit, cfg.size_margin_percent)?;
+        report.print(cfg.size_report_json)?;
+    }
 
     if verbose {
         greyln!(
             "wasm size: {}",
             format_file_size(wasm_file_bytes.len(), 96, 128)
         );
-        greyln!("connecting to RPC: {}", &cfg.common_cfg.endpoint.lavender());
+    }
+
+    if let Some(address) = cfg.compare_with {
+        compare_with_deployed(address, &wasm_file_bytes, &code, &provider).await?;
     }
 
     // check if the program already exists
-    let provider = sys::new_provider(&cfg.common_cfg.endpoint)?;
     let codehash = alloy_primitives::keccak256(&code);
 
-    if program_exists(codehash, &provider).await? {
-        return Ok(ProgramCheck::Active { code });
-    }
+    let program = if program_exists(codehash, &provider).await? {
+        ProgramCheck::Active { code }
+    } else if !simulate_activation {
+        greyln!("wasm data fee: {}", "not simulated".yellow());
+        ProgramCheck::Ready {
+            code,
+            fee: U256::ZERO,
+        }
+    } else {
+        let address = cfg.program_address.unwrap_or(H160::random());
+        let fee = check_activate(code.clone().into(), address, &provider).await?;
+        let visual_fee = format_data_fee(fee).unwrap_or("???".red());
+        greyln!("wasm data fee: {visual_fee}");
+        ProgramCheck::Ready { code, fee }
+    };
+
+    warnings::enforce(cfg.fail_on_warnings)?;
+    Ok(program)
+}
 
-    let address = cfg.program_address.unwrap_or(H160::random());
-    let fee = check_activate(code.clone().into(), address, &provider).await?;
-    let visual_fee = format_data_fee(fee).unwrap_or("???".red());
-    greyln!("wasm data fee: {visual_fee}");
-    Ok(ProgramCheck::Ready { code, fee })
+/// Reports whether the locally built program is identical to the one deployed at an address,
+/// so that pointless redeployments can be skipped.
+async fn compare_with_deployed(
+    address: H160,
+    wasm: &[u8],
+    code: &[u8],
+    provider: &Provider<RpcClient>,
+) -> Result<()> {
+    let deployed = provider
+        .get_code(address, None)
+        .await
+        .wrap_err("failed to fetch deployed code")?;
+    if deployed.is_empty() {
+        bail!("no code at address {}", address.red());
+    }
+    // Different brotli builds may compress the same WASM differently, so compare the WASMs.
+    let identical = deployed.as_ref() == code
+        || project::decompress_code(&deployed)
+            .wrap_err_with(|| format!("program at {address:?} is not a Stylus program"))?
+            == wasm;
+    match identical {
+        true => greyln!(
+            "local build is {} to the program at {}, consider skipping the deploy",
+            "identical".mint(),
+            address.lavender()
+        ),
+        false => greyln!(
+            "local build {} from the program at {} ({} locally, {} onchain)",
+            "differs".yellow(),
+            address.lavender(),
+            ByteSize::b(code.len() as u64),
+            ByteSize::b(deployed.len() as u64)
+        ),
+    }
+    Ok(())
 }
 
 /// Whether a program is active, or needs activation.
@@ -117,12 +260,99 @@ impl ProgramCheck {
     }
 }
 
+impl CommonConfig {
+    /// Network behavior for RPC requests, as configured on the command line.
+    pub fn rpc_policy(&self) -> RpcPolicy {
+        RpcPolicy {
+            timeout: Duration::from_secs(self.rpc_timeout_secs),
+            retries: self.rpc_retries,
+            retry_delay: Duration::from_millis(self.rpc_retry_delay_ms),
+            fallback_endpoints: self.fallback_endpoint.clone(),
+            log_requests: self.verbose_rpc,
+            headers: self.rpc_header.clone(),
+        }
+    }
+
+    /// The RPC endpoint to use: `--endpoint`, else that of `--chain`, else Arbitrum Sepolia.
+    pub fn endpoint(&self) -> &str {
+        match (&self.endpoint, self.chain) {
+            (Some(endpoint), _) => endpoint,
+            (None, Some(chain)) => chain.endpoint,
+            (None, None) => DEFAULT_ENDPOINT,
+        }
+    }
+
+    /// Connects to the configured RPC endpoint.
+    pub fn provider(&self) -> Result<Provider<RpcClient>> {
+        sys::new_provider(self.endpoint(), &self.rpc_policy())
+    }
+
+    /// Warns if the endpoint is on a different chain than `--chain` names.
+    pub fn check_chain(&self, chain_id: u64) -> Result<()> {
+        let Some(chain) = self.chain else {
+            return Ok(());
+        };
+        if chain.chain_id != chain_id {
+            warnln!(
+                ChainMismatch,
+                "--chain {} expects chain ID {}, but {} is on chain {chain_id}",
+                chain.name,
+                chain.chain_id,
+                self.endpoint()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Fails if the endpoint is on a mainnet that `--chain` doesn't name, so that real funds
+    /// are only spent when asked for explicitly.
+    pub fn check_mainnet(&self, chain_id: u64) -> Result<()> {
+        let mainnet = CHAIN_PRESETS
+            .iter()
+            .find(|chain| chain.mainnet && chain.chain_id == chain_id);
+        let Some(mainnet) = mainnet else {
+            return Ok(());
+        };
+        if self.chain.map(|chain| chain.chain_id) != Some(chain_id) {
+            bail!(
+                "{} is on {}, pass --chain {} to deploy to mainnet",
+                self.endpoint(),
+                mainnet.name,
+                mainnet.name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parses the name of a chain in [`CHAIN_PRESETS`].
+pub fn parse_chain(name: &str) -> Result<&'static ChainPreset, String> {
+    CHAIN_PRESETS
+        .iter()
+        .find(|chain| chain.name == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = CHAIN_PRESETS.iter().map(|chain| chain.name).collect();
+            format!(
+                "unknown chain {name}, expected one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
 impl CheckConfig {
     fn build_wasm(&self) -> Result<(PathBuf, [u8; 32])> {
         if let Some(wasm) = self.wasm_file.clone() {
             return Ok((wasm, [0u8; 32]));
         }
-        let cfg = BuildConfig::new(self.common_cfg.rust_stable);
+        let cfg = BuildConfig {
+            allow_non_stylus: self.common_cfg.allow_non_stylus,
+            normalize_manifest_paths: self.common_cfg.normalize_manifest_paths,
+            no_ignore: self.common_cfg.no_ignore,
+            rustc_wrapper: self.common_cfg.rustc_wrapper.clone(),
+            cargo_args: self.common_cfg.cargo_arg.clone(),
+            invocation_file: self.common_cfg.build_invocation.clone(),
+            ..BuildConfig::new(self.common_cfg.rust_stable, self.common_cfg.package.clone())
+        };
         let wasm = project::build_dylib(cfg.clone())?;
         let project_hash =
             project::hash_files(self.common_cfg.source_files_for_project_hash.clone(), cfg)?;
@@ -173,7 +403,7 @@ impl From<EthCallError> for ErrReport {
 pub async fn eth_call(
     tx: Eip1559TransactionRequest,
     mut state: State,
-    provider: &Provider<Http>,
+    provider: &Provider<RpcClient>,
 ) -> Result<Result<Vec<u8>, EthCallError>> {
     let tx = TypedTransaction::Eip1559(tx);
     state.account(Default::default()).balance = Some(ethers::types::U256::MAX); // infinite balance
@@ -197,8 +427,34 @@ pub async fn eth_call(
     }
 }
 
+/// Explains a revert from ArbWasm, if its data is one of ArbWasm's errors or a revert string.
+pub fn describe_arbwasm_error(data: &[u8]) -> Option<String> {
+    if let Ok(revert) = Revert::abi_decode(data, true) {
+        return Some(revert.reason);
+    }
+    use ArbWasmErrors as A;
+    Some(match ArbWasmErrors::abi_decode(data, true).ok()? {
+        A::ProgramNotWasm(_) => "ProgramNotWasm: the code isn't a Stylus program".into(),
+        A::ProgramNotActivated(_) => "ProgramNotActivated".into(),
+        A::ProgramNeedsUpgrade(e) => format!(
+            "ProgramNeedsUpgrade: the program is at Stylus version {} but the chain is at {}",
+            e.version, e.stylusVersion
+        ),
+        A::ProgramExpired(e) => format!("ProgramExpired {} seconds ago", e.ageInSeconds),
+        A::ProgramUpToDate(_) => "ProgramUpToDate: the program is already activated".into(),
+        A::ProgramKeepaliveTooSoon(e) => format!(
+            "ProgramKeepaliveTooSoon: the program was kept alive {} seconds ago",
+            e.ageInSeconds
+        ),
+        A::ProgramInsufficientValue(e) => format!(
+            "ProgramInsufficientValue: sent {} wei but the data fee is {} wei",
+            e.have, e.want
+        ),
+    })
+}
+
 /// Checks whether a program has already been activated with the most recent version of Stylus.
-async fn program_exists(codehash: B256, provider: &Provider<Http>) -> Result<bool> {
+pub async fn program_exists(codehash: B256, provider: &Provider<RpcClient>) -> Result<bool> {
     let data = ArbWasm::codehashVersionCall { codehash }.abi_encode();
     let tx = Eip1559TransactionRequest::new()
         .to(*ARB_WASM_H160)
@@ -237,8 +493,25 @@ async fn program_exists(codehash: B256, provider: &Provider<Http>) -> Result<boo
     Ok(program_version == version)
 }
 
+/// Asks ArbWasm for the chain's maximum compressed program size, which chain owners can raise.
+/// Returns `None` if the chain's ArbOS predates the query.
+async fn max_program_size(provider: &Provider<RpcClient>) -> Option<u64> {
+    let data = ArbWasm::maxCodeSizeCall {}.abi_encode();
+    let tx = Eip1559TransactionRequest::new()
+        .to(*ARB_WASM_H160)
+        .data(data);
+    let outs = eth_call(tx, State::default(), provider).await.ok()?.ok()?;
+    let ArbWasm::maxCodeSizeReturn { size } =
+        ArbWasm::maxCodeSizeCall::abi_decode_returns(&outs, true).ok()?;
+    (size > 0).then_some(size.into())
+}
+
 /// Checks program activation, returning the data fee.
-async fn check_activate(code: Bytes, address: H160, provider: &Provider<Http>) -> Result<U256> {
+pub async fn check_activate(
+    code: Bytes,
+    address: H160,
+    provider: &Provider<RpcClient>,
+) -> Result<U256> {
     let program = Address::from(address.to_fixed_bytes());
     let data = ArbWasm::activateProgramCall { program }.abi_encode();
     let tx = Eip1559TransactionRequest::new()
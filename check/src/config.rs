@@ -0,0 +1,258 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{check, AuthOpts, CommonConfig};
+use cargo_stylus_util::text;
+use ethers::types::{H160, U256};
+use eyre::{bail, eyre, Result, WrapErr};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Project settings file, found in the directory cargo stylus runs in or the nearest one above.
+pub const CONFIG_FILE_NAME: &str = "stylus.toml";
+
+/// The contents of `stylus.toml`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct StylusConfig {
+    /// Values for flags the command line leaves unset.
+    #[serde(default)]
+    pub defaults: Defaults,
+    /// Settings for each network, keyed by chain ID or by a name with a `chain-id` of its own.
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkConfig>,
+}
+
+/// Values for the flags shared by most commands, keyed by the flag's name. Fees are per network,
+/// so they're set in `networks` instead.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Defaults {
+    pub endpoint: Option<String>,
+    /// Name of a known chain, like `--chain`.
+    pub chain: Option<String>,
+    pub fallback_endpoint: Vec<String>,
+    /// Headers like `--rpc-header`, as "Name: Value".
+    pub rpc_header: Vec<String>,
+    pub gas_oracle_url: Option<String>,
+    pub nonce_file: Option<PathBuf>,
+    pub rustc_wrapper: Option<PathBuf>,
+    pub cargo_arg: Vec<String>,
+    pub source_files_for_project_hash: Vec<String>,
+    pub private_key_path: Option<PathBuf>,
+    pub keystore_path: Option<String>,
+    pub keystore_password_path: Option<PathBuf>,
+    pub mnemonic_path: Option<PathBuf>,
+}
+
+/// Defaults for one network, each overridden by its command line flag.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Chain the section applies to, when it's keyed by name.
+    pub chain_id: Option<u64>,
+    pub max_fee_per_gas_gwei: Option<u64>,
+    pub max_priority_fee_per_gas_gwei: Option<u64>,
+    /// Cache bid in wei, like `cache --bid`.
+    pub bid: Option<u64>,
+    /// Like `cache --bid-buffer-percent`.
+    pub bid_buffer_percent: Option<u64>,
+    /// Address of the chain's CacheManager, for chains where it isn't the usual one.
+    pub cache_manager: Option<H160>,
+}
+
+/// Where settings are read from, as chosen by `--config` and `--no-config`.
+enum Source {
+    Discover,
+    File(PathBuf),
+    Disabled,
+}
+
+lazy_static! {
+    static ref SOURCE: Mutex<Source> = Mutex::new(Source::Discover);
+}
+
+/// Reads settings from `path` instead of the nearest `stylus.toml`, or from nowhere if
+/// `disabled`.
+pub fn configure(path: Option<PathBuf>, disabled: bool) {
+    *SOURCE.lock().unwrap() = match (path, disabled) {
+        (_, true) => Source::Disabled,
+        (Some(path), false) => Source::File(path),
+        (None, false) => Source::Discover,
+    };
+}
+
+/// The nearest `stylus.toml` in the current directory or its ancestors.
+fn discover() -> Result<Option<PathBuf>> {
+    let dir = env::current_dir().wrap_err("failed to get current directory")?;
+    let path = dir
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file());
+    Ok(path)
+}
+
+impl StylusConfig {
+    /// Reads the settings file given by `--config`, or else the nearest `stylus.toml`, if
+    /// there is one.
+    pub fn load() -> Result<Self> {
+        let path = match &*SOURCE.lock().unwrap() {
+            Source::Disabled => return Ok(Self::default()),
+            Source::File(path) => {
+                if !path.is_file() {
+                    bail!("config file {path:?} not found");
+                }
+                path.clone()
+            }
+            Source::Discover => match discover()? {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        Self::read(&path)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {path:?}"))?;
+        toml::from_str(&text).wrap_err_with(|| {
+            format!(
+                "invalid {path:?}, whose values fill in flags missing from the command line and \
+                 override built-in defaults (pass --no-config to ignore it)"
+            )
+        })
+    }
+
+    /// The settings for a chain, or the defaults if no section applies to it.
+    pub fn network(&self, chain_id: u64) -> Result<NetworkConfig> {
+        let mut sections = self.networks.iter().filter(|(key, network)| {
+            network.chain_id == Some(chain_id) || **key == chain_id.to_string()
+        });
+        let Some((name, network)) = sections.next() else {
+            return Ok(NetworkConfig::default());
+        };
+        if let Some((other, _)) = sections.next() {
+            bail!("{CONFIG_FILE_NAME} networks {name} and {other} are both for chain {chain_id}");
+        }
+        Ok(network.clone())
+    }
+}
+
+/// Reads the settings `stylus.toml` has for a chain.
+pub fn network(chain_id: u64) -> Result<NetworkConfig> {
+    StylusConfig::load()?.network(chain_id)
+}
+
+impl Defaults {
+    /// Fills in the flags the command line left unset.
+    pub fn apply(&self, cfg: &mut CommonConfig) -> Result<()> {
+        if cfg.endpoint.is_none() {
+            cfg.endpoint.clone_from(&self.endpoint);
+        }
+        if let (None, Some(chain)) = (cfg.chain, &self.chain) {
+            let chain =
+                check::parse_chain(chain).map_err(|error| eyre!("{CONFIG_FILE_NAME}: {error}"))?;
+            cfg.chain = Some(chain);
+        }
+        if cfg.fallback_endpoint.is_empty() {
+            cfg.fallback_endpoint.clone_from(&self.fallback_endpoint);
+        }
+        if cfg.rpc_header.is_empty() {
+            cfg.rpc_header = self
+                .rpc_header
+                .iter()
+                .map(|header| text::parse_header(header))
+                .collect::<Result<_, _>>()
+                .map_err(|error| eyre!("{CONFIG_FILE_NAME}: {error}"))?;
+        }
+        if cfg.gas_oracle_url.is_none() {
+            cfg.gas_oracle_url.clone_from(&self.gas_oracle_url);
+        }
+        if cfg.nonce_file.is_none() {
+            cfg.nonce_file.clone_from(&self.nonce_file);
+        }
+        if cfg.rustc_wrapper.is_none() {
+            cfg.rustc_wrapper.clone_from(&self.rustc_wrapper);
+        }
+        if cfg.cargo_arg.is_empty() {
+            cfg.cargo_arg.clone_from(&self.cargo_arg);
+        }
+        if cfg.source_files_for_project_hash.is_empty() {
+            cfg.source_files_for_project_hash
+                .clone_from(&self.source_files_for_project_hash);
+        }
+        Ok(())
+    }
+
+    /// Fills in the wallet, unless the command line chose one, since sources can't be mixed.
+    pub fn apply_auth(&self, auth: &mut AuthOpts) {
+        let chosen = auth.private_key_path.is_some()
+            || auth.private_key.is_some()
+            || auth.keystore_path.is_some()
+            || auth.keystore_dir.is_some()
+            || auth.mnemonic_path.is_some()
+            || auth.ledger;
+        if chosen {
+            return;
+        }
+        auth.private_key_path.clone_from(&self.private_key_path);
+        auth.keystore_path.clone_from(&self.keystore_path);
+        auth.keystore_password_path
+            .clone_from(&self.keystore_password_path);
+        auth.mnemonic_path.clone_from(&self.mnemonic_path);
+    }
+}
+
+impl NetworkConfig {
+    /// Fills in the fees the command line left unset.
+    pub fn apply_fees(&self, cfg: &CommonConfig) -> CommonConfig {
+        let mut cfg = cfg.clone();
+        if cfg.max_fee_per_gas_gwei.is_none() && cfg.max_fee_per_gas_wei.is_none() {
+            cfg.max_fee_per_gas_gwei = self.max_fee_per_gas_gwei.map(U256::from);
+        }
+        if cfg.max_priority_fee_per_gas_gwei.is_none() {
+            cfg.max_priority_fee_per_gas_gwei = self.max_priority_fee_per_gas_gwei.map(U256::from);
+        }
+        cfg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_network_sections() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            "[defaults]\nendpoint = \"http://localhost:8547\"\nchain = \"arbitrum-one\"\n\n\
+             [networks.sepolia]\nchain-id = 421614\nbid-buffer-percent = 10\n\n\
+             [networks.42161]\nmax-fee-per-gas-gwei = 2\n\
+             cache-manager = \"0x0000000000000000000000000000000000000072\"\n",
+        )?;
+        let config = StylusConfig::read(&path)?;
+        let defaults = &config.defaults;
+        assert_eq!(defaults.endpoint.as_deref(), Some("http://localhost:8547"));
+        assert_eq!(defaults.chain.as_deref(), Some("arbitrum-one"));
+        assert_eq!(config.network(421614)?.bid_buffer_percent, Some(10));
+        let one = config.network(42161)?;
+        assert_eq!(one.max_fee_per_gas_gwei, Some(2));
+        assert_eq!(one.cache_manager, Some(H160::from_low_u64_be(0x72)));
+        assert!(config.network(1)?.bid.is_none());
+
+        fs::write(&path, "[networks.42161]\nbid = \"high\"\n")?;
+        assert!(StylusConfig::read(&path).is_err());
+        Ok(())
+    }
+}
@@ -4,6 +4,8 @@
 use alloy_primitives::{address, Address};
 use ethers::types::{H160, U256};
 use lazy_static::lazy_static;
+use std::time::Duration;
+use wasmparser::WasmFeatures;
 
 /// EOF prefix used in Stylus compressed WASMs on-chain
 pub const EOF_PREFIX_NO_DICT: &str = "EFF00000";
@@ -11,9 +13,15 @@ pub const EOF_PREFIX_NO_DICT: &str = "EFF00000";
 /// Maximum brotli compression level used for Stylus programs.
 pub const BROTLI_COMPRESSION_LEVEL: u32 = 11;
 
+/// Base 2 logarithm of the brotli window, which bounds the encoder's memory. This is brotli's
+/// default, so compressed programs are unchanged from those of earlier versions.
+pub const BROTLI_WINDOW_BITS: u32 = 22;
+
 lazy_static! {
     /// Address of the ArbWasm precompile.
     pub static ref ARB_WASM_H160: H160 = H160(*ARB_WASM_ADDRESS.0);
+    /// Address of the ArbWasmCache precompile.
+    pub static ref ARB_WASM_CACHE_H160: H160 = H160(*ARB_WASM_CACHE_ADDRESS.0);
     /// Address of the Stylus program cache manager.
     pub static ref CACHE_MANAGER_H160: H160 = H160(*CACHE_MANAGER_ADDRESS.0);
 }
@@ -21,9 +29,23 @@ lazy_static! {
 /// Address of the ArbWasm precompile.
 pub const ARB_WASM_ADDRESS: Address = address!("0000000000000000000000000000000000000071");
 
+/// Address of the ArbWasmCache precompile, which knows which programs are cached.
+pub const ARB_WASM_CACHE_ADDRESS: Address = address!("0000000000000000000000000000000000000072");
+
 /// Address of the Stylus program cache manager for Arbitrum chains.
 pub const CACHE_MANAGER_ADDRESS: Address = address!("d1bbd579988f394a26d6ec16e77b3fa8a5e8fcee");
 
+/// Deterministic deployment proxy that `deploy --salt` creates programs through by default. It
+/// has the same address on Arbitrum chains and most other EVM chains, and creates a contract
+/// with CREATE2 from calldata holding a 32-byte salt followed by the init code.
+pub const CREATE2_DEPLOYER: &str = "0x4e59b44847b379578588920ca78fbf26c0b4956c";
+
+/// What each version byte after the deployment prelude expects of the chain.
+pub const PRELUDE_VERSIONS: &[(u8, &str)] = &[(0, "Stylus 1 or later")];
+
+/// The ArbOS release that introduced each Stylus version.
+pub const STYLUS_VERSIONS: &[(u16, &str)] = &[(1, "ArbOS 31"), (2, "ArbOS 32")];
+
 /// Target for compiled WASM folder in a Rust project
 pub const RUST_TARGET: &str = "wasm32-unknown-unknown";
 
@@ -34,6 +56,114 @@ pub const GITHUB_TEMPLATE_REPO: &str = "https://github.com/OffchainLabs/stylus-h
 pub const GITHUB_TEMPLATE_REPO_MINIMAL: &str =
     "https://github.com/OffchainLabs/stylus-hello-world-minimal";
 
+/// Where a template's files come from.
+pub enum TemplateSource {
+    /// A repo to clone.
+    Repo(&'static str),
+    /// A `src/lib.rs` shipped with cargo stylus, around which the rest of the project is
+    /// generated.
+    Bundled(&'static str),
+}
+
+/// Templates `new` can create projects from, as their name, source and a short description.
+/// The first is the default.
+pub const TEMPLATES: &[(&str, TemplateSource, &str)] = &[
+    (
+        "hello-world",
+        TemplateSource::Repo(GITHUB_TEMPLATE_REPO),
+        "a counter program with tests and a Solidity interface",
+    ),
+    (
+        "minimal",
+        TemplateSource::Repo(GITHUB_TEMPLATE_REPO_MINIMAL),
+        "the smallest program with an entrypoint",
+    ),
+    (
+        "erc20",
+        TemplateSource::Bundled(include_str!("../templates/erc20/lib.rs")),
+        "a fungible token with an initial supply",
+    ),
+    (
+        "erc721",
+        TemplateSource::Bundled(include_str!("../templates/erc721/lib.rs")),
+        "a collection of non-fungible tokens with a minter",
+    ),
+];
+
+/// Name of the function Stylus programs export for the chain to call.
+pub const ENTRYPOINT_SYMBOL: &str = "user_entrypoint";
+
+/// Modules Stylus programs may import host functions from: the chain's hooks, and the debug
+/// console local dev nodes provide.
+pub const HOST_MODULES: &[&str] = &["vm_hooks", "console"];
+
+/// Maximum compressed size in bytes of a Stylus program, for chains that don't report their own.
+pub const MAX_PROGRAM_SIZE: u64 = 24 * 1024;
+
+/// Maximum size of EVM init code, as set by EIP-3860.
+pub const MAX_INIT_CODE_SIZE: usize = 2 * 24576;
+
+/// WASM features ArbOS validates Stylus programs against.
+pub const STYLUS_WASM_FEATURES: WasmFeatures = WasmFeatures::MUTABLE_GLOBAL
+    .union(WasmFeatures::FLOATS)
+    .union(WasmFeatures::SATURATING_FLOAT_TO_INT)
+    .union(WasmFeatures::SIGN_EXTENSION)
+    .union(WasmFeatures::MULTI_VALUE)
+    .union(WasmFeatures::BULK_MEMORY);
+
+/// A well-known chain that `--chain` can name instead of an endpoint.
+#[derive(Debug)]
+pub struct ChainPreset {
+    pub name: &'static str,
+    pub chain_id: u64,
+    /// Public RPC endpoint, used unless `--endpoint` is given.
+    pub endpoint: &'static str,
+    /// Whether the chain holds real funds, so that deploying to it must be asked for by name.
+    pub mainnet: bool,
+    /// WASM features the chain accepts in programs.
+    pub wasm_features: WasmFeatures,
+}
+
+/// Chains that `--chain` accepts.
+pub const CHAIN_PRESETS: &[ChainPreset] = &[
+    ChainPreset {
+        name: "arbitrum-one",
+        chain_id: 42161,
+        endpoint: "https://arb1.arbitrum.io/rpc",
+        mainnet: true,
+        wasm_features: STYLUS_WASM_FEATURES,
+    },
+    ChainPreset {
+        name: "arbitrum-nova",
+        chain_id: 42170,
+        endpoint: "https://nova.arbitrum.io/rpc",
+        mainnet: true,
+        wasm_features: STYLUS_WASM_FEATURES,
+    },
+    ChainPreset {
+        name: "arbitrum-sepolia",
+        chain_id: 421614,
+        endpoint: "https://sepolia-rollup.arbitrum.io/rpc",
+        mainnet: false,
+        wasm_features: STYLUS_WASM_FEATURES,
+    },
+];
+
+/// Endpoint used when neither `--endpoint` nor `--chain` is given.
+pub const DEFAULT_ENDPOINT: &str = "https://sepolia-rollup.arbitrum.io/rpc";
+
+/// Chain IDs of local Nitro dev nodes, the only chains watch mode will deploy to.
+pub const LOCAL_DEV_CHAIN_IDS: &[u64] = &[412346, 333333];
+
+/// How often watch mode polls the project's source files for changes.
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often to ask ArbWasm whether a just-activated program is reported active.
+pub const ACTIVATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default file recording the deployments broadcast from a project.
+pub const DEPLOYMENT_RECORD_FILE: &str = "stylus-deployments.json";
+
 /// One ether in wei.
 pub const ONE_ETH: U256 = U256([1000000000000000000, 0, 0, 0]);
 
@@ -41,5 +171,8 @@ pub const ONE_ETH: U256 = U256([1000000000000000000, 0, 0, 0]);
 /// to include a hash of the Rust project's source files for reproducible verification of builds.
 pub const PROJECT_HASH_SECTION_NAME: &str = "project_hash";
 
+/// Name of the custom wasm section holding the semantic version given by `--contract-version`.
+pub const CONTRACT_VERSION_SECTION_NAME: &str = "contract_version";
+
 /// Name of the toolchain file used to specify the Rust toolchain version for a project.
 pub const TOOLCHAIN_FILE_NAME: &str = "rust-toolchain.toml";
@@ -0,0 +1,30 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{project, ContractVersionConfig};
+use cargo_stylus_util::color::{Color, DebugColor};
+use ethers::providers::Middleware;
+use eyre::{bail, Result, WrapErr};
+
+/// Prints the contract version a deployed program was built with, as embedded by
+/// `--contract-version`. Costs a single `eth_getCode` request.
+pub async fn print_contract_version(cfg: &ContractVersionConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let code = provider
+        .get_code(cfg.program_address, None)
+        .await
+        .wrap_err("failed to fetch program code")?;
+    if code.is_empty() {
+        bail!("no code at address {}", cfg.program_address.debug_red());
+    }
+    let wasm = project::decompress_code(&code).wrap_err("program isn't a Stylus program")?;
+    let Some(version) = project::contract_version(&wasm)? else {
+        bail!(
+            "program {} has no embedded contract version, it wasn't deployed with \
+             --contract-version",
+            cfg.program_address.debug_red()
+        );
+    };
+    println!("{}", version.mint());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    constants::EOF_PREFIX_NO_DICT,
+    deploy::{
+        extract_compressed_wasm, extract_program_evm_deployment_prelude, parse_deployment_prelude,
+    },
+    macros::*,
+};
+use cargo_stylus_util::{color::Color, text};
+use eyre::{Result, WrapErr};
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+/// Decodes a locally produced deployment calldata blob without touching the network.
+pub fn decode_calldata(file: Option<PathBuf>, stdin: bool) -> Result<()> {
+    let hex = match file {
+        Some(file) if !stdin => fs::read_to_string(&file)
+            .wrap_err_with(|| format!("failed to read {}", file.to_string_lossy()))?,
+        _ => {
+            let mut hex = String::new();
+            io::stdin()
+                .read_to_string(&mut hex)
+                .wrap_err("failed to read stdin")?;
+            hex
+        }
+    };
+    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    let calldata = text::decode0x(hex).wrap_err("calldata is not valid hex")?;
+
+    let prelude = parse_deployment_prelude(&calldata)?;
+    let compressed = extract_compressed_wasm(&calldata);
+    let eof_prefix = hex::decode(EOF_PREFIX_NO_DICT).unwrap();
+
+    greyln!(
+        "prelude: {}",
+        hex::encode(extract_program_evm_deployment_prelude(&calldata)).lavender()
+    );
+    greyln!("code length: {}", prelude.code_len.mint());
+    greyln!("stylus version byte: {}", prelude.version.mint());
+    greyln!("compressed section length: {}", compressed.len().mint());
+    if prelude.code_len != compressed.len().into() {
+        greyln!(
+            "{}",
+            "code length in prelude does not match the compressed section".red()
+        );
+    }
+    if !compressed.starts_with(&eof_prefix) {
+        greyln!(
+            "{}",
+            format!("compressed section does not start with prefix {EOF_PREFIX_NO_DICT}").red()
+        );
+    }
+    Ok(())
+}
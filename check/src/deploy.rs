@@ -5,26 +5,50 @@
 
 use crate::{
     check::{self, ProgramCheck},
-    constants::ARB_WASM_H160,
+    config,
+    constants::{ACTIVATION_POLL_INTERVAL, ARB_WASM_H160, ENTRYPOINT_SYMBOL},
+    deployments::{self, Deployment},
+    gas_oracle,
+    hardhat::{self, HardhatDeployment},
     macros::*,
-    DeployConfig,
+    nonce::NonceFile,
+    offline, output, project,
+    summary::{self, DeploySummary, Summary},
+    verifiable,
+    wallet::{self, StylusSigner},
+    CommonConfig, DeployConfig, ProxyKind,
 };
-use alloy_primitives::{Address, U256 as AU256};
+use alloy_primitives::{keccak256, Address, U256 as AU256};
 use alloy_sol_macro::sol;
 use alloy_sol_types::SolCall;
 use cargo_stylus_util::{
     color::{Color, DebugColor},
+    rpc::RpcClient,
     sys,
 };
 use ethers::{
-    core::k256::ecdsa::SigningKey,
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        AbiParser,
+    },
     middleware::SignerMiddleware,
     prelude::*,
     providers::{Middleware, Provider},
     signers::Signer,
-    types::{transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, H160, U256, U64},
+    types::{
+        spoof::State, transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, H160,
+        U256, U64,
+    },
 };
 use eyre::{bail, eyre, Result, WrapErr};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
+    time::{Duration, Instant},
+};
 
 sol! {
     interface ArbWasm {
@@ -32,10 +56,12 @@ sol! {
             external
             payable
             returns (uint16 version, uint256 dataFee);
+
+        function programVersion(address program) external view returns (uint16 version);
     }
 }
 
-pub type SignerClient = SignerMiddleware<Provider<Http>, Wallet<SigningKey>>;
+pub type SignerClient = SignerMiddleware<Provider<RpcClient>, StylusSigner>;
 
 /// Deploys a stylus program, activating if needed.
 pub async fn deploy(cfg: DeployConfig) -> Result<()> {
@@ -48,14 +74,82 @@ pub async fn deploy(cfg: DeployConfig) -> Result<()> {
         };
     }
 
-    let program = run!(check::check(&cfg.check_config), "cargo stylus check failed");
+    if cfg.output_address_only {
+        output::diagnostics_to_stderr();
+    }
+    if let Some(serialized) = &cfg.serialized_tx {
+        return offline::broadcast_serialized(&cfg, serialized).await;
+    }
+
+    if cfg.check_config.entrypoint_symbol != ENTRYPOINT_SYMBOL {
+        bail!(
+            "can't deploy with --entrypoint-symbol, since the chain calls {}",
+            ENTRYPOINT_SYMBOL.red()
+        );
+    }
+
+    if cfg.require_verifiable {
+        let patterns = cfg
+            .check_config
+            .common_cfg
+            .source_files_for_project_hash
+            .clone();
+        let use_gitignore = !cfg.check_config.common_cfg.no_ignore;
+        let problems = verifiable::verifiability_problems(Path::new("."), patterns, use_gitignore)?;
+        if !problems.is_empty() {
+            bail!(
+                "refusing to deploy with --require-verifiable:\n  {}",
+                problems.join("\n  ")
+            );
+        }
+    }
+
+    let allowed_signer = check_allowed_deployer(&cfg).await?;
+
+    let constructor = match &cfg.constructor_signature {
+        Some(signature) => Some(constructor_calldata(signature, &cfg.constructor_args)?),
+        None => None,
+    };
+    let sends_txs = !cfg.estimate_gas && !cfg.dry_run;
+    if constructor.is_some() && sends_txs && !cfg.allow_separate_init_tx {
+        bail!(
+            "the initializer is sent in its own tx after deploy and activation, so anyone can \
+             call it first and take over the program; pass --allow-separate-init-tx to deploy \
+             anyway"
+        );
+    }
+
+    let simulate_activation = !cfg.no_activate_sim_on_estimate;
+    let program = run!(
+        check::check_program(&cfg.check_config, simulate_activation),
+        "cargo stylus check failed"
+    );
     let verbose = cfg.check_config.common_cfg.verbose;
+    let hardhat_abi = match &cfg.hardhat_deploy {
+        Some(_) => Some(hardhat::abi().wrap_err("failed to export ABI for hardhat-deploy")?),
+        None => None,
+    };
+
+    if let Some(path) = &cfg.dry_run_output {
+        if let ProgramCheck::Ready { .. } = &program {
+            greyln!("the program will need activating once deployed");
+        }
+        return offline::write_unsigned_deploy(&cfg, program.code(), path, allowed_signer).await;
+    }
 
-    let client = sys::new_provider(&cfg.check_config.common_cfg.endpoint)?;
+    let client = cfg.check_config.common_cfg.provider()?;
     let chain_id = run!(client.get_chainid(), "failed to get chain id");
+    cfg.check_config.common_cfg.check_chain(chain_id.as_u64())?;
+    if !cfg.estimate_gas {
+        cfg.check_config
+            .common_cfg
+            .check_mainnet(chain_id.as_u64())?;
+    }
 
-    let wallet = cfg.auth.wallet().wrap_err("failed to load wallet")?;
-    let wallet = wallet.with_chain_id(chain_id.as_u64());
+    let wallet = match allowed_signer {
+        Some(signer) => signer.with_chain_id(chain_id.as_u64()),
+        None => wallet::resolve_signer(&cfg.auth, chain_id.as_u64()).await?,
+    };
     let sender = wallet.address();
     let client = SignerMiddleware::new(client, wallet);
 
@@ -70,7 +164,7 @@ pub async fn deploy(cfg: DeployConfig) -> Result<()> {
         let balance = run!(client.get_balance(sender, None), "failed to get balance");
         let balance = alloy_ethers_typecast::ethers_u256_to_alloy(balance);
 
-        if balance < data_fee && !cfg.estimate_gas {
+        if balance < data_fee && !cfg.estimate_gas && !cfg.dry_run {
             bail!(
                 "not enough funds in account {} to pay for data fee\n\
                  balance {} < {}\n\
@@ -83,22 +177,130 @@ pub async fn deploy(cfg: DeployConfig) -> Result<()> {
         }
     }
 
-    let contract = cfg.deploy_contract(program.code(), sender, &client).await?;
+    if cfg.estimate_gas {
+        return cfg
+            .estimate_costs(&program, sender, data_fee, &client)
+            .await;
+    }
+    if cfg.dry_run {
+        return cfg
+            .dry_run(&program, sender, data_fee, constructor, &client)
+            .await;
+    }
+
+    let activation_fee = match (&program, &cfg.emit_activation_calldata) {
+        (ProgramCheck::Ready { .. }, None) => Some(data_fee),
+        _ => None,
+    };
+    let duplicate = match cfg.check_duplicates {
+        true => cfg.reuse_duplicate(program.code(), &client).await?,
+        false => None,
+    };
+    let (contract, tx_hash) = match (duplicate, cfg.salt) {
+        (Some(contract), _) => (contract, None),
+        (None, Some(salt)) => {
+            cfg.deploy_create2(program.code(), salt, sender, activation_fee, &client)
+                .await?
+        }
+        (None, None) => {
+            cfg.deploy_contract(program.code(), sender, activation_fee, &client)
+                .await?
+        }
+    };
 
-    match program {
-        ProgramCheck::Ready { .. } => cfg.activate(sender, contract, data_fee, &client).await?,
-        ProgramCheck::Active { .. } => greyln!("wasm already activated!"),
+    let (activated, activation_tx) = match program {
+        ProgramCheck::Ready { .. } => match &cfg.emit_activation_calldata {
+            Some(path) => {
+                write_activation_calldata(path, contract, data_fee)?;
+                (false, None)
+            }
+            None => (true, cfg.activate(contract, data_fee, &client).await?),
+        },
+        ProgramCheck::Active { .. } => {
+            greyln!("wasm already activated!");
+            (true, None)
+        }
+    };
+    if cfg.wait {
+        let codehash = keccak256(program.code());
+        cfg.wait_until_active(contract, codehash.0.into(), tx_hash, activation_tx, &client)
+            .await?;
+    }
+
+    let proxy = match cfg.proxy {
+        Some(ProxyKind::Minimal) => cfg.deploy_minimal_proxy(contract, sender, &client).await?,
+        None => None,
+    };
+    if let Some(data) = constructor {
+        cfg.construct(proxy.unwrap_or(contract), sender, data, &client)
+            .await?;
+    }
+    if let (Some(network), Some(abi), Some(tx_hash)) = (&cfg.hardhat_deploy, hardhat_abi, tx_hash) {
+        let name = match (
+            &cfg.hardhat_deploy_name,
+            &cfg.check_config.common_cfg.package,
+        ) {
+            (Some(name), _) | (None, Some(name)) => name.clone(),
+            (None, None) => project::package_name(Path::new("Cargo.toml"))?,
+        };
+        let chain_id = chain_id.as_u64();
+        let deployment = HardhatDeployment::new(
+            contract,
+            abi,
+            &cfg.constructor_args,
+            tx_hash,
+            program.code(),
+        );
+        hardhat::write_deployment(network, &name, chain_id, deployment)?;
+    }
+    if let Some(path) = &cfg.summary_json_append {
+        let summary = DeploySummary {
+            chain_id: chain_id.as_u64(),
+            contract_address: contract,
+            deployment_tx: tx_hash,
+            codehash: hex::encode(keccak256(program.code())),
+            activated,
+            proxy,
+        };
+        summary::append(path, Summary::Deploy(&summary))?;
+    }
+    if cfg.output_address_only {
+        println!("{}", ethers::utils::to_checksum(&contract, None));
     }
     Ok(())
 }
 
+/// Checks the deployer against `--allowed-deployers`, if given, before anything is built.
+///
+/// An exported tx is checked by its `--sender`, which needs no wallet. Otherwise the signer is
+/// resolved so that a wrong wallet fails fast, and kept so that a keystore or Ledger isn't
+/// unlocked twice.
+async fn check_allowed_deployer(cfg: &DeployConfig) -> Result<Option<StylusSigner>> {
+    let Some(path) = &cfg.allowed_deployers else {
+        return Ok(None);
+    };
+    if let Some(sender) = cfg.sender {
+        wallet::check_allowed_deployer(path, sender)?;
+        greyln!("sender {} is an allowed deployer", sender.debug_lavender());
+        return Ok(None);
+    }
+    let signer = wallet::resolve_signer(&cfg.auth, 0).await?;
+    wallet::check_allowed_deployer(path, signer.address())?;
+    greyln!(
+        "wallet {} is an allowed deployer",
+        signer.address().debug_lavender()
+    );
+    Ok(Some(signer))
+}
+
 impl DeployConfig {
     async fn deploy_contract(
         &self,
         code: &[u8],
         sender: H160,
+        activation_fee: Option<AU256>,
         client: &SignerClient,
-    ) -> Result<H160> {
+    ) -> Result<(H160, Option<H256>)> {
         let init_code = program_deployment_calldata(code);
 
         let tx = Eip1559TransactionRequest::new()
@@ -106,28 +308,66 @@ impl DeployConfig {
             .data(init_code);
 
         let verbose = self.check_config.common_cfg.verbose;
-        let gas = client
-            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
-            .await?;
+        let gas = match self.gas_limit {
+            Some(gas) => gas,
+            None => {
+                let gas = client
+                    .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+                    .await?;
+                if verbose {
+                    greyln!("deploy gas estimate: {}", format_gas(gas));
+                }
+                gas
+            }
+        };
 
-        if self.check_config.common_cfg.verbose || self.estimate_gas {
-            greyln!("deploy gas estimate: {}", format_gas(gas));
-        }
-        if self.estimate_gas {
-            let nonce = client.get_transaction_count(sender, None).await?;
-            return Ok(ethers::utils::get_contract_address(sender, nonce));
+        let common_cfg = &self.check_config.common_cfg;
+        // Held until the deploy tx is sent and recorded, so parallel deploys can't take the
+        // same nonce.
+        let mut nonces = match &common_cfg.nonce_file {
+            Some(path) => Some(NonceFile::lock(path)?),
+            None => None,
+        };
+        let nonce = match &nonces {
+            Some(nonces) => nonces.next_nonce(client).await?,
+            None => client
+                .get_transaction_count(sender, Some(BlockNumber::Pending.into()))
+                .await
+                .wrap_err("failed to get nonce")?,
+        };
+        if let Some(max_gas) = self.max_gas {
+            let contract = ethers::utils::get_contract_address(sender, nonce);
+            self.check_gas_budget(max_gas, gas, code, contract, activation_fee, client)
+                .await?;
         }
 
-        let receipt = run_tx(
-            "deploy",
-            tx,
-            Some(gas),
-            self.check_config.common_cfg.max_fee_per_gas_gwei,
-            client,
-            self.check_config.common_cfg.verbose,
-        )
-        .await?;
+        let record = &self.deployment_record;
+        let codehash = keccak256(code);
+        let (key, receipt) = match deployments::resume(record, codehash, nonce, client).await? {
+            Some(resumed) => resumed,
+            None => {
+                let key = deployments::idempotency_key(codehash, client, nonce);
+                self.check_base_fee(client).await?;
+                let tx = tx.nonce(nonce);
+                let tx_hash =
+                    send_tx_locked("deploy", tx, Some(gas), common_cfg, client, nonces.as_mut())
+                        .await?;
+                let deployment = Deployment {
+                    chain_id: client.signer().chain_id(),
+                    sender,
+                    nonce: nonce.as_u64(),
+                    tx_hash,
+                    contract_address: None,
+                    codehash: Some(H256::from(codehash.0)),
+                    git: deployments::git_metadata(record),
+                };
+                deployments::record_broadcast(record, &key, deployment)?;
+                drop(nonces);
+                (key, wait_for_tx("deploy", tx_hash, client).await?)
+            }
+        };
         let contract = receipt.contract_address.ok_or(eyre!("missing address"))?;
+        deployments::record_contract(record, &key, contract)?;
         let address = contract.debug_lavender();
 
         if verbose {
@@ -139,85 +379,1075 @@ impl DeployConfig {
         } else {
             greyln!("deployed code at address: {address}");
         }
-        let tx_hash = receipt.transaction_hash.debug_lavender();
-        greyln!("Deployment tx hash: {tx_hash}");
-        Ok(contract)
+        let tx_hash = receipt.transaction_hash;
+        greyln!("Deployment tx hash: {}", tx_hash.debug_lavender());
+        Ok((contract, Some(tx_hash)))
     }
 
-    async fn activate(
+    /// Deploys the program through the CREATE2 deployer, at an address predicted from the salt
+    /// and init code. Re-running is safe without a deployment record, since a deployment that
+    /// already landed leaves code at the predicted address, which is then reused.
+    async fn deploy_create2(
         &self,
+        code: &[u8],
+        salt: H256,
         sender: H160,
+        activation_fee: Option<AU256>,
+        client: &SignerClient,
+    ) -> Result<(H160, Option<H256>)> {
+        let deployer = self.create2_deployer;
+        let (contract, data) = create2_deployment(deployer, salt, code);
+        greyln!(
+            "predicted address: {} {} {}",
+            contract.debug_lavender(),
+            "with salt".grey(),
+            salt.debug_lavender()
+        );
+
+        let existing = client
+            .get_code(contract, None)
+            .await
+            .wrap_err("failed to get code at the predicted address")?;
+        if !existing.is_empty() {
+            greyln!("code already exists at the predicted address, skipping deployment");
+            return Ok((contract, None));
+        }
+        let deployer_code = client
+            .get_code(deployer, None)
+            .await
+            .wrap_err("failed to get the CREATE2 deployer's code")?;
+        if deployer_code.is_empty() {
+            bail!(
+                "no CREATE2 deployer at {}, pass the chain's with --create2-deployer",
+                deployer.debug_red()
+            );
+        }
+
+        let tx = Eip1559TransactionRequest::new()
+            .from(sender)
+            .to(deployer)
+            .data(data);
+        let verbose = self.check_config.common_cfg.verbose;
+        let gas = match self.gas_limit {
+            Some(gas) => gas,
+            None => {
+                let gas = client
+                    .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+                    .await?;
+                if verbose {
+                    greyln!("deploy gas estimate: {}", format_gas(gas));
+                }
+                gas
+            }
+        };
+        if let Some(max_gas) = self.max_gas {
+            self.check_gas_budget(max_gas, gas, code, contract, activation_fee, client)
+                .await?;
+        }
+
+        self.check_base_fee(client).await?;
+        let common_cfg = &self.check_config.common_cfg;
+        let receipt = run_tx("deploy", tx, Some(gas), common_cfg, client).await?;
+        let deployed = client
+            .get_code(contract, None)
+            .await
+            .wrap_err("failed to get code at the predicted address")?;
+        if deployed.is_empty() {
+            bail!(
+                "the CREATE2 deployer didn't create {}",
+                contract.debug_red()
+            );
+        }
+        greyln!("deployed code at address: {}", contract.debug_lavender());
+        let tx_hash = receipt.transaction_hash;
+        greyln!("Deployment tx hash: {}", tx_hash.debug_lavender());
+        Ok((contract, Some(tx_hash)))
+    }
+
+    /// Refuses to deploy if the deployment and activation would use more gas than `max_gas`,
+    /// unless `--force` is set.
+    async fn check_gas_budget(
+        &self,
+        max_gas: U256,
+        deploy_gas: U256,
+        code: &[u8],
+        contract: H160,
+        activation_fee: Option<AU256>,
+        client: &SignerClient,
+    ) -> Result<()> {
+        let activation_gas = match (activation_fee, self.activation_gas_limit) {
+            (None, _) => U256::zero(),
+            (Some(_), Some(gas)) => gas,
+            (Some(data_fee), None) => {
+                match estimate_activation_gas(code, contract, data_fee, client).await {
+                    Ok(gas) => gas,
+                    Err(error) if self.force => {
+                        warnln!(
+                            ActivationGasUnknown,
+                            "could not estimate activation gas for --max-gas: {error}"
+                        )?;
+                        U256::zero()
+                    }
+                    Err(error) => bail!(
+                        "could not estimate activation gas to compare against --max-gas: {error}\n\
+                         set --activation-gas-limit, or pass --force to deploy anyway"
+                    ),
+                }
+            }
+        };
+
+        let total = deploy_gas + activation_gas;
+        if total <= max_gas {
+            if self.check_config.common_cfg.verbose {
+                greyln!(
+                    "estimated gas {} is within --max-gas {max_gas}",
+                    format_gas(total)
+                );
+            }
+            return Ok(());
+        }
+        let msg = format!(
+            "estimated gas {total} (deploy {deploy_gas} + activation {activation_gas}) \
+             exceeds --max-gas {max_gas} by {}",
+            total - max_gas
+        );
+        if !self.force {
+            bail!("{msg}, pass --force to deploy anyway");
+        }
+        warnln!(MaxGasExceeded, "{msg}, deploying anyway due to --force")?;
+        Ok(())
+    }
+
+    /// Refuses to send a tx while the base fee is above `--max-acceptable-base-fee-gwei`,
+    /// unless `--force` is set.
+    async fn check_base_fee(&self, client: &SignerClient) -> Result<()> {
+        let Some(max_gwei) = self.max_acceptable_base_fee_gwei else {
+            return Ok(());
+        };
+        let max_base_fee = gwei_to_wei(max_gwei)?;
+        let block = client
+            .get_block(BlockNumber::Latest)
+            .await
+            .wrap_err("failed to get latest block")?
+            .ok_or(eyre!("no latest block"))?;
+        let Some(base_fee) = block.base_fee_per_gas else {
+            bail!("latest block has no base fee to compare against --max-acceptable-base-fee-gwei");
+        };
+        let gwei = ethers::utils::format_units(base_fee, "gwei")?;
+        greyln!("current base fee: {gwei} gwei");
+        if base_fee <= max_base_fee {
+            return Ok(());
+        }
+        let msg = format!("base fee {gwei} gwei exceeds --max-acceptable-base-fee-gwei {max_gwei}");
+        if !self.force {
+            bail!("{msg}, pass --force to send anyway");
+        }
+        warnln!(BaseFeeExceeded, "{msg}, sending anyway due to --force")?;
+        Ok(())
+    }
+
+    async fn activate(
+        &self,
         contract: H160,
         data_fee: AU256,
         client: &SignerClient,
+    ) -> Result<Option<H256>> {
+        self.check_base_fee(client).await?;
+        let tx_hash = send_activation(
+            &self.check_config.common_cfg,
+            contract,
+            data_fee,
+            self.activation_gas_limit,
+            false,
+            self.check_reverts_with_trace,
+            client,
+        )
+        .await?;
+        if self.poll_for_activation_state {
+            let timeout = Duration::from_secs(self.activation_poll_timeout_secs);
+            poll_activation_state(contract, timeout, client.provider()).await?;
+        }
+        Ok(tx_hash)
+    }
+
+    /// Waits for the deployment and activation txs to be confirmed and for ArbWasm to report
+    /// the program active, printing the tx hashes to follow up on if it doesn't happen in time.
+    async fn wait_until_active(
+        &self,
+        contract: H160,
+        codehash: H256,
+        deploy_tx: Option<H256>,
+        activation_tx: Option<H256>,
+        client: &SignerClient,
     ) -> Result<()> {
-        let verbose = self.check_config.common_cfg.verbose;
-        let data_fee = alloy_ethers_typecast::alloy_u256_to_ethers(data_fee);
-        let program: Address = contract.to_fixed_bytes().into();
+        let timeout = Duration::from_secs(self.wait_timeout_secs);
+        let deadline = Instant::now() + timeout;
+        let provider = client.provider();
+        let result = async {
+            let mut block = None;
+            if let Some(tx_hash) = deploy_tx {
+                let receipt = self
+                    .confirmed("deployment", tx_hash, deadline, provider)
+                    .await?;
+                greyln!(
+                    "deployment confirmed in block {}",
+                    receipt.block_number.unwrap_or_default()
+                );
+                block = receipt.block_number;
+            }
+            let deployed = || match deploy_tx {
+                Some(_) => format!("deployed to {}, but activation", contract.debug_lavender()),
+                None => "activation".to_string(),
+            };
+            if let Some(tx_hash) = activation_tx {
+                let receipt = self
+                    .confirmed("activation", tx_hash, deadline, provider)
+                    .await
+                    .wrap_err_with(|| format!("program {} isn't confirmed", deployed()))?;
+                block = receipt.block_number;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            poll_activation_state(contract, remaining, provider)
+                .await
+                .wrap_err_with(|| format!("program {} is pending or failed", deployed()))?;
+            let block = match block {
+                Some(block) => block,
+                None => provider.get_block_number().await?,
+            };
+            Ok(block)
+        }
+        .await;
 
-        let data = ArbWasm::activateProgramCall { program }.abi_encode();
+        let block = match result {
+            Ok(block) => block,
+            Err(error) => {
+                for (name, tx_hash) in [("deployment", deploy_tx), ("activation", activation_tx)] {
+                    if let Some(tx_hash) = tx_hash {
+                        greyln!("{name} tx: {}", tx_hash.debug_lavender());
+                    }
+                }
+                return Err(error);
+            }
+        };
+        greyln!("program confirmed active onchain");
+        greyln!("  address: {}", contract.debug_lavender());
+        greyln!("  codehash: {}", codehash.debug_lavender());
+        greyln!("  block: {}", block.mint());
+        Ok(())
+    }
+
+    /// Polls for a tx's receipt until it's buried under `--confirmations` blocks, failing if it
+    /// reverted or the deadline passes first.
+    async fn confirmed(
+        &self,
+        name: &str,
+        tx_hash: H256,
+        deadline: Instant,
+        provider: &Provider<RpcClient>,
+    ) -> Result<TransactionReceipt> {
+        loop {
+            let receipt = provider
+                .get_transaction_receipt(tx_hash)
+                .await
+                .wrap_err_with(|| format!("failed to get {name} receipt"))?;
+            if let Some(receipt) = receipt {
+                if receipt.status != Some(U64::from(1)) {
+                    bail!("{name} tx {} reverted", tx_hash.debug_red());
+                }
+                let mined = receipt.block_number.unwrap_or_default().as_u64();
+                let head = provider.get_block_number().await?.as_u64();
+                let confirmations = (head + 1).saturating_sub(mined);
+                if confirmations >= self.confirmations {
+                    return Ok(receipt);
+                }
+                greyln!(
+                    "{name} tx has {confirmations} of {} confirmations",
+                    self.confirmations
+                );
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "timed out after {}s waiting for {} confirmations of the {name} tx",
+                    self.wait_timeout_secs,
+                    self.confirmations
+                );
+            }
+            tokio::time::sleep(ACTIVATION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Reports an identical deployment from the record on this chain, returning its address
+    /// if the user chooses to reuse it rather than deploy again.
+    async fn reuse_duplicate(&self, code: &[u8], client: &SignerClient) -> Result<Option<H160>> {
+        let record = &self.deployment_record;
+        let Some(existing) = deployments::find_duplicate(record, keccak256(code), client).await?
+        else {
+            return Ok(None);
+        };
+        let contract = existing.contract_address.unwrap_or_default();
+        greyln!(
+            "identical code was already deployed to {} by tx {}",
+            contract.debug_lavender(),
+            existing.tx_hash.debug_lavender()
+        );
+        if !io::stdin().is_terminal() {
+            greyln!("not asking whether to reuse it, since stdin isn't a terminal");
+            return Ok(None);
+        }
+        match sys::confirm("skip deploying and reuse it?")? {
+            true => Ok(Some(contract)),
+            false => Ok(None),
+        }
+    }
+
+    /// Prints the gas and cost of each tx a deploy would send, and their total, without
+    /// sending any. Activation is simulated against the undeployed code.
+    async fn estimate_costs(
+        &self,
+        program: &ProgramCheck,
+        sender: H160,
+        data_fee: AU256,
+        client: &SignerClient,
+    ) -> Result<()> {
+        let price = self.effective_gas_price(client).await?;
+        let cost = |gas: U256| format_eth(gas * price);
 
         let tx = Eip1559TransactionRequest::new()
             .from(sender)
-            .to(*ARB_WASM_H160)
-            .data(data)
-            .value(data_fee);
+            .data(program_deployment_calldata(program.code()));
+        let deploy_gas = client
+            .estimate_gas(&TypedTransaction::Eip1559(tx), None)
+            .await
+            .wrap_err("failed to estimate deploy gas")?;
+        greyln!(
+            "deploy gas estimate: {} ({})",
+            format_gas(deploy_gas),
+            cost(deploy_gas)
+        );
 
-        let gas = client
-            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+        let nonce = client.get_transaction_count(sender, None).await?;
+        let contract = ethers::utils::get_contract_address(sender, nonce);
+        let data_fee = alloy_ethers_typecast::alloy_u256_to_ethers(data_fee);
+        let activation_gas = match program {
+            ProgramCheck::Active { .. } => {
+                greyln!(
+                    "activation gas estimate: {}, the codehash is already activated",
+                    format_gas(U256::zero())
+                );
+                Some(U256::zero())
+            }
+            ProgramCheck::Ready { .. } if self.no_activate_sim_on_estimate => None,
+            ProgramCheck::Ready { code, .. } => {
+                let fee = alloy_ethers_typecast::ethers_u256_to_alloy(data_fee);
+                match estimate_activation_gas(code, contract, fee, client).await {
+                    Ok(gas) => Some(gas),
+                    Err(error) => {
+                        warnln!(
+                            ActivationGasUnknown,
+                            "could not estimate activation gas: {error}"
+                        )?;
+                        None
+                    }
+                }
+            }
+        };
+        match activation_gas {
+            Some(gas) if !gas.is_zero() => greyln!(
+                "activation gas estimate: {} ({} plus a {} data fee)",
+                format_gas(gas),
+                cost(gas),
+                format_eth(data_fee)
+            ),
+            Some(_) => {}
+            None => greyln!(
+                "activation gas estimate: {}",
+                "not estimated, so the total leaves it out".yellow()
+            ),
+        }
+        let mut total = deploy_gas + activation_gas.unwrap_or_default();
+
+        if self.proxy.is_some() {
+            let tx = Eip1559TransactionRequest::new()
+                .from(sender)
+                .data(minimal_proxy_deployment_calldata(contract));
+            let gas = client
+                .estimate_gas(&TypedTransaction::Eip1559(tx), None)
+                .await
+                .wrap_err("failed to estimate proxy deploy gas")?;
+            greyln!(
+                "proxy deploy gas estimate: {} ({})",
+                format_gas(gas),
+                cost(gas)
+            );
+            total += gas;
+        }
+        if self.constructor_signature.is_some() {
+            greyln!(
+                "constructor gas estimate: {}",
+                "not estimated, since the program isn't deployed yet".yellow()
+            );
+        }
+
+        let fee = match activation_gas {
+            Some(_) => data_fee,
+            None => U256::zero(),
+        };
+        greyln!(
+            "total gas estimate: {} ({} at {} gwei)",
+            format_gas(total),
+            format_eth(total * price + fee),
+            ethers::utils::format_units(price, "gwei")?
+        );
+        Ok(())
+    }
+
+    /// Prints every tx a deploy would send, in order, with its calldata, value and estimated gas,
+    /// stopping short of signing any of them.
+    async fn dry_run(
+        &self,
+        program: &ProgramCheck,
+        sender: H160,
+        data_fee: AU256,
+        constructor: Option<Vec<u8>>,
+        client: &SignerClient,
+    ) -> Result<()> {
+        let price = self.effective_gas_price(client).await?;
+        let cost = |gas: U256| format_eth(gas * price);
+        let estimate = |tx: &Eip1559TransactionRequest| {
+            let tx = TypedTransaction::Eip1559(tx.clone());
+            async move { client.estimate_gas(&tx, None).await }
+        };
+        let print_gas = |name: &str, gas: Option<U256>| match gas {
+            Some(gas) => greyln!("  {name}: {} ({})", format_gas(gas), cost(gas)),
+            None => greyln!("  {name}: {}", "not estimated".yellow()),
+        };
+        let code = program.code();
+        let data_fee = alloy_ethers_typecast::alloy_u256_to_ethers(data_fee);
+        let mut nonce = client.get_transaction_count(sender, None).await?;
+        greyln!("{}", "dry run, nothing will be signed or sent".yellow());
+        greyln!("signer: {}", sender.debug_lavender());
+        greyln!("nonce: {nonce}");
+
+        let (contract, tx) = match self.salt {
+            Some(salt) => {
+                let (contract, data) = create2_deployment(self.create2_deployer, salt, code);
+                greyln!(
+                    "deploy tx, through CREATE2 deployer {}:",
+                    self.create2_deployer.debug_lavender()
+                );
+                let tx = Eip1559TransactionRequest::new()
+                    .from(sender)
+                    .to(self.create2_deployer)
+                    .data(data);
+                (contract, tx)
+            }
+            None => {
+                greyln!("deploy tx, with CREATE:");
+                let contract = ethers::utils::get_contract_address(sender, nonce);
+                let tx = Eip1559TransactionRequest::new()
+                    .from(sender)
+                    .data(program_deployment_calldata(code));
+                (contract, tx)
+            }
+        };
+        greyln!("  predicted address: {}", contract.debug_lavender());
+        let existing = client
+            .get_code(contract, None)
             .await
-            .map_err(|e| eyre!("did not estimate correctly: {e}"))?;
+            .wrap_err("failed to get code at the predicted address")?;
+        let mut total = U256::zero();
+        if self.salt.is_some() && !existing.is_empty() {
+            greyln!(
+                "  {}",
+                "skipped, code already exists at the predicted address".yellow()
+            );
+        } else {
+            let data = tx.data.clone().unwrap_or_default();
+            greyln!(
+                "  calldata ({} bytes): 0x{}",
+                data.len(),
+                hex::encode(&data)
+            );
+            let gas = match self.gas_limit {
+                Some(gas) => gas,
+                None => estimate(&tx)
+                    .await
+                    .wrap_err("failed to estimate deploy gas")?,
+            };
+            print_gas("gas", Some(gas));
+            total += gas;
+            nonce += U256::one();
+        }
 
-        if self.check_config.common_cfg.verbose || self.estimate_gas {
-            greyln!("activation gas estimate: {}", format_gas(gas));
+        let mut value = U256::zero();
+        match program {
+            ProgramCheck::Active { .. } => {
+                greyln!(
+                    "activation tx: {}",
+                    "skipped, the codehash is already activated".mint()
+                );
+            }
+            ProgramCheck::Ready { code, .. } => {
+                let program: Address = contract.to_fixed_bytes().into();
+                let data = ArbWasm::activateProgramCall { program }.abi_encode();
+                greyln!(
+                    "activation tx, to ArbWasm {}:",
+                    ARB_WASM_H160.debug_lavender()
+                );
+                greyln!("  calldata: 0x{}", hex::encode(data));
+                greyln!("  data fee sent: {}", format_eth(data_fee));
+                let fee = alloy_ethers_typecast::ethers_u256_to_alloy(data_fee);
+                let gas = match self.activation_gas_limit {
+                    Some(gas) => Some(gas),
+                    None => match estimate_activation_gas(code, contract, fee, client).await {
+                        Ok(gas) => Some(gas),
+                        Err(error) => {
+                            warnln!(
+                                ActivationGasUnknown,
+                                "could not estimate activation gas: {error}"
+                            )?;
+                            None
+                        }
+                    },
+                };
+                print_gas("gas", gas);
+                total += gas.unwrap_or_default();
+                value = data_fee;
+                nonce += U256::one();
+            }
         }
-        if self.estimate_gas {
-            return Ok(());
+
+        let mut target = contract;
+        if self.proxy.is_some() {
+            let proxy = ethers::utils::get_contract_address(sender, nonce);
+            let tx = Eip1559TransactionRequest::new()
+                .from(sender)
+                .data(minimal_proxy_deployment_calldata(contract));
+            greyln!("proxy deploy tx, with CREATE:");
+            greyln!("  predicted address: {}", proxy.debug_lavender());
+            greyln!(
+                "  calldata: 0x{}",
+                hex::encode(tx.data.clone().unwrap_or_default())
+            );
+            let gas = estimate(&tx)
+                .await
+                .wrap_err("failed to estimate proxy deploy gas")?;
+            print_gas("gas", Some(gas));
+            total += gas;
+            target = proxy;
+        }
+        if let Some(data) = constructor {
+            greyln!("constructor tx, to {}:", target.debug_lavender());
+            greyln!("  calldata: 0x{}", hex::encode(data));
+            greyln!(
+                "  gas: {}",
+                "not estimated, since the program isn't deployed yet".yellow()
+            );
+        }
+
+        greyln!(
+            "total gas estimate: {} ({} at {} gwei)",
+            format_gas(total),
+            format_eth(total * price + value),
+            ethers::utils::format_units(price, "gwei")?
+        );
+        if let Some(max_gas) = self.max_gas {
+            if total > max_gas && !self.force {
+                greyln!(
+                    "{}",
+                    format!("a real deploy would refuse, since this exceeds --max-gas {max_gas}")
+                        .yellow()
+                );
+            }
+        }
+        let balance = client.get_balance(sender, None).await?;
+        if balance < total * price + value {
+            greyln!(
+                "{}",
+                format!(
+                    "the signer's balance of {} doesn't cover this",
+                    ethers::utils::format_ether(balance)
+                )
+                .yellow()
+            );
         }
+        Ok(())
+    }
+
+    /// The gas price txs would pay: the node's, capped by any max fee per gas that's set.
+    async fn effective_gas_price(&self, client: &SignerClient) -> Result<U256> {
+        let cfg = &self.check_config.common_cfg;
+        let cfg = config::network(client.signer().chain_id())?.apply_fees(cfg);
+        let price = client
+            .get_gas_price()
+            .await
+            .wrap_err("failed to get gas price")?;
+        Ok(match cfg.max_fee_per_gas()? {
+            Some(max_fee) => price.min(max_fee),
+            None => price,
+        })
+    }
+
+    async fn deploy_minimal_proxy(
+        &self,
+        implementation: H160,
+        sender: H160,
+        client: &SignerClient,
+    ) -> Result<Option<H160>> {
+        let verbose = self.check_config.common_cfg.verbose;
+        let tx = Eip1559TransactionRequest::new()
+            .from(sender)
+            .data(minimal_proxy_deployment_calldata(implementation));
 
+        let gas = client
+            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+            .await?;
+        if verbose {
+            greyln!("proxy deploy gas estimate: {}", format_gas(gas));
+        }
+
+        self.check_base_fee(client).await?;
         let receipt = run_tx(
-            "activate",
+            "proxy",
             tx,
             Some(gas),
-            self.check_config.common_cfg.max_fee_per_gas_gwei,
+            &self.check_config.common_cfg,
             client,
-            self.check_config.common_cfg.verbose,
         )
         .await?;
+        let proxy = receipt.contract_address.ok_or(eyre!("missing address"))?;
+        greyln!(
+            "deployed minimal proxy at address: {} {} {}",
+            proxy.debug_lavender(),
+            "delegating to".grey(),
+            implementation.debug_lavender()
+        );
+        Ok(Some(proxy))
+    }
 
-        if verbose {
-            let gas = format_gas(receipt.gas_used.unwrap_or_default());
-            greyln!("activated with {gas}");
+    /// Calls the initializer given by `--constructor-signature` on the deployed contract.
+    async fn construct(
+        &self,
+        contract: H160,
+        sender: H160,
+        data: Vec<u8>,
+        client: &SignerClient,
+    ) -> Result<()> {
+        let tx = Eip1559TransactionRequest::new()
+            .from(sender)
+            .to(contract)
+            .data(data);
+        let gas = client
+            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+            .await
+            .wrap_err("failed to estimate constructor gas, the initializer may revert")?;
+        if self.check_config.common_cfg.verbose {
+            greyln!("constructor gas estimate: {}", format_gas(gas));
         }
+
+        self.check_base_fee(client).await?;
+        let receipt = run_tx(
+            "constructor",
+            tx,
+            Some(gas),
+            &self.check_config.common_cfg,
+            client,
+        )
+        .await?;
         greyln!(
-            "program activated and ready onchain with tx hash: {}",
+            "constructed {} with tx hash: {}",
+            contract.debug_lavender(),
             receipt.transaction_hash.debug_lavender()
         );
         Ok(())
     }
 }
 
+/// ABI-encodes a call to the initializer with the given signature, checking each argument
+/// against the parameter types before anything is sent.
+pub fn constructor_calldata(signature: &str, args: &[String]) -> Result<Vec<u8>> {
+    let function = AbiParser::default()
+        .parse_function(signature)
+        .map_err(|error| eyre!("invalid constructor signature {signature}: {error}"))?;
+    let params = &function.inputs;
+    if params.len() != args.len() {
+        bail!(
+            "{} takes {} constructor args but {} were given",
+            function.signature(),
+            params.len(),
+            args.len()
+        );
+    }
+    let mut tokens = vec![];
+    for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+        let token = LenientTokenizer::tokenize(&param.kind, arg).map_err(|error| {
+            eyre!(
+                "constructor arg {index} ({arg}) is not a valid {}: {error}",
+                param.kind
+            )
+        })?;
+        tokens.push(token);
+    }
+    Ok(function.encode_input(&tokens)?)
+}
+
+/// Activates a deployed program through ArbWasm, paying the given data fee. Returns the
+/// activation tx's hash, or nothing when only estimating gas.
+pub async fn send_activation(
+    cfg: &CommonConfig,
+    contract: H160,
+    data_fee: AU256,
+    gas_limit: Option<U256>,
+    estimate_gas: bool,
+    trace_reverts: bool,
+    client: &SignerClient,
+) -> Result<Option<H256>> {
+    let verbose = cfg.verbose;
+    let data_fee = alloy_ethers_typecast::alloy_u256_to_ethers(data_fee);
+    let program: Address = contract.to_fixed_bytes().into();
+
+    let data = ArbWasm::activateProgramCall { program }.abi_encode();
+
+    let tx = Eip1559TransactionRequest::new()
+        .from(client.address())
+        .to(*ARB_WASM_H160)
+        .data(data)
+        .value(data_fee);
+
+    let gas = match gas_limit {
+        Some(gas) if !estimate_gas => gas,
+        _ => {
+            let gas = client
+                .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+                .await;
+            let gas = match gas {
+                Ok(gas) => gas,
+                Err(error) => {
+                    if trace_reverts {
+                        trace_activation(&tx, client).await;
+                    }
+                    bail!("did not estimate correctly: {error}");
+                }
+            };
+            if verbose || estimate_gas {
+                greyln!("activation gas estimate: {}", format_gas(gas));
+            }
+            gas
+        }
+    };
+    if estimate_gas {
+        return Ok(None);
+    }
+
+    let receipt = match run_tx("activate", tx.clone(), Some(gas), cfg, client).await {
+        Ok(receipt) => receipt,
+        Err(error) => {
+            if trace_reverts {
+                trace_activation(&tx, client).await;
+            }
+            return Err(error);
+        }
+    };
+
+    if verbose {
+        let gas = format_gas(receipt.gas_used.unwrap_or_default());
+        greyln!("activated with {gas}");
+    }
+    greyln!(
+        "program activated and ready onchain with tx hash: {}",
+        receipt.transaction_hash.debug_lavender()
+    );
+    Ok(Some(receipt.transaction_hash))
+}
+
+/// Waits until ArbWasm reports the program as active, since a mined activation tx alone doesn't
+/// prove the node serving reads agrees.
+async fn poll_activation_state(
+    contract: H160,
+    timeout: Duration,
+    provider: &Provider<RpcClient>,
+) -> Result<()> {
+    let program: Address = contract.to_fixed_bytes().into();
+    let tx = Eip1559TransactionRequest::new()
+        .to(*ARB_WASM_H160)
+        .data(ArbWasm::programVersionCall { program }.abi_encode());
+    let deadline = Instant::now() + timeout;
+    loop {
+        let reason = match check::eth_call(tx.clone(), State::default(), provider).await? {
+            Ok(outs) => {
+                let version = ArbWasm::programVersionCall::abi_decode_returns(&outs, true)?.version;
+                greyln!(
+                    "confirmed active onchain for Stylus version {}",
+                    version.mint()
+                );
+                return Ok(());
+            }
+            Err(error) => check::describe_arbwasm_error(&error.data).unwrap_or(error.msg),
+        };
+        if Instant::now() >= deadline {
+            bail!(
+                "program still isn't reported active after {}s: {reason}",
+                timeout.as_secs()
+            );
+        }
+        greyln!("waiting for the program to be reported active ({reason})");
+        tokio::time::sleep(ACTIVATION_POLL_INTERVAL).await;
+    }
+}
+
+/// Replays a failing activation with the node's call tracer and prints the calls leading to
+/// the innermost one that failed, decoding its revert. Nodes without `debug_traceCall` only
+/// get a note, leaving the plain revert reason to explain the failure.
+async fn trace_activation(tx: &Eip1559TransactionRequest, client: &SignerClient) {
+    let options = GethDebugTracingCallOptions {
+        tracing_options: GethDebugTracingOptions {
+            tracer: Some(GethDebugTracerType::BuiltInTracer(
+                GethDebugBuiltInTracerType::CallTracer,
+            )),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let block = Some(BlockNumber::Pending.into());
+    let trace = client
+        .provider()
+        .debug_trace_call(tx.clone(), block, options)
+        .await;
+    let mut frame = match trace {
+        Ok(GethTrace::Known(GethTraceFrame::CallTracer(frame))) => frame,
+        Ok(_) => {
+            greyln!("could not trace the activation: the node returned an unexpected trace");
+            return;
+        }
+        Err(error) => {
+            greyln!(
+                "could not trace the activation, the node may not support debug_traceCall: {error}"
+            );
+            return;
+        }
+    };
+
+    greyln!("activation trace:");
+    let mut depth = 1;
+    loop {
+        let to = match &frame.to {
+            Some(NameOrAddress::Address(to)) => to.debug_lavender(),
+            Some(NameOrAddress::Name(to)) => to.lavender(),
+            None => "a new contract".lavender(),
+        };
+        let status = match &frame.error {
+            Some(error) => error.red(),
+            None => "ok".mint(),
+        };
+        greyln!("{}{} to {to}: {status}", "  ".repeat(depth), frame.typ);
+        let failed = frame.calls.take().unwrap_or_default();
+        match failed.into_iter().find(|call| call.error.is_some()) {
+            Some(call) => frame = call,
+            None => break,
+        }
+        depth += 1;
+    }
+    let output = frame.output.unwrap_or_default();
+    match check::describe_arbwasm_error(&output) {
+        Some(reason) => greyln!("failed with: {}", reason.red()),
+        None if !output.is_empty() => greyln!("failed with data: {}", output.debug_red()),
+        None => {}
+    }
+}
+
+/// Estimates the gas to activate the given code before it's deployed, by overriding the code
+/// at the address it will be deployed to. Requires a node that accepts state overrides in
+/// `eth_estimateGas`.
+async fn estimate_activation_gas(
+    code: &[u8],
+    contract: H160,
+    data_fee: AU256,
+    client: &SignerClient,
+) -> Result<U256> {
+    let program = Address::from(contract.to_fixed_bytes());
+    let data = ArbWasm::activateProgramCall { program }.abi_encode();
+    let tx = Eip1559TransactionRequest::new()
+        .from(client.address())
+        .to(*ARB_WASM_H160)
+        .data(data)
+        .value(alloy_ethers_typecast::alloy_u256_to_ethers(data_fee));
+    let tx = TypedTransaction::Eip1559(tx);
+    let state = spoof::code(contract, code.to_vec().into());
+    let gas = client
+        .provider()
+        .request("eth_estimateGas", (tx, BlockNumber::Pending, state))
+        .await?;
+    Ok(gas)
+}
+
+/// An ArbWasm activation transaction, as written by `--emit-activation-calldata`.
+#[derive(Serialize, JsonSchema)]
+pub struct ActivationCalldata {
+    /// Address of the ArbWasm precompile.
+    #[schemars(with = "String")]
+    to: H160,
+    /// Data fee to send along, in wei.
+    value: String,
+    /// Hex-encoded `activateProgram` calldata.
+    data: String,
+}
+
+/// Writes the ArbWasm activation transaction for a program to a JSON file, so that it can be
+/// submitted by another account such as a multisig. The value is the data fee in wei.
+pub fn write_activation_calldata(path: &Path, contract: H160, data_fee: AU256) -> Result<()> {
+    let program: Address = contract.to_fixed_bytes().into();
+    let data = ArbWasm::activateProgramCall { program }.abi_encode();
+    let calldata = ActivationCalldata {
+        to: *ARB_WASM_H160,
+        value: data_fee.to_string(),
+        data: format!("0x{}", hex::encode(data)),
+    };
+    let json = serde_json::to_string_pretty(&calldata)?;
+    fs::write(path, json).wrap_err_with(|| format!("failed to write {path:?}"))?;
+    greyln!(
+        "wrote activation calldata to {}",
+        path.to_string_lossy().lavender()
+    );
+    Ok(())
+}
+
 pub async fn run_tx(
     name: &str,
     tx: Eip1559TransactionRequest,
     gas: Option<U256>,
-    max_fee_per_gas_gwei: Option<U256>,
+    cfg: &CommonConfig,
     client: &SignerClient,
-    verbose: bool,
 ) -> Result<TransactionReceipt> {
+    let tx_hash = send_tx(name, tx, gas, cfg, client).await?;
+    wait_for_tx(name, tx_hash, client).await
+}
+
+/// Signs and broadcasts a tx, returning its hash without waiting for it to be mined.
+pub async fn send_tx(
+    name: &str,
+    tx: Eip1559TransactionRequest,
+    gas: Option<U256>,
+    cfg: &CommonConfig,
+    client: &SignerClient,
+) -> Result<H256> {
+    let mut nonces = match &cfg.nonce_file {
+        Some(path) => Some(NonceFile::lock(path)?),
+        None => None,
+    };
+    send_tx_locked(name, tx, gas, cfg, client, nonces.as_mut()).await
+}
+
+/// Like [`send_tx`], but takes the nonce from a nonce file the caller already holds locked.
+async fn send_tx_locked(
+    name: &str,
+    tx: Eip1559TransactionRequest,
+    gas: Option<U256>,
+    cfg: &CommonConfig,
+    client: &SignerClient,
+    nonces: Option<&mut NonceFile>,
+) -> Result<H256> {
+    let verbose = cfg.verbose;
     let mut tx = tx;
     if let Some(gas) = gas {
         tx.gas = Some(gas);
     }
-    if let Some(max_fee) = max_fee_per_gas_gwei {
-        tx.max_fee_per_gas = Some(gwei_to_wei(max_fee)?);
+    fill_fees(&mut tx, cfg, client).await?;
+    if let (Some(nonces), None) = (&nonces, tx.nonce) {
+        tx.nonce = Some(nonces.next_nonce(client).await?);
     }
+    let nonce = tx.nonce;
     let tx = TypedTransaction::Eip1559(tx);
     let tx = client.send_transaction(tx, None).await?;
+    if let (Some(nonces), Some(nonce)) = (nonces, nonce) {
+        nonces.record(client, nonce)?;
+    }
     let tx_hash = tx.tx_hash();
     if verbose {
         greyln!("sent {name} tx: {}", tx_hash.debug_lavender());
     }
+    Ok(tx_hash)
+}
+
+/// Sets the tx's fees from `--max-fee-per-gas-gwei`, the gas oracle or `--auto-gas`, leaving
+/// them unset for the node to fill in if none apply. Fees already on the tx are kept.
+pub async fn fill_fees(
+    tx: &mut Eip1559TransactionRequest,
+    cfg: &CommonConfig,
+    client: &SignerClient,
+) -> Result<()> {
+    if tx.max_fee_per_gas.is_some() {
+        return Ok(());
+    }
+    let cfg = &config::network(client.signer().chain_id())?.apply_fees(cfg);
+    let verbose = cfg.verbose;
+    let max_priority_fee = cfg.max_priority_fee_per_gas()?;
+    if let Some(max_fee) = cfg.max_fee_per_gas()? {
+        if verbose {
+            let gwei = ethers::utils::format_units(max_fee, "gwei")?;
+            greyln!("max fee per gas: {max_fee} wei ({gwei} gwei)");
+        }
+        tx.max_fee_per_gas = Some(max_fee);
+        if let Some(tip) = max_priority_fee {
+            if verbose {
+                let gwei = ethers::utils::format_units(tip, "gwei")?;
+                greyln!("max priority fee per gas: {tip} wei ({gwei} gwei)");
+            }
+            tx.max_priority_fee_per_gas = Some(tip);
+        }
+    } else if let Some(url) = &cfg.gas_oracle_url {
+        match gas_oracle::fetch_fees(url, cfg.rpc_policy().timeout).await {
+            Ok(fees) => {
+                if verbose {
+                    greyln!(
+                        "gas oracle suggests max fee {} wei and priority fee {} wei",
+                        fees.max_fee_per_gas.mint(),
+                        fees.max_priority_fee_per_gas.mint()
+                    );
+                }
+                tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+            }
+            Err(error) => warnln!(
+                GasOracleFailed,
+                "{error} ({}), using the node's fees instead",
+                error.root_cause()
+            )?,
+        }
+    } else if cfg.auto_gas {
+        let (mut max_fee, mut tip) = gas_oracle::auto_fees(client, cfg.gas_multiplier).await?;
+        if let Some(cap) = cfg.max_gas_cap_gwei {
+            if max_fee > cap {
+                let msg = format!(
+                    "--auto-gas picked a max fee of {} gwei, capping it at --max-gas-cap-gwei",
+                    ethers::utils::format_units(max_fee, "gwei")?
+                );
+                greyln!("{}", msg.yellow());
+                max_fee = cap;
+                tip = tip.map(|tip| tip.min(cap));
+            }
+        }
+        let gwei = |wei: U256| ethers::utils::format_units(wei, "gwei");
+        match tip {
+            Some(tip) => greyln!(
+                "auto gas: max fee per gas {} gwei, priority fee {} gwei",
+                gwei(max_fee)?.mint(),
+                gwei(tip)?.mint()
+            ),
+            None => greyln!("auto gas: max fee per gas {} gwei", gwei(max_fee)?.mint()),
+        }
+        tx.max_fee_per_gas = Some(max_fee);
+        tx.max_priority_fee_per_gas = tip;
+    }
+    Ok(())
+}
+
+/// Waits for a broadcast tx to be mined, failing if it reverts.
+pub async fn wait_for_tx(
+    name: &str,
+    tx_hash: H256,
+    client: &SignerClient,
+) -> Result<TransactionReceipt> {
+    let tx = PendingTransaction::new(tx_hash, client.provider());
     let Some(receipt) = tx.await.wrap_err("tx failed to complete")? else {
         bail!("failed to get receipt for tx {}", tx_hash.lavender());
     };
@@ -248,18 +1478,83 @@ pub fn program_deployment_calldata(code: &[u8]) -> Vec<u8> {
     deploy
 }
 
+/// The fields encoded in a program's EVM deployment prelude.
+#[derive(Debug, PartialEq)]
+pub struct DeploymentPrelude {
+    /// Length of the compressed program the prelude copies into the contract.
+    pub code_len: U256,
+    /// Stylus version byte that follows the prelude.
+    pub version: u8,
+}
+
+/// Parses the prelude produced by [`program_deployment_calldata`], checking each opcode.
+pub fn parse_deployment_prelude(calldata: &[u8]) -> Result<DeploymentPrelude> {
+    let prelude_len = 42 + 1;
+    if calldata.len() < prelude_len {
+        bail!(
+            "calldata is {} bytes, shorter than the {prelude_len} byte prelude",
+            calldata.len()
+        );
+    }
+    let expect = |offset: usize, byte: u8, name: &str| -> Result<()> {
+        if calldata[offset] != byte {
+            bail!(
+                "expected {name} (0x{byte:02x}) at offset {offset}, found 0x{:02x}",
+                calldata[offset]
+            );
+        }
+        Ok(())
+    };
+    expect(0, 0x7f, "PUSH32")?;
+    let code_len = U256::from_big_endian(&calldata[1..33]);
+    expect(33, 0x80, "DUP1")?;
+    expect(34, 0x60, "PUSH1")?;
+    expect(35, prelude_len as u8, "prelude length")?;
+    expect(36, 0x60, "PUSH1")?;
+    expect(37, 0x00, "0")?;
+    expect(38, 0x39, "CODECOPY")?;
+    expect(39, 0x60, "PUSH1")?;
+    expect(40, 0x00, "0")?;
+    expect(41, 0xf3, "RETURN")?;
+    Ok(DeploymentPrelude {
+        code_len,
+        version: calldata[42],
+    })
+}
+
+/// Predicts the address a CREATE2 deployer creates the program at, and prepares the calldata
+/// asking it to: the salt, followed by the program's init code.
+pub fn create2_deployment(deployer: H160, salt: H256, code: &[u8]) -> (H160, Vec<u8>) {
+    let init_code = program_deployment_calldata(code);
+    let contract = ethers::utils::get_create2_address(deployer, salt, &init_code);
+    (contract, [salt.as_bytes(), &init_code].concat())
+}
+
+/// Prepares the creation code of an EIP-1167 minimal proxy delegating to `implementation`.
+pub fn minimal_proxy_deployment_calldata(implementation: H160) -> Vec<u8> {
+    let mut deploy = hex::decode("3d602d80600a3d3981f3363d3d373d3d3d363d73").unwrap();
+    deploy.extend(implementation.as_bytes());
+    deploy.extend(hex::decode("5af43d82803e903d91602b57fd5bf3").unwrap());
+    deploy
+}
+
 pub fn extract_program_evm_deployment_prelude(calldata: &[u8]) -> Vec<u8> {
     // The length of the prelude, version part is 42 + 1 as per the code
     let metadata_length = 42 + 1;
-    // Extract and return the metadata part
-    calldata[0..metadata_length].to_vec()
+    // Extract and return the metadata part, or as much of it as a short input has
+    calldata[..metadata_length.min(calldata.len())].to_vec()
 }
 
 pub fn extract_compressed_wasm(calldata: &[u8]) -> Vec<u8> {
     // The length of the prelude, version part is 42 + 1 as per the code
     let metadata_length = 42 + 1;
-    // Extract and return the metadata part
-    calldata[metadata_length..].to_vec()
+    // Extract and return what follows the metadata part
+    calldata.get(metadata_length..).unwrap_or_default().to_vec()
+}
+
+/// Formats an amount of wei as ether.
+fn format_eth(wei: U256) -> String {
+    format!("Ξ{}", ethers::utils::format_ether(wei)).lavender()
 }
 
 pub fn format_gas(gas: U256) -> String {
@@ -274,6 +1569,35 @@ pub fn format_gas(gas: U256) -> String {
     }
 }
 
+impl CommonConfig {
+    /// The max fee per gas set on the command line in either unit, in wei.
+    pub fn max_fee_per_gas(&self) -> Result<Option<U256>> {
+        match (self.max_fee_per_gas_gwei, self.max_fee_per_gas_wei) {
+            (Some(gwei), _) => Ok(Some(gwei_to_wei(gwei)?)),
+            (None, wei) => Ok(wei),
+        }
+    }
+
+    /// The max priority fee per gas set on the command line, in wei. It's only accepted
+    /// alongside a max fee per gas, since the tip can't be checked against an unknown cap.
+    pub fn max_priority_fee_per_gas(&self) -> Result<Option<U256>> {
+        let Some(gwei) = self.max_priority_fee_per_gas_gwei else {
+            return Ok(None);
+        };
+        let tip = gwei_to_wei(gwei)?;
+        let Some(max_fee) = self.max_fee_per_gas()? else {
+            bail!(
+                "--max-priority-fee-per-gas-gwei requires --max-fee-per-gas-gwei or \
+                 --max-fee-per-gas-wei, as both caps are needed to build an EIP-1559 tx"
+            );
+        };
+        if tip > max_fee {
+            bail!("max priority fee per gas {tip} wei exceeds the max fee per gas {max_fee} wei");
+        }
+        Ok(Some(tip))
+    }
+}
+
 fn gwei_to_wei(gwei: U256) -> Result<U256> {
     let wei_per_gwei: U256 = U256::from(10u64.pow(9));
     match gwei.checked_mul(wei_per_gwei) {
@@ -281,3 +1605,105 @@ fn gwei_to_wei(gwei: U256) -> Result<U256> {
         None => bail!("overflow occurred while converting gwei to wei"),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::{Args, FromArgMatches};
+
+    #[test]
+    fn test_create2_deployment() {
+        let code = [0xef, 0xf0, 0x00, 0x00, 1, 2, 3];
+        let deployer: H160 = crate::constants::CREATE2_DEPLOYER.parse().unwrap();
+        let salt = H256::from_low_u64_be(1);
+        let (contract, data) = create2_deployment(deployer, salt, &code);
+        let expected: H160 = "0x47d9bf1323b36325575b01bfbd2fce0b49e509a9"
+            .parse()
+            .unwrap();
+        assert_eq!(contract, expected);
+        assert_eq!(data[..32], salt[..]);
+        assert_eq!(data[32..], program_deployment_calldata(&code));
+    }
+
+    #[test]
+    fn test_parse_deployment_prelude() -> Result<()> {
+        let code = vec![0xef, 0xf0, 0x00, 0x00, 1, 2, 3];
+        let calldata = program_deployment_calldata(&code);
+
+        let prelude = parse_deployment_prelude(&calldata)?;
+        assert_eq!(prelude.code_len, U256::from(code.len()));
+        assert_eq!(prelude.version, 0);
+        assert_eq!(extract_compressed_wasm(&calldata), code);
+
+        let mut corrupted = calldata.clone();
+        corrupted[38] = 0x00;
+        let err = parse_deployment_prelude(&corrupted).unwrap_err();
+        assert!(err.to_string().contains("CODECOPY"));
+
+        assert!(parse_deployment_prelude(&calldata[..10]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimal_proxy_deployment_calldata() {
+        let implementation = H160::repeat_byte(0xbe);
+        let calldata = minimal_proxy_deployment_calldata(implementation);
+        let expected = format!(
+            "3d602d80600a3d3981f3363d3d373d3d3d363d73{}5af43d82803e903d91602b57fd5bf3",
+            "be".repeat(20)
+        );
+        assert_eq!(hex::encode(&calldata), expected);
+        // 10 bytes of creation code followed by the 45 byte runtime.
+        assert_eq!(calldata.len(), 10 + 45);
+    }
+
+    #[test]
+    fn test_constructor_calldata() -> Result<()> {
+        let owner = format!("0x{}", "be".repeat(20));
+        let args = [owner.clone(), "5".to_string()];
+        let calldata = constructor_calldata("initialize(address,uint256)", &args)?;
+        let selector = &ethers::utils::keccak256("initialize(address,uint256)")[..4];
+        assert_eq!(&calldata[..4], selector);
+        assert_eq!(&calldata[16..36], H160::repeat_byte(0xbe).as_bytes());
+        assert_eq!(calldata[67], 5);
+
+        let err = constructor_calldata("initialize(address,uint256)", &args[..1]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("takes 2 constructor args but 1 were given"));
+        let args = [owner, "five".to_string()];
+        let err = constructor_calldata("initialize(address,uint256)", &args).unwrap_err();
+        assert!(err.to_string().starts_with("constructor arg 1 (five)"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_allowed_sender_needs_no_wallet() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("deployers.txt");
+        let alice = H160::repeat_byte(0xaa);
+        fs::write(&path, format!("{alice:?}\n"))?;
+        let deploy_cfg = |sender: H160| {
+            let command = DeployConfig::augment_args(clap::Command::new("test"));
+            let path = path.to_string_lossy();
+            let sender = format!("{sender:?}");
+            let args = [
+                "test",
+                "--allowed-deployers",
+                &path,
+                "--dry-run-output",
+                "tx.json",
+                "--sender",
+                &sender,
+            ];
+            DeployConfig::from_arg_matches(&command.get_matches_from(args)).unwrap()
+        };
+
+        assert!(check_allowed_deployer(&deploy_cfg(alice)).await?.is_none());
+        let err = check_allowed_deployer(&deploy_cfg(H160::repeat_byte(0xbb)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("allowed deployers"));
+        Ok(())
+    }
+}
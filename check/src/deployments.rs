@@ -0,0 +1,303 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    deploy::{self, SignerClient},
+    macros::*,
+};
+use alloy_primitives::{keccak256, B256};
+use cargo_stylus_util::color::{Color, DebugColor};
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{TransactionReceipt, H160, H256, U256, U64},
+};
+use eyre::{Result, WrapErr};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A deployment broadcast by `cargo stylus deploy`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub chain_id: u64,
+    pub sender: H160,
+    pub nonce: u64,
+    pub tx_hash: H256,
+    /// Set once the deployment tx has succeeded.
+    pub contract_address: Option<H160>,
+    /// Keccak hash of the deployed code, which embeds the project hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codehash: Option<H256>,
+    /// Source state the deployment was built from, when deployed from a git repo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitMetadata>,
+}
+
+/// The git checkout a deployment was made from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMetadata {
+    /// Hash of the checked out commit.
+    pub commit: String,
+    /// Checked out branch, if HEAD isn't detached.
+    pub branch: Option<String>,
+    /// Whether tracked files had uncommitted changes.
+    pub dirty: bool,
+}
+
+/// Reads the commit, branch and dirty state of the current directory's git checkout, or
+/// `None` outside a repo or without git. Changes to the deployment record itself are ignored,
+/// since deploying writes to it.
+pub fn git_metadata(record: &Path) -> Option<GitMetadata> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"]).filter(|branch| branch != "HEAD");
+    let exclude = format!(":(exclude){}", record.to_string_lossy());
+    let changes = git(&[
+        "status",
+        "--porcelain",
+        "--untracked-files=no",
+        "--",
+        ".",
+        &exclude,
+    ])?;
+    Some(GitMetadata {
+        commit,
+        branch,
+        dirty: !changes.is_empty(),
+    })
+}
+
+/// Deployments keyed by their idempotency key.
+type Deployments = BTreeMap<String, Deployment>;
+
+/// Identifies a deployment of the given code by the client's signer at the given nonce.
+///
+/// The code embeds the project hash, so re-running the same deploy yields the same key.
+pub fn idempotency_key(codehash: B256, client: &SignerClient, nonce: U256) -> String {
+    let mut preimage = codehash.to_vec();
+    preimage.extend(client.signer().chain_id().to_be_bytes());
+    preimage.extend(client.address().as_bytes());
+    let mut nonce_bytes = [0u8; 32];
+    nonce.to_big_endian(&mut nonce_bytes);
+    preimage.extend(nonce_bytes);
+    hex::encode(keccak256(preimage))
+}
+
+/// Looks for a deployment of the same code that an interrupted run broadcast but never saw
+/// complete, and waits for it rather than deploying again.
+///
+/// `nonce` is the one the next deployment would use. Once the earlier tx is pending or mined
+/// the account's nonce has moved past it, so the previous nonce is checked as well.
+pub async fn resume(
+    path: &Path,
+    codehash: B256,
+    nonce: U256,
+    client: &SignerClient,
+) -> Result<Option<(String, TransactionReceipt)>> {
+    let mut nonces = vec![nonce];
+    if !nonce.is_zero() {
+        nonces.insert(0, nonce - 1);
+    }
+    for nonce in nonces {
+        let key = idempotency_key(codehash, client, nonce);
+        let Some(deployment) = read_deployments(path)?.remove(&key) else {
+            continue;
+        };
+        if deployment.contract_address.is_some() {
+            continue;
+        }
+        let tx_hash = deployment.tx_hash;
+        let receipt = client
+            .get_transaction_receipt(tx_hash)
+            .await
+            .wrap_err("failed to get receipt")?;
+        if let Some(receipt) = &receipt {
+            if receipt.status != Some(U64::from(1)) {
+                warnln!(
+                    PreviousDeployFailed,
+                    "deploy tx {} from a previous run reverted, deploying again",
+                    tx_hash.debug_red()
+                )?;
+                forget(path, &key)?;
+                continue;
+            }
+        } else if client.get_transaction(tx_hash).await?.is_none() {
+            warnln!(
+                PreviousDeployFailed,
+                "deploy tx {} from a previous run was dropped, deploying again",
+                tx_hash.debug_yellow()
+            )?;
+            forget(path, &key)?;
+            continue;
+        }
+        greyln!(
+            "resuming deploy tx {} from a previous run",
+            tx_hash.debug_lavender()
+        );
+        let receipt = match receipt {
+            Some(receipt) => receipt,
+            None => deploy::wait_for_tx("deploy", tx_hash, client).await?,
+        };
+        return Ok(Some((key, receipt)));
+    }
+    Ok(None)
+}
+
+/// Looks for an earlier deployment of the same code on the same chain that's still there, so
+/// that it can be reused instead of deploying again.
+pub async fn find_duplicate(
+    path: &Path,
+    codehash: B256,
+    client: &SignerClient,
+) -> Result<Option<Deployment>> {
+    let chain_id = client.signer().chain_id();
+    let codehash = H256::from(codehash.0);
+    for deployment in read_deployments(path)?.into_values() {
+        let Some(contract) = deployment.contract_address else {
+            continue;
+        };
+        if deployment.chain_id != chain_id || deployment.codehash != Some(codehash) {
+            continue;
+        }
+        // The record outlives local dev chains, which may have been reset since.
+        let code = client
+            .get_code(contract, None)
+            .await
+            .wrap_err("failed to get code")?;
+        if keccak256(&code).0 == codehash.0 {
+            return Ok(Some(deployment));
+        }
+    }
+    Ok(None)
+}
+
+/// Records a deployment tx right after it's broadcast.
+pub fn record_broadcast(path: &Path, key: &str, deployment: Deployment) -> Result<()> {
+    let mut record = LockedRecord::lock(path)?;
+    record.deployments.insert(key.to_string(), deployment);
+    record.save()
+}
+
+/// Records the address of a deployment whose tx has succeeded.
+pub fn record_contract(path: &Path, key: &str, contract: H160) -> Result<()> {
+    let mut record = LockedRecord::lock(path)?;
+    if let Some(deployment) = record.deployments.get_mut(key) {
+        deployment.contract_address = Some(contract);
+    }
+    record.save()
+}
+
+fn forget(path: &Path, key: &str) -> Result<()> {
+    let mut record = LockedRecord::lock(path)?;
+    record.deployments.remove(key);
+    record.save()
+}
+
+/// The deployment record, locked for as long as the value lives so that parallel deploys
+/// each add their entry rather than overwriting one another's.
+///
+/// The lock is taken on a `.lock` file next to the record rather than on the record itself,
+/// since saving replaces the record with a new file.
+struct LockedRecord {
+    _lock: File,
+    path: PathBuf,
+    deployments: Deployments,
+}
+
+impl LockedRecord {
+    fn lock(path: &Path) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .wrap_err_with(|| format!("failed to open {lock_path:?}"))?;
+        lock.lock_exclusive()
+            .wrap_err_with(|| format!("failed to lock deployment record {path:?}"))?;
+        Ok(Self {
+            _lock: lock,
+            path: path.to_path_buf(),
+            deployments: read_deployments(path)?,
+        })
+    }
+
+    /// Writes the record to a temporary file and renames it into place, so that an
+    /// interrupted write never leaves a truncated record behind.
+    fn save(&self) -> Result<()> {
+        let path = &self.path;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let json = serde_json::to_string_pretty(&self.deployments)?;
+        let mut file = tempfile::NamedTempFile::new_in(dir)
+            .wrap_err_with(|| format!("failed to create a temporary file in {dir:?}"))?;
+        file.write_all(json.as_bytes())
+            .and_then(|_| file.as_file().sync_all())
+            .wrap_err_with(|| format!("failed to write deployment record {path:?}"))?;
+        file.persist(path)
+            .wrap_err_with(|| format!("failed to write deployment record {path:?}"))?;
+        Ok(())
+    }
+}
+
+fn read_deployments(path: &Path) -> Result<Deployments> {
+    if !path.exists() {
+        return Ok(Deployments::new());
+    }
+    let json = fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read deployment record {path:?}"))?;
+    serde_json::from_str(&json)
+        .wrap_err_with(|| format!("invalid deployment record {path:?}, please repair or remove it"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_corrupt_record() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("stylus-deployments.json");
+        let deployment = Deployment {
+            chain_id: 421614,
+            sender: H160::repeat_byte(1),
+            nonce: 5,
+            tx_hash: H256::repeat_byte(2),
+            contract_address: None,
+            codehash: None,
+            git: None,
+        };
+        record_broadcast(&path, "key", deployment)?;
+        record_contract(&path, "key", H160::repeat_byte(3))?;
+        let deployments = read_deployments(&path)?;
+        assert_eq!(
+            deployments["key"].contract_address,
+            Some(H160::repeat_byte(3))
+        );
+
+        let json = fs::read_to_string(&path)?;
+        fs::write(&path, &json[..json.len() / 2])?;
+        let err = record_contract(&path, "key", H160::repeat_byte(4)).unwrap_err();
+        assert!(err.to_string().contains("invalid deployment record"));
+        assert_eq!(fs::read_to_string(&path)?, json[..json.len() / 2]);
+        Ok(())
+    }
+}
@@ -1,17 +1,59 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
+use std::any::TypeId;
+use std::env;
+use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-use eyre::{bail, eyre, Result};
+use alloy_primitives::keccak256;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, ValueHint};
+use eyre::{bail, eyre, Result, WrapErr};
 
 use crate::constants::TOOLCHAIN_FILE_NAME;
+use crate::explorer::EXPLORER_API_KEY_ENV;
+use crate::macros::*;
 use crate::project::extract_toolchain_channel;
+use crate::{ImageConfig, Opts};
+use cargo_stylus_util::{color::Color, sys};
 
-fn version_to_image_name(version: &str) -> String {
-    format!("cargo-stylus-{}", version)
+/// Label recording the exact base image a reproducible image was built from.
+const BASE_IMAGE_LABEL: &str = "cargo-stylus.base-image";
+
+/// Environment variables that network commands read, passed into the container when set.
+const FORWARDED_ENV_VARS: &[&str] = &[
+    EXPLORER_API_KEY_ENV,
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+];
+
+/// Names the image after what it's built from, so that a different base or Dockerfile gets an
+/// image of its own.
+fn image_name(version: &str, image: &ImageConfig) -> Result<String> {
+    let name = format!("cargo-stylus-{}", version);
+    let source = match &image.dockerfile {
+        Some(path) => fs::read(path).wrap_err_with(|| format!("failed to read {path:?}"))?,
+        None if image.base_image.is_none() && image.image_digest.is_none() => return Ok(name),
+        None => format!("{:?} {:?}", image.base_image, image.image_digest).into_bytes(),
+    };
+    Ok(format!("{name}-{}", &hex::encode(keccak256(source))[..12]))
+}
+
+/// Parses a digest like `sha256:<64 hex digits>`.
+pub fn parse_digest(digest: &str) -> Result<String, String> {
+    let valid = digest
+        .strip_prefix("sha256:")
+        .is_some_and(|hex| hex.len() == 64 && hex.bytes().all(|c| c.is_ascii_hexdigit()));
+    match valid {
+        true => Ok(digest.to_ascii_lowercase()),
+        false => Err(format!("{digest} should look like sha256:<64 hex digits>")),
+    }
 }
 
 fn image_exists(name: &str) -> Result<bool> {
@@ -23,17 +65,83 @@ fn image_exists(name: &str) -> Result<bool> {
     Ok(output.stdout.iter().filter(|c| **c == b'\n').count() > 1)
 }
 
-fn create_image(version: &str) -> Result<()> {
-    let name = version_to_image_name(version);
+fn docker_succeeds(args: &[&str]) -> Result<bool> {
+    let status = Command::new("docker")
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| eyre!("failed to execute Docker command: {e}"))?;
+    Ok(status.success())
+}
+
+fn docker_inspect(image: &str, format: &str) -> Result<String> {
+    let output = Command::new("docker")
+        .args(["image", "inspect", "--format", format, image])
+        .output()
+        .map_err(|e| eyre!("failed to execute Docker command: {e}"))?;
+    if !output.status.success() {
+        bail!("failed to inspect Docker image {image}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Finds the base image to build from, pulling it if needed, and pins it by digest so the build
+/// uses exactly the image that's reported.
+fn resolve_base_image(version: &str, image: &ImageConfig) -> Result<String> {
+    let base = image
+        .base_image
+        .clone()
+        .unwrap_or_else(|| format!("rust:{version}"));
+    let base = match &image.image_digest {
+        Some(digest) => format!("{base}@{digest}"),
+        None => base,
+    };
+    let present = docker_succeeds(&["image", "inspect", &base])?;
+    if !present && !docker_succeeds(&["pull", &base])? {
+        bail!("base image {base} isn't available locally and couldn't be pulled");
+    }
+    if image.image_digest.is_some() {
+        return Ok(base);
+    }
+    // Images built locally have no registry digest, so they can only be named by tag.
+    let digests = docker_inspect(&base, "{{range .RepoDigests}}{{.}} {{end}}")?;
+    Ok(digests
+        .split_whitespace()
+        .next()
+        .map_or(base, str::to_string))
+}
+
+fn create_image(version: &str, image: &ImageConfig) -> Result<String> {
+    let name = image_name(version, image)?;
     if image_exists(&name)? {
-        return Ok(());
+        return Ok(name);
     }
+    sys::ensure_network("building the reproducible Docker image")?;
+    if let Some(dockerfile) = &image.dockerfile {
+        let status = Command::new("docker")
+            .arg("build")
+            .arg("-t")
+            .arg(&name)
+            .arg("-f")
+            .arg(dockerfile)
+            .arg(".")
+            .status()
+            .map_err(|e| eyre!("failed to execute Docker command: {e}"))?;
+        if !status.success() {
+            bail!("failed to build Docker image from {dockerfile:?}");
+        }
+        return Ok(name);
+    }
+    let base = resolve_base_image(version, image)?;
     let toolchain_file_path = PathBuf::from(".").as_path().join(TOOLCHAIN_FILE_NAME);
     let toolchain_channel = extract_toolchain_channel(&toolchain_file_path)?;
     let mut child = Command::new("docker")
         .arg("build")
         .arg("-t")
-        .arg(name)
+        .arg(&name)
+        .arg("--label")
+        .arg(format!("{BASE_IMAGE_LABEL}={base}"))
         .arg(".")
         .arg("-f-")
         .stdin(Stdio::piped())
@@ -42,7 +150,7 @@ fn create_image(version: &str) -> Result<()> {
     write!(
         child.stdin.as_mut().unwrap(),
         "\
-            FROM rust:{} as builder\n\
+            FROM {} as builder\n\
             RUN rustup toolchain install {} && rustup default {}
             RUN rustup target add wasm32-unknown-unknown
             RUN rustup target add wasm32-wasi
@@ -53,47 +161,232 @@ fn create_image(version: &str) -> Result<()> {
             RUN cargo install --force cargo-stylus-replay
             RUN cargo install --force cargo-stylus-cgen
         ",
-        version,
+        base,
         toolchain_channel,
         toolchain_channel,
     )?;
-    child.wait().map_err(|e| eyre!("wait failed: {e}"))?;
+    let status = child.wait().map_err(|e| eyre!("wait failed: {e}"))?;
+    if !status.success() {
+        bail!("failed to build Docker image {name}");
+    }
+    Ok(name)
+}
+
+/// Prints the image's ID and the base it was built from, which together pin the environment
+/// of a reproducible build.
+fn report_image(name: &str) -> Result<()> {
+    let id = docker_inspect(name, "{{.Id}}")?;
+    greyln!("reproducible image {} is {}", name, id.lavender());
+    let base = docker_inspect(
+        name,
+        &format!("{{{{index .Config.Labels {BASE_IMAGE_LABEL:?}}}}}"),
+    )?;
+    if let Some((_, digest)) = base.split_once('@') {
+        greyln!(
+            "built from {}, pass --image-digest {} to pin it",
+            base.lavender(),
+            digest
+        );
+    }
     Ok(())
 }
 
-fn run_in_docker_container(version: &str, command_line: &[&str]) -> Result<()> {
-    let name = version_to_image_name(version);
-    if !image_exists(&name)? {
+/// Builds the `docker run` command for running a command line in the image, mounting the
+/// current directory at `/source`. Every run in the image goes through here so that they all
+/// see the same environment.
+fn docker_run(name: &str, command_line: &[&str]) -> Result<Command> {
+    if !image_exists(name)? {
         bail!("Docker image {name} doesn't exist");
     }
     let dir =
         std::env::current_dir().map_err(|e| eyre!("failed to find current directory: {e}"))?;
-    Command::new("docker")
+    let mut docker = Command::new("docker");
+    docker
         .arg("run")
+        .arg("--rm")
         .arg("--network")
         .arg("host")
         .arg("-w")
         .arg("/source")
         .arg("-v")
-        .arg(format!("{}:/source", dir.as_os_str().to_str().unwrap()))
-        .arg(name)
-        .args(command_line)
+        .arg(format!("{}:/source", dir.as_os_str().to_str().unwrap()));
+    for var in FORWARDED_ENV_VARS {
+        if env::var_os(var).is_some() {
+            // Docker reads the value from our environment, keeping secrets off its command line.
+            docker.arg("-e").arg(var);
+        }
+    }
+    docker.arg(name).args(command_line);
+    Ok(docker)
+}
+
+fn run_in_docker_container(name: &str, command_line: &[&str]) -> Result<()> {
+    let status = docker_run(name, command_line)?
         .spawn()
         .map_err(|e| eyre!("failed to execure Docker command: {e}"))?
         .wait()
         .map_err(|e| eyre!("wait failed: {e}"))?;
+    if !status.success() {
+        bail!("`{}` failed in Docker", command_line.join(" "));
+    }
+    Ok(())
+}
+
+/// Fails if the command names a file outside the project directory, which the container
+/// can't see.
+fn check_paths_are_mounted(command_line: &[String], dir: &Path) -> Result<()> {
+    for (flag, value) in command_paths(command_line) {
+        let path = dir.join(&value);
+        let path = match path.parent().map(|parent| parent.canonicalize()) {
+            Some(Ok(parent)) => parent.join(path.file_name().unwrap_or_default()),
+            _ => path,
+        };
+        if !path.starts_with(dir) {
+            bail!(
+                "{flag} {} is outside the project directory, the only one mounted in the \
+                 reproducible container",
+                value.to_string_lossy()
+            );
+        }
+    }
     Ok(())
 }
 
-pub fn run_reproducible(version: &str, command_line: &[String]) -> Result<()> {
-    let version: String = version
+/// The files and directories a cargo stylus command line names, with the flags naming them.
+/// Path flags are found from the command's clap definitions: those parsed as a `PathBuf` or
+/// hinted as a path. Command lines of the other cargo stylus tools aren't checked.
+fn command_paths(command_line: &[String]) -> Vec<(String, PathBuf)> {
+    let mut command = Opts::command();
+    let args = std::iter::once("cargo-stylus").chain(command_line.iter().map(String::as_str));
+    let Ok(matches) = command.try_get_matches_from_mut(args) else {
+        return vec![];
+    };
+    let mut paths = vec![];
+    collect_paths(&command, &matches, &mut paths);
+    paths
+}
+
+fn collect_paths(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    paths: &mut Vec<(String, PathBuf)>,
+) {
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let flag = match arg.get_long() {
+            Some(long) => format!("--{long}"),
+            None => id.to_string(),
+        };
+        if arg.get_value_parser().type_id() == TypeId::of::<PathBuf>() {
+            let values = matches.get_many::<PathBuf>(id).into_iter().flatten();
+            paths.extend(values.map(|path| (flag.clone(), path.clone())));
+        } else if matches!(
+            arg.get_value_hint(),
+            ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath
+        ) {
+            let values = matches.get_many::<String>(id).into_iter().flatten();
+            paths.extend(values.map(|path| (flag.clone(), PathBuf::from(path))));
+        }
+    }
+    if let Some((name, matches)) = matches.subcommand() {
+        if let Some(subcommand) = command.find_subcommand(name) {
+            collect_paths(subcommand, matches, paths);
+        }
+    }
+}
+
+fn sanitize_version(version: &str) -> String {
+    version
         .chars()
         .filter(|c| c.is_alphanumeric() || *c == '.')
-        .collect();
+        .collect()
+}
+
+pub fn run_reproducible(version: &str, image: &ImageConfig, command_line: &[String]) -> Result<()> {
+    let version = sanitize_version(version);
     let mut command = vec!["cargo", "stylus"];
     for s in command_line.iter() {
         command.push(s);
     }
-    create_image(&version)?;
-    run_in_docker_container(&version, &command)
+    let dir = env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .map_err(|e| eyre!("failed to find current directory: {e}"))?;
+    check_paths_are_mounted(command_line, &dir)?;
+    let name = create_image(&version, image)?;
+    report_image(&name)?;
+    run_in_docker_container(&name, &command)
+}
+
+/// Runs a command in the reproducible image for the given Rust version, returning its stdout.
+pub fn reproducible_output(
+    version: &str,
+    image: &ImageConfig,
+    command_line: &[&str],
+) -> Result<Vec<u8>> {
+    let version = sanitize_version(version);
+    let name = create_image(&version, image)?;
+    let output = docker_run(&name, command_line)?
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| eyre!("failed to execute Docker command: {e}"))?;
+    if !output.status.success() {
+        bail!("`{}` failed in Docker", command_line.join(" "));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_image_names() -> Result<()> {
+        let default = ImageConfig {
+            image_digest: None,
+            base_image: None,
+            dockerfile: None,
+        };
+        assert_eq!(image_name("1.80", &default)?, "cargo-stylus-1.80");
+
+        let digest = format!("sha256:{}", "AB".repeat(32));
+        let pinned = ImageConfig {
+            image_digest: Some(parse_digest(&digest).unwrap()),
+            ..default.clone()
+        };
+        let name = image_name("1.80", &pinned)?;
+        assert!(name.starts_with("cargo-stylus-1.80-") && name.len() == 30);
+        assert_ne!(name, image_name("1.81", &pinned)?);
+        assert!(parse_digest("sha256:abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_paths() {
+        let command_line: Vec<String> = [
+            "deploy",
+            "--keystore-path=../keystore",
+            "--keystore-password-path",
+            "password.txt",
+            "--summary-json-append",
+            "summary.json",
+        ]
+        .map(String::from)
+        .into();
+        let paths: Vec<_> = command_paths(&command_line)
+            .into_iter()
+            .map(|(flag, path)| (flag, path.to_string_lossy().to_string()))
+            .collect();
+        let paths: Vec<_> = paths
+            .iter()
+            .map(|(f, p)| (f.as_str(), p.as_str()))
+            .collect();
+        assert!(paths.contains(&("--keystore-path", "../keystore")));
+        assert!(paths.contains(&("--keystore-password-path", "password.txt")));
+        assert!(paths.contains(&("--summary-json-append", "summary.json")));
+        assert_eq!(paths.len(), 3);
+        assert!(command_paths(&["not-a-command".to_string()]).is_empty());
+    }
 }
@@ -0,0 +1,85 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::macros::*;
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    sys, text,
+};
+use ethers::{
+    etherscan::{errors::EtherscanError, Client},
+    types::H160,
+};
+use eyre::{bail, eyre, Result};
+
+/// Environment variable consulted when `--explorer-api-key` is not passed.
+pub const EXPLORER_API_KEY_ENV: &str = "STYLUS_EXPLORER_API_KEY";
+
+/// Queries an Etherscan-compatible explorer, such as Arbiscan, for whether it lists
+/// verified source for the program at the given address.
+pub async fn check_explorer_verification(
+    api_url: &str,
+    api_key: Option<&str>,
+    program: H160,
+    verbose: bool,
+) -> Result<bool> {
+    let redact = |msg: String| match api_key {
+        Some(key) => text::redact(msg, key),
+        None => msg,
+    };
+
+    sys::ensure_network("querying the explorer API")?;
+    // The key may be embedded in the url as a query param.
+    let shown_url = redact(api_url.to_string());
+    if verbose {
+        greyln!("querying explorer API: {}", shown_url.lavender());
+    }
+    let client = Client::builder()
+        .with_url(api_url)
+        .and_then(|b| b.with_api_url(api_url))
+        .map(|b| b.with_api_key(api_key.unwrap_or_default()))
+        .and_then(|b| b.build())
+        .map_err(|e| {
+            eyre!(
+                "invalid explorer API url {shown_url}: {}",
+                redact(e.to_string())
+            )
+        })?;
+
+    match client.contract_source_code(program).await {
+        Ok(_) => {
+            greyln!(
+                "explorer lists verified source for program {}",
+                program.debug_lavender()
+            );
+            Ok(true)
+        }
+        Err(EtherscanError::ContractCodeNotVerified(_)) => {
+            greyln!(
+                "explorer has no verified source for program {}",
+                program.debug_lavender()
+            );
+            Ok(false)
+        }
+        Err(error) if requires_api_key(&error) => match api_key {
+            None => bail!(
+                "explorer at {shown_url} requires an API key, please pass --explorer-api-key \
+                 or set {EXPLORER_API_KEY_ENV}"
+            ),
+            Some(_) => bail!("explorer at {shown_url} rejected the provided API key"),
+        },
+        Err(error) => bail!("explorer request failed: {}", redact(error.to_string())),
+    }
+}
+
+/// Whether the explorer rejected a request because of a missing or invalid API key.
+fn requires_api_key(error: &EtherscanError) -> bool {
+    match error {
+        EtherscanError::InvalidApiKey => true,
+        EtherscanError::ErrorResponse {
+            result: Some(result),
+            ..
+        } => result.to_lowercase().contains("api key"),
+        _ => false,
+    }
+}
@@ -1,24 +1,51 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
-use crate::macros::*;
+use crate::{hardhat, macros::*, RegistryConfig};
+use alloy_json_abi::{AbiItem, JsonAbi};
 use cargo_stylus_util::{color::Color, sys};
-use eyre::{bail, Result, WrapErr};
+use ethers::types::H160;
+use eyre::{bail, eyre, Result, WrapErr};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
 use std::{
+    fs,
     io::Write,
     path::PathBuf,
     process::{self, Command, Stdio},
 };
 
 /// Exports Solidity ABIs by running the program natively.
-pub fn export_abi(file: Option<PathBuf>, json: bool) -> Result<()> {
-    if json && !sys::command_exists("solc") {
-        let link = "https://docs.soliditylang.org/en/latest/installing-solidity.html".red();
-        bail!("solc not found. Please see\n{link}");
+pub fn export_abi(file: Option<PathBuf>, json: bool, rust_alloy: bool) -> Result<()> {
+    let mut abi = solidity_abi()?;
+
+    // convert the ABI to a JSON file via solc, which handles any Solidity, or else directly
+    if json {
+        abi = match sys::command_exists("solc") {
+            true => json_abi(&abi)?,
+            false => {
+                egreyln!("solc not found, converting the ABI without it");
+                let items = json_abi_without_solc(&String::from_utf8_lossy(&abi))?;
+                to_json(&items)?
+            }
+        };
+    }
+
+    if rust_alloy {
+        let solidity = String::from_utf8_lossy(&abi);
+        abi = alloy_bindings(&solidity).into_bytes();
     }
 
+    let mut out = sys::file_or_stdout(file)?;
+    out.write_all(&abi)?;
+    Ok(())
+}
+
+/// Runs the program natively with the `export-abi` feature to get its Solidity interface.
+pub fn solidity_abi() -> Result<Vec<u8>> {
     let target = format!("--target={}", sys::host_arch()?);
-    let mut output = Command::new("cargo")
+    let output = Command::new("cargo")
         .stderr(Stdio::inherit())
         .arg("run")
         .arg("--features=export-abi")
@@ -33,24 +60,268 @@ pub fn export_abi(file: Option<PathBuf>, json: bool) -> Result<()> {
         egreyln!("failed to run program{out}");
         process::exit(1);
     }
+    Ok(output.stdout)
+}
 
-    // convert the ABI to a JSON file via solc
-    if json {
-        let solc = Command::new("solc")
-            .stdin(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .stdout(Stdio::piped())
-            .arg("--abi")
-            .arg("-")
-            .spawn()
-            .wrap_err("failed to run solc")?;
+/// Converts a Solidity interface to a JSON ABI with solc.
+pub fn json_abi(solidity: &[u8]) -> Result<Vec<u8>> {
+    let solc = Command::new("solc")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .arg("--abi")
+        .arg("-")
+        .spawn()
+        .wrap_err("failed to run solc")?;
 
-        let mut stdin = solc.stdin.as_ref().unwrap();
-        stdin.write_all(&output.stdout)?;
-        output = solc.wait_with_output()?;
-    }
+    let mut stdin = solc.stdin.as_ref().unwrap();
+    stdin.write_all(solidity)?;
+    Ok(solc.wait_with_output()?.stdout)
+}
 
-    let mut out = sys::file_or_stdout(file)?;
-    out.write_all(&output.stdout)?;
+/// An ABI wrapped for a contract registry, as written by `export-abi --registry-entry`.
+#[derive(Serialize, JsonSchema)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub version: String,
+    /// The contract's JSON ABI.
+    pub abi: Vec<Value>,
+    /// Address of the deployed contract, if given.
+    #[schemars(with = "Option<String>")]
+    pub address: Option<H160>,
+}
+
+/// Writes the program's JSON ABI wrapped in a registry entry, or adds it to a registry file.
+pub fn export_registry_entry(cfg: &RegistryConfig, file: Option<PathBuf>) -> Result<()> {
+    let solidity = solidity_abi()?;
+    let abi = match sys::command_exists("solc") {
+        true => hardhat::parse_solc_abi(&String::from_utf8_lossy(&json_abi(&solidity)?))?,
+        false => {
+            egreyln!("solc not found, converting the ABI without it");
+            json_abi_without_solc(&String::from_utf8_lossy(&solidity))?
+        }
+    };
+    let entry = RegistryEntry {
+        name: cfg.name.clone().unwrap_or_default(),
+        version: cfg.version.clone().unwrap_or_default(),
+        abi,
+        address: cfg.address,
+    };
+    let Some(path) = &cfg.merge_into else {
+        let mut out = sys::file_or_stdout(file)?;
+        out.write_all(&to_json(&entry)?)?;
+        return Ok(());
+    };
+    let mut registry: Vec<Value> = match path.exists() {
+        true => {
+            let text =
+                fs::read_to_string(path).wrap_err_with(|| format!("failed to read {path:?}"))?;
+            serde_json::from_str(&text)
+                .wrap_err_with(|| format!("{path:?} should hold a JSON array of entries"))?
+        }
+        false => vec![],
+    };
+    registry.retain(|other| other["name"] != entry.name || other["version"] != entry.version);
+    registry.push(serde_json::to_value(&entry)?);
+    fs::write(path, to_json(&registry)?).wrap_err_with(|| format!("failed to write {path:?}"))?;
+    egreyln!(
+        "added {} {} to {}",
+        entry.name,
+        entry.version,
+        path.to_string_lossy()
+    );
     Ok(())
 }
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut json = serde_json::to_vec_pretty(value)?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+/// Converts an exported Solidity interface to a JSON ABI array, giving each function and error
+/// its selector. Unlike solc's output, struct types are expanded into tuples with unnamed
+/// components.
+pub fn json_abi_without_solc(solidity: &str) -> Result<Vec<Value>> {
+    let mut text = strip_comments(solidity);
+    let mut structs: Vec<(String, String)> = vec![];
+    while let Some(start) = text.find("struct ") {
+        let Some(len) = text[start..].find('}') else {
+            bail!("unterminated struct in the Solidity interface");
+        };
+        let def = &text[start + "struct ".len()..start + len];
+        let Some((name, fields)) = def.split_once('{') else {
+            bail!("malformed struct in the Solidity interface: {def}");
+        };
+        // tuple types can't name their components, so only the field types are kept
+        let fields: Vec<_> = fields
+            .split(';')
+            .filter_map(|field| field.split_whitespace().next())
+            .collect();
+        structs.push((name.trim().to_string(), format!("({})", fields.join(", "))));
+        text.replace_range(start..=start + len, "");
+    }
+
+    let mut items = vec![];
+    for statement in text.split(';') {
+        let mut item = statement.trim();
+        // drop everything up to the last brace, such as `interface I {` or a previous `}`
+        if let Some(brace) = item.rfind(['{', '}']) {
+            item = item[brace + 1..].trim();
+        }
+        if item.is_empty() || item.starts_with("pragma ") {
+            continue;
+        }
+        items.push(expand_structs(item, &structs)?);
+    }
+
+    let abi = JsonAbi::parse(items.iter().map(String::as_str))
+        .map_err(|error| eyre!("failed to parse the Solidity interface: {error}"))?;
+    let mut json = vec![];
+    for item in abi.items() {
+        let selector = match &item {
+            AbiItem::Function(function) => Some(function.selector()),
+            AbiItem::Error(error) => Some(error.selector()),
+            _ => None,
+        };
+        let mut value = serde_json::to_value(&item)?;
+        if let Some(selector) = selector {
+            value["selector"] = selector.to_string().into();
+        }
+        json.push(value);
+    }
+    Ok(json)
+}
+
+/// Replaces each struct named in a signature with the tuple of its fields, which may
+/// themselves be structs.
+fn expand_structs(signature: &str, structs: &[(String, String)]) -> Result<String> {
+    let mut signature = signature.to_string();
+    for _ in 0..=structs.len() {
+        let mut changed = false;
+        for (name, tuple) in structs {
+            let words: Vec<_> = signature
+                .split_inclusive(|c: char| !c.is_alphanumeric() && c != '_')
+                .map(
+                    |word| match word.strip_suffix(|c: char| !c.is_alphanumeric() && c != '_') {
+                        Some(stem) if stem == name => {
+                            changed = true;
+                            format!("{tuple}{}", &word[stem.len()..])
+                        }
+                        None if word == name => {
+                            changed = true;
+                            tuple.clone()
+                        }
+                        _ => word.to_string(),
+                    },
+                )
+                .collect();
+            signature = words.concat();
+        }
+        if !changed {
+            return Ok(signature);
+        }
+    }
+    bail!("recursive struct in the Solidity interface: {signature}")
+}
+
+/// Removes `//` and `/* */` comments from Solidity source.
+fn strip_comments(solidity: &str) -> String {
+    let mut text = String::new();
+    let mut rest = solidity;
+    loop {
+        let line = rest.find("//");
+        let block = rest.find("/*");
+        let (start, end) = match (line, block) {
+            (Some(line), Some(block)) if block < line => (block, "*/"),
+            (_, Some(block)) if line.is_none() => (block, "*/"),
+            (Some(line), _) => (line, "\n"),
+            (None, _) => break,
+        };
+        text += &rest[..start];
+        rest = match rest[start..].find(end) {
+            Some(len) => &rest[start + len + end.len()..],
+            None => "",
+        };
+        text.push(' ');
+    }
+    text + rest
+}
+
+/// Wraps an exported Solidity interface in an `alloy::sol!` invocation.
+///
+/// The macro parses Solidity directly, so only the header of doc comments and pragmas is
+/// dropped, since those aren't valid inside the macro.
+fn alloy_bindings(solidity: &str) -> String {
+    let mut in_comment = false;
+    let body: Vec<&str> = solidity
+        .lines()
+        .skip_while(|line| {
+            let line = line.trim();
+            if in_comment {
+                in_comment = !line.ends_with("*/");
+                return true;
+            }
+            if line.starts_with("/*") {
+                in_comment = !line.ends_with("*/");
+                return true;
+            }
+            line.is_empty() || line.starts_with("//") || line.starts_with("pragma ")
+        })
+        .collect();
+
+    let mut bindings = String::new();
+    bindings += "// Generated by `cargo stylus export-abi --rust-alloy`.\n\n";
+    bindings += "alloy::sol! {\n";
+    for line in body {
+        match line.is_empty() {
+            true => bindings += "\n",
+            false => bindings += &format!("    {line}\n"),
+        }
+    }
+    bindings += "}\n";
+    bindings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alloy_bindings() {
+        let solidity = "/**\n * Generated by Stylus.\n */\n\n\
+            // SPDX-License-Identifier: MIT\npragma solidity ^0.8.23;\n\n\
+            interface ICounter {\n    function number() external view returns (uint256);\n}\n";
+        let bindings = alloy_bindings(solidity);
+        assert!(bindings.contains("alloy::sol! {\n    interface ICounter {\n"));
+        assert!(!bindings.contains("pragma"));
+        assert!(!bindings.contains("SPDX"));
+        assert!(bindings.ends_with("    }\n}\n"));
+    }
+
+    #[test]
+    fn test_json_abi_without_solc() -> Result<()> {
+        let solidity = "/**\n * Generated by Stylus.\n */\n\n\
+            // SPDX-License-Identifier: MIT\npragma solidity ^0.8.23;\n\n\
+            interface IBook {\n    function number() external view returns (uint256);\n\n\
+            \x20   function add(Entry calldata entry, uint8[] memory tags) external payable; // adds\n\n\
+            \x20   error Full(uint256 size);\n\n\
+            \x20   struct Entry {\n        address owner;\n        Page page;\n    }\n\n\
+            \x20   struct Page {\n        string text;\n    }\n}\n";
+        let json = json_abi_without_solc(solidity)?;
+        let names: Vec<_> = json.iter().map(|item| item["name"].clone()).collect();
+        assert_eq!(names, ["add", "number", "Full"]);
+
+        let add = &json[0];
+        assert_eq!(add["stateMutability"], "payable");
+        let selector = &alloy_primitives::keccak256("add((address,(string)),uint8[])")[..4];
+        assert_eq!(add["selector"], format!("0x{}", hex::encode(selector)));
+        let entry = &add["inputs"][0];
+        assert_eq!(entry["type"], "tuple");
+        assert_eq!(entry["components"][1]["components"][0]["type"], "string");
+        assert_eq!(add["inputs"][1]["type"], "uint8[]");
+        assert_eq!(json[1]["outputs"][0]["type"], "uint256");
+        assert_eq!(json[2]["type"], "error");
+        Ok(())
+    }
+}
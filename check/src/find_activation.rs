@@ -0,0 +1,144 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{constants::ARB_WASM_H160, macros::*, FindActivationConfig};
+use alloy_primitives::{keccak256, Address};
+use alloy_sol_macro::sol;
+use alloy_sol_types::SolEvent;
+use cargo_stylus_util::color::{Color, DebugColor};
+use ethers::{
+    providers::Middleware,
+    types::{Filter, H256, U256},
+};
+use eyre::{bail, eyre, Result, WrapErr};
+
+sol! {
+    interface ArbWasm {
+        event ProgramActivated(
+            bytes32 indexed codehash,
+            bytes32 moduleHash,
+            address program,
+            uint256 dataFee,
+            uint16 version
+        );
+    }
+}
+
+/// Most blocks scanned by a single `eth_getLogs` request, since many providers cap the range.
+const LOG_SCAN_CHUNK_BLOCKS: u64 = 10_000;
+
+/// An activation of a codehash, as logged by ArbWasm.
+pub struct Activation {
+    pub tx_hash: H256,
+    pub block: u64,
+    /// The program whose activation activated the codehash, which may be another deployment of
+    /// the same code.
+    pub program: Address,
+    pub module_hash: H256,
+    /// The data fee charged in wei.
+    pub data_fee: U256,
+    pub version: u16,
+}
+
+/// Finds ArbWasm's activations of a codehash between two blocks, oldest first.
+///
+/// Costs one `eth_getLogs` request per 10,000 blocks in the range.
+pub async fn scan_activations<M: Middleware>(
+    provider: &M,
+    codehash: [u8; 32],
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Activation>> {
+    let mut activations = vec![];
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start + LOG_SCAN_CHUNK_BLOCKS - 1);
+        let filter = Filter::new()
+            .address(*ARB_WASM_H160)
+            .topic0(H256(ArbWasm::ProgramActivated::SIGNATURE_HASH.0))
+            .topic1(H256(codehash))
+            .from_block(start)
+            .to_block(end);
+        let logs = provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| eyre!("failed to get logs for blocks {start} to {end}: {e}"))?;
+
+        for log in logs {
+            let topics = log.topics.iter().map(|topic| topic.0);
+            let event = ArbWasm::ProgramActivated::decode_raw_log(topics, &log.data, true)
+                .wrap_err("failed to decode activation event")?;
+            activations.push(Activation {
+                tx_hash: log.transaction_hash.unwrap_or_default(),
+                block: log.block_number.unwrap_or_default().as_u64(),
+                program: event.program,
+                module_hash: H256(event.moduleHash.0),
+                data_fee: alloy_ethers_typecast::alloy_u256_to_ethers(event.dataFee),
+                version: event.version,
+            });
+        }
+        start = end + 1;
+    }
+    Ok(activations)
+}
+
+/// Scans ArbWasm's activation events for those of the program's code hash.
+///
+/// Costs one `eth_getLogs` request per 10,000 blocks in the range, so narrow it with
+/// `--from-block` when the deployment block is known.
+pub async fn find_activation(cfg: &FindActivationConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let code = provider
+        .get_code(cfg.program_address, None)
+        .await
+        .wrap_err("failed to fetch program code")?;
+    if code.is_empty() {
+        bail!("no code at address {}", cfg.program_address.debug_red());
+    }
+    let codehash = keccak256(&code);
+
+    let to_block = match cfg.to_block {
+        Some(block) => block,
+        None => provider
+            .get_block_number()
+            .await
+            .wrap_err("failed to get block number")?
+            .as_u64(),
+    };
+    if cfg.from_block > to_block {
+        bail!(
+            "--from-block {} is after --to-block {to_block}",
+            cfg.from_block
+        );
+    }
+    greyln!(
+        "scanning blocks {} to {to_block} for activations of codehash {}",
+        cfg.from_block,
+        codehash.lavender()
+    );
+
+    let program = Address::from(cfg.program_address.to_fixed_bytes());
+    let activations = scan_activations(&provider, codehash.0, cfg.from_block, to_block).await?;
+    for activation in &activations {
+        let via = match activation.program == program {
+            true => String::new(),
+            false => format!(" {} {}", "via".grey(), activation.program.debug_yellow()),
+        };
+        println!(
+            "activation tx {} in block {} (stylus version {}, data fee {} wei){via}",
+            activation.tx_hash.debug_mint(),
+            activation.block,
+            activation.version,
+            activation.data_fee
+        );
+    }
+
+    if activations.is_empty() {
+        bail!(
+            "no activation of codehash {} found in blocks {} to {to_block}",
+            codehash.red(),
+            cfg.from_block
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,156 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::macros::*;
+use cargo_stylus_util::{color::Color, sys};
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, FeeHistory, U256},
+};
+use eyre::{bail, eyre, Result, WrapErr};
+use serde_json::Value;
+use std::time::Duration;
+
+/// How many recent blocks `--auto-gas` reads the fee history of.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Percentile of each block's priority fees that `--auto-gas` tips at.
+const TIP_PERCENTILE: f64 = 50.;
+
+/// Fees suggested by an external gas oracle, in wei.
+///
+/// Oracles must answer a `GET` request with a JSON object of the form
+/// `{"maxFeePerGas": "100000000", "maxPriorityFeePerGas": "0"}`, where each amount is
+/// a JSON integer or a decimal or `0x`-prefixed hex string.
+#[derive(Debug, PartialEq)]
+pub struct OracleFees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Fetches suggested fees from the gas oracle at the given url.
+pub async fn fetch_fees(url: &str, timeout: Duration) -> Result<OracleFees> {
+    sys::ensure_network("querying the gas oracle")?;
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .wrap_err("failed to init http client")?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .wrap_err_with(|| format!("gas oracle request to {url} failed"))?;
+    let body = response
+        .text()
+        .await
+        .wrap_err("failed to read gas oracle response")?;
+    parse_fees(&body)
+}
+
+fn parse_fees(body: &str) -> Result<OracleFees> {
+    let json: Value = serde_json::from_str(body).wrap_err("gas oracle response is not JSON")?;
+    let field = |name: &str| -> Result<U256> {
+        let amount = match json.get(name) {
+            Some(Value::String(text)) => match text.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16).ok(),
+                None => U256::from_dec_str(text).ok(),
+            },
+            Some(Value::Number(number)) => number.as_u64().map(U256::from),
+            Some(_) => None,
+            None => bail!("gas oracle response is missing {name}"),
+        };
+        amount.ok_or_else(|| eyre!("gas oracle response has an invalid {name}"))
+    };
+    let fees = OracleFees {
+        max_fee_per_gas: field("maxFeePerGas")?,
+        max_priority_fee_per_gas: field("maxPriorityFeePerGas")?,
+    };
+    if fees.max_priority_fee_per_gas > fees.max_fee_per_gas {
+        bail!("gas oracle suggests a priority fee above its max fee");
+    }
+    Ok(fees)
+}
+
+/// Fees for `--auto-gas`, from the node's fee history or else its gas price. The max fee is
+/// scaled by `multiplier`, while the priority fee is the median of recent blocks' tips, or left
+/// for the node to suggest when there's no fee history.
+pub async fn auto_fees<M: Middleware>(client: &M, multiplier: f64) -> Result<(U256, Option<U256>)> {
+    let history = client
+        .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &[TIP_PERCENTILE])
+        .await;
+    if let Ok(Some(fees)) = history.as_ref().map(|h| fees_from_history(h, multiplier)) {
+        return Ok((fees.max_fee_per_gas, Some(fees.max_priority_fee_per_gas)));
+    }
+    greyln!(
+        "{}",
+        "the node has no fee history, basing --auto-gas on eth_gasPrice instead".yellow()
+    );
+    let price = client
+        .get_gas_price()
+        .await
+        .map_err(|e| eyre!("failed to get gas price: {e}"))?;
+    Ok((scale(price, multiplier), None))
+}
+
+/// Computes a max fee from the next block's base fee plus the median of recent blocks' tips,
+/// scaled by `multiplier`.
+fn fees_from_history(history: &FeeHistory, multiplier: f64) -> Option<OracleFees> {
+    let base_fee = *history.base_fee_per_gas.last()?;
+    let mut tips: Vec<U256> = history
+        .reward
+        .iter()
+        .filter_map(|r| r.first().copied())
+        .collect();
+    tips.sort();
+    let tip = tips.get(tips.len() / 2).copied().unwrap_or_default();
+    Some(OracleFees {
+        max_fee_per_gas: scale(base_fee + tip, multiplier),
+        max_priority_fee_per_gas: tip,
+    })
+}
+
+fn scale(amount: U256, multiplier: f64) -> U256 {
+    amount * U256::from((multiplier * 1000.).round() as u64) / 1000
+}
+
+/// Parses `--gas-multiplier`, which mustn't lower the estimate.
+pub fn parse_multiplier(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(multiplier) if (1.0..=100.0).contains(&multiplier) => Ok(multiplier),
+        _ => Err(format!(
+            "invalid multiplier {text}, expected a number from 1 to 100"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_fees() {
+        let fees = parse_fees(r#"{"maxFeePerGas": "0x64", "maxPriorityFeePerGas": 10}"#).unwrap();
+        assert_eq!(fees.max_fee_per_gas, U256::from(100));
+        assert_eq!(fees.max_priority_fee_per_gas, U256::from(10));
+
+        assert!(parse_fees(r#"{"maxFeePerGas": "100"}"#).is_err());
+        assert!(parse_fees(r#"{"maxFeePerGas": "1", "maxPriorityFeePerGas": "2"}"#).is_err());
+        assert!(parse_fees("<html>bad gateway</html>").is_err());
+    }
+
+    #[test]
+    fn test_fees_from_history() {
+        let history = FeeHistory {
+            base_fee_per_gas: vec![U256::from(90), U256::from(100)],
+            gas_used_ratio: vec![0.5; 3],
+            oldest_block: U256::one(),
+            reward: [3, 1, 2].map(|tip| vec![U256::from(tip)]).to_vec(),
+        };
+        let fees = fees_from_history(&history, 1.2).unwrap();
+        assert_eq!(fees.max_priority_fee_per_gas, U256::from(2));
+        assert_eq!(fees.max_fee_per_gas, U256::from(122));
+        assert_eq!(scale(U256::from(1000), 1.0), U256::from(1000));
+        assert!(parse_multiplier("0.5").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{deploy::program_deployment_calldata, export_abi, macros::*};
+use cargo_stylus_util::{color::Color, sys};
+use ethers::types::{H160, H256};
+use eyre::{Result, WrapErr};
+use serde::Serialize;
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+
+/// Directory hardhat-deploy keeps its deployments in, relative to the project.
+const DEPLOYMENTS_DIR: &str = "deployments";
+
+/// A deployment in the format hardhat-deploy reads from `deployments/<network>/<Name>.json`.
+/// Only the fields cargo stylus knows are populated.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardhatDeployment {
+    address: String,
+    abi: Vec<Value>,
+    transaction_hash: H256,
+    /// The `--constructor-args` the initializer was called with.
+    args: Vec<Value>,
+    num_deployments: u64,
+    /// The deployment tx's init code.
+    bytecode: String,
+    /// The compressed WASM stored onchain.
+    deployed_bytecode: String,
+}
+
+impl HardhatDeployment {
+    pub fn new(
+        address: H160,
+        abi: Vec<Value>,
+        args: &[String],
+        tx_hash: H256,
+        code: &[u8],
+    ) -> Self {
+        Self {
+            address: ethers::utils::to_checksum(&address, None),
+            abi,
+            transaction_hash: tx_hash,
+            args: args.iter().cloned().map(Value::String).collect(),
+            num_deployments: 1,
+            bytecode: format!("0x{}", hex::encode(program_deployment_calldata(code))),
+            deployed_bytecode: format!("0x{}", hex::encode(code)),
+        }
+    }
+}
+
+/// Exports the project's JSON ABI for its hardhat-deploy deployment. This runs before
+/// deploying so that a failing export doesn't interrupt a deployment after it's been sent.
+pub fn abi() -> Result<Vec<Value>> {
+    let solidity = export_abi::solidity_abi()?;
+    if !sys::command_exists("solc") {
+        greyln!("solc not found, converting the ABI for hardhat-deploy without it");
+        return export_abi::json_abi_without_solc(&String::from_utf8_lossy(&solidity));
+    }
+    let output = export_abi::json_abi(&solidity)?;
+    parse_solc_abi(&String::from_utf8_lossy(&output))
+}
+
+/// Joins the ABIs of every contract in the output of `solc --abi`.
+pub fn parse_solc_abi(output: &str) -> Result<Vec<Value>> {
+    let mut abi = vec![];
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() != "Contract JSON ABI" {
+            continue;
+        }
+        let json = lines.next().unwrap_or_default();
+        let items: Vec<Value> =
+            serde_json::from_str(json).wrap_err("failed to parse solc's JSON ABI")?;
+        abi.extend(items);
+    }
+    Ok(abi)
+}
+
+/// Writes `deployments/<network>/<name>.json`, along with the `.chainId` file hardhat-deploy
+/// expects next to it.
+pub fn write_deployment(
+    network: &str,
+    name: &str,
+    chain_id: u64,
+    mut deployment: HardhatDeployment,
+) -> Result<()> {
+    let dir = PathBuf::from(DEPLOYMENTS_DIR).join(network);
+    fs::create_dir_all(&dir).wrap_err_with(|| format!("failed to create {dir:?}"))?;
+    let chain_id_file = dir.join(".chainId");
+    if !chain_id_file.exists() {
+        fs::write(&chain_id_file, chain_id.to_string())
+            .wrap_err_with(|| format!("failed to write {chain_id_file:?}"))?;
+    }
+
+    let path = dir.join(format!("{name}.json"));
+    let previous = fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+        .and_then(|json| json.get("numDeployments").and_then(Value::as_u64))
+        .unwrap_or_default();
+    deployment.num_deployments = previous + 1;
+    let json = serde_json::to_string_pretty(&deployment)?;
+    fs::write(&path, json).wrap_err_with(|| format!("failed to write {path:?}"))?;
+    greyln!(
+        "wrote hardhat-deploy deployment to {}",
+        path.to_string_lossy().lavender()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_solc_abi() -> Result<()> {
+        let output = "\n======= <stdin>:ICounter =======\nContract JSON ABI\n\
+                      [{\"type\":\"function\",\"name\":\"number\"}]\n\n\
+                      ======= <stdin>:IOwnable =======\nContract JSON ABI\n\
+                      [{\"type\":\"event\",\"name\":\"OwnershipTransferred\"}]\n";
+        let abi = parse_solc_abi(output)?;
+        assert_eq!(abi.len(), 2);
+        assert_eq!(abi[1]["name"], "OwnershipTransferred");
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    check::{eth_call, EthCallError},
+    constants::{ARB_WASM_H160, EOF_PREFIX_NO_DICT, PRELUDE_VERSIONS, STYLUS_VERSIONS},
+    deploy::parse_deployment_prelude,
+    InspectVersionConfig,
+};
+use alloy_primitives::Address;
+use alloy_sol_macro::sol;
+use alloy_sol_types::{SolCall, SolInterface};
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    rpc::RpcClient,
+};
+use ethers::{
+    providers::{Middleware, Provider},
+    types::{spoof::State, Eip1559TransactionRequest},
+};
+use eyre::{bail, eyre, Result, WrapErr};
+
+sol! {
+    interface ArbWasm {
+        function stylusVersion() external view returns (uint16 version);
+
+        function programVersion(address program) external view returns (uint16 version);
+
+        error ProgramNotWasm();
+        error ProgramNotActivated();
+        error ProgramNeedsUpgrade(uint16 version, uint16 stylusVersion);
+        error ProgramExpired(uint64 ageInSeconds);
+    }
+}
+
+/// Prints which Stylus version a deployed program targets and what that expects of the chain.
+///
+/// Onchain code doesn't keep the deployment prelude, so its version byte is only read when the
+/// deployment tx is given.
+pub async fn inspect_version(cfg: &InspectVersionConfig) -> Result<()> {
+    let provider = cfg.common_cfg.provider()?;
+    let code = provider
+        .get_code(cfg.program_address, None)
+        .await
+        .wrap_err("failed to fetch program code")?;
+    if !code.starts_with(&hex::decode(EOF_PREFIX_NO_DICT).unwrap()) {
+        bail!(
+            "code at {} does not start with Stylus prefix {EOF_PREFIX_NO_DICT}",
+            cfg.program_address.debug_red()
+        );
+    }
+
+    if let Some(tx_hash) = cfg.deployment_tx {
+        let tx = provider
+            .get_transaction(tx_hash)
+            .await
+            .wrap_err("failed to get deployment tx")?
+            .ok_or_else(|| eyre!("no transaction found for hash {tx_hash:?}"))?;
+        let prelude = parse_deployment_prelude(&tx.input)
+            .wrap_err("deployment tx doesn't start with a Stylus prelude")?;
+        let expects = PRELUDE_VERSIONS
+            .iter()
+            .find(|(version, _)| *version == prelude.version)
+            .map_or("unknown to this version of cargo stylus", |(_, expects)| {
+                expects
+            });
+        println!(
+            "prelude version byte: {} (expects {expects})",
+            prelude.version.mint()
+        );
+    }
+
+    let current = stylus_version(&provider).await?;
+    let program = Address::from(cfg.program_address.to_fixed_bytes());
+    let data = ArbWasm::programVersionCall { program }.abi_encode();
+    let tx = Eip1559TransactionRequest::new()
+        .to(*ARB_WASM_H160)
+        .data(data);
+    match eth_call(tx, State::default(), &provider).await? {
+        Ok(outs) => {
+            let ArbWasm::programVersionReturn { version } =
+                ArbWasm::programVersionCall::abi_decode_returns(&outs, true)?;
+            println!(
+                "activated for Stylus version {} ({})",
+                version.mint(),
+                introduced_in(version)
+            );
+        }
+        Err(EthCallError { data, msg }) => {
+            use ArbWasm::ArbWasmErrors as A;
+            match A::abi_decode(&data, true) {
+                Ok(A::ProgramNotActivated(_)) => println!("{}", "not activated".yellow()),
+                Ok(A::ProgramNeedsUpgrade(e)) => println!(
+                    "activated for Stylus version {} ({}), which needs reactivating",
+                    e.version.yellow(),
+                    introduced_in(e.version)
+                ),
+                Ok(A::ProgramExpired(e)) => println!(
+                    "{} {} seconds after activation, reactivate it to use it again",
+                    "expired".yellow(),
+                    e.ageInSeconds
+                ),
+                Ok(A::ProgramNotWasm(_)) => bail!("not a Stylus program"),
+                Err(_) => bail!("unknown ArbWasm error: {msg}"),
+            }
+        }
+    }
+    println!(
+        "chain's current Stylus version: {} ({})",
+        current.mint(),
+        introduced_in(current)
+    );
+    Ok(())
+}
+
+async fn stylus_version(provider: &Provider<RpcClient>) -> Result<u16> {
+    let data = ArbWasm::stylusVersionCall {}.abi_encode();
+    let tx = Eip1559TransactionRequest::new()
+        .to(*ARB_WASM_H160)
+        .data(data);
+    let outs = eth_call(tx, State::default(), provider).await??;
+    let ArbWasm::stylusVersionReturn { version } =
+        ArbWasm::stylusVersionCall::abi_decode_returns(&outs, true)?;
+    Ok(version)
+}
+
+/// Describes the ArbOS release a Stylus version needs.
+fn introduced_in(version: u16) -> String {
+    match STYLUS_VERSIONS.iter().find(|(v, _)| *v == version) {
+        Some((_, arbos)) => format!("needs {arbos} or later"),
+        None => "unknown to this version of cargo stylus".into(),
+    }
+}
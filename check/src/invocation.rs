@@ -0,0 +1,205 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    constants::{RUST_TARGET, TOOLCHAIN_FILE_NAME},
+    macros::*,
+    project::{self, BuildConfig},
+};
+use cargo_stylus_util::color::Color;
+use eyre::{bail, eyre, Result, WrapErr};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Environment variables that can change what cargo builds, besides those named by
+/// [`ENV_PREFIXES`].
+const ENV_NAMES: &[&str] = &["AR", "CC", "CFLAGS", "SOURCE_DATE_EPOCH"];
+
+/// Prefixes of the environment variables cargo and rustc read.
+const ENV_PREFIXES: &[&str] = &["CARGO", "RUST"];
+
+/// Words that mark a variable as holding a credential, such as `CARGO_REGISTRY_TOKEN`.
+const SECRET_WORDS: &[&str] = &["AUTH", "CREDENTIAL", "KEY", "PASSWORD", "SECRET", "TOKEN"];
+
+/// Exactly how cargo was run to build a program, as written by `--build-invocation`.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInvocation {
+    /// Version of cargo stylus that ran the build.
+    pub cargo_stylus_version: String,
+    /// The cargo command, starting with the program name.
+    pub command: Vec<String>,
+    /// Directory the command ran in.
+    pub working_directory: PathBuf,
+    /// Build-related environment variables the command saw, with credentials redacted.
+    pub env: BTreeMap<String, String>,
+    /// The toolchain that ran the build.
+    pub toolchain: Toolchain,
+    /// The target triple the program was compiled for.
+    pub target: String,
+    /// Features enabled with `--features` in the cargo args.
+    pub features: Vec<String>,
+    /// Whether the crate's default features were enabled.
+    pub default_features: bool,
+    /// Whether all the crate's features were enabled.
+    pub all_features: bool,
+}
+
+/// The Rust toolchain that ran a build.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Toolchain {
+    /// Channel of the project's Rust toolchain file, if it has one.
+    pub channel: Option<String>,
+    /// Output of `cargo --version`.
+    pub cargo_version: String,
+    /// Output of `rustc --version`.
+    pub rustc_version: String,
+}
+
+impl BuildInvocation {
+    /// Describes the cargo command that builds the project in `cwd` with the given config.
+    pub fn new(cfg: &BuildConfig, cwd: &Path) -> Result<Self> {
+        let cargo_version = project::cargo_version(cfg)?;
+        let mut rustc = Command::new("rustc");
+        if !cfg.stable {
+            rustc.arg("+nightly");
+        }
+        let output = rustc
+            .arg("--version")
+            .output()
+            .map_err(|e| eyre!("failed to execute rustc: {e}"))?;
+        if !output.status.success() {
+            bail!("rustc version command failed");
+        }
+        let channel = project::extract_toolchain_channel(&cwd.join(TOOLCHAIN_FILE_NAME)).ok();
+
+        let mut env = build_env(env::vars());
+        if let Some(wrapper) = &cfg.rustc_wrapper {
+            env.insert("RUSTC_WRAPPER".into(), wrapper.to_string_lossy().into());
+        }
+        let mut command = vec!["cargo".to_string()];
+        command.extend(project::build_args(cfg));
+        let features = Features::parse(&cfg.cargo_args);
+        Ok(Self {
+            cargo_stylus_version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            working_directory: cwd.to_path_buf(),
+            env,
+            toolchain: Toolchain {
+                channel,
+                cargo_version: String::from_utf8_lossy(&cargo_version).trim().to_string(),
+                rustc_version: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            },
+            target: RUST_TARGET.to_string(),
+            features: features.enabled,
+            default_features: features.default,
+            all_features: features.all,
+        })
+    }
+
+    /// Writes the invocation as pretty JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n").wrap_err_with(|| format!("failed to write {path:?}"))?;
+        greyln!(
+            "wrote build invocation to {}",
+            path.to_string_lossy().lavender()
+        );
+        Ok(())
+    }
+}
+
+/// Picks the variables that affect a build, redacting the values of any that look secret.
+fn build_env(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    vars.filter(|(name, _)| {
+        ENV_NAMES.contains(&name.as_str()) || ENV_PREFIXES.iter().any(|p| name.starts_with(p))
+    })
+    .map(|(name, value)| {
+        let upper = name.to_uppercase();
+        match SECRET_WORDS.iter().any(|word| upper.contains(word)) {
+            true => (name, "<redacted>".into()),
+            false => (name, value),
+        }
+    })
+    .collect()
+}
+
+/// The features selected by a list of cargo args.
+struct Features {
+    enabled: Vec<String>,
+    default: bool,
+    all: bool,
+}
+
+impl Features {
+    fn parse(args: &[String]) -> Self {
+        let mut features = Self {
+            enabled: vec![],
+            default: true,
+            all: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let list = match arg.as_str() {
+                "--features" | "-F" => args.next().map(String::as_str),
+                "--no-default-features" => {
+                    features.default = false;
+                    None
+                }
+                "--all-features" => {
+                    features.all = true;
+                    None
+                }
+                arg => arg.strip_prefix("--features=").or(arg.strip_prefix("-F")),
+            };
+            let names = list.into_iter().flat_map(|list| list.split([',', ' ']));
+            features
+                .enabled
+                .extend(names.filter(|n| !n.is_empty()).map(String::from));
+        }
+        features
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_invocation_inputs() {
+        let args: Vec<String> = ["--features", "a,b", "--no-default-features", "-Fc"]
+            .map(String::from)
+            .into();
+        let features = Features::parse(&args);
+        assert_eq!(features.enabled, ["a", "b", "c"]);
+        assert!(!features.default);
+        assert!(!features.all);
+
+        let vars = [
+            ("RUSTFLAGS", "-C debuginfo=0"),
+            ("CARGO_REGISTRIES_CORP_TOKEN", "hunter2"),
+            ("HOME", "/root"),
+            ("SOURCE_DATE_EPOCH", "1"),
+        ];
+        let env = build_env(
+            vars.map(|(k, v)| (k.to_string(), v.to_string()))
+                .into_iter(),
+        );
+        let env: Vec<_> = env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(
+            env,
+            [
+                ("CARGO_REGISTRIES_CORP_TOKEN", "<redacted>"),
+                ("RUSTFLAGS", "-C debuginfo=0"),
+                ("SOURCE_DATE_EPOCH", "1"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{macros::*, wallet::SignerError};
+use cargo_stylus_util::color::{Color, DebugColor};
+use coins_ledger::{
+    common::APDUResponseCodes as Code, errors::LedgerError as TransportError,
+    transports::hid::NativeTransportError,
+};
+use ethers::{
+    signers::{HDPath, Ledger, LedgerError, Signer},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Signature,
+    },
+};
+use eyre::{eyre, Result};
+use std::time::Duration;
+
+/// How long to wait for the user to confirm a tx on the device.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A Ledger running the Ethereum app, which asks for each signature to be confirmed on the
+/// device.
+#[derive(Debug)]
+pub struct LedgerSigner(pub Ledger);
+
+/// Connects to the Ledger account at `m/44'/60'/{index}'/0/0`, the path Ledger Live uses.
+pub async fn connect(index: usize, chain_id: u64) -> Result<LedgerSigner> {
+    let ledger = Ledger::new(HDPath::LedgerLive(index), chain_id)
+        .await
+        .map_err(|error| eyre!(describe(error)))?;
+    greyln!(
+        "using Ledger account {} {}",
+        index,
+        ledger.address().debug_lavender()
+    );
+    Ok(LedgerSigner(ledger))
+}
+
+impl LedgerSigner {
+    pub async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        greyln!(
+            "{}",
+            "confirm the tx on your Ledger (waiting up to 2 minutes)".yellow()
+        );
+        let signature = tokio::time::timeout(CONFIRM_TIMEOUT, self.0.sign_transaction(tx));
+        match signature.await {
+            Ok(signature) => signature.map_err(|error| SignerError::Ledger(describe(error))),
+            Err(_) => Err(SignerError::Ledger(
+                "timed out waiting for the tx to be confirmed on the Ledger".into(),
+            )),
+        }
+    }
+
+    pub async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, SignerError> {
+        greyln!("{}", "confirm the message on your Ledger".yellow());
+        let signature = self.0.sign_message(message).await;
+        signature.map_err(|error| SignerError::Ledger(describe(error)))
+    }
+
+    pub async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, SignerError> {
+        let signature = Signer::sign_typed_data(&self.0, payload).await;
+        signature.map_err(|error| SignerError::Ledger(describe(error)))
+    }
+}
+
+/// Explains the device states behind common Ledger errors, which otherwise surface as raw
+/// HID errors or APDU status codes.
+fn describe(error: LedgerError) -> String {
+    let LedgerError::LedgerError(transport) = &error else {
+        return format!("Ledger error: {error}");
+    };
+    match transport {
+        TransportError::NativeTransportError(NativeTransportError::DeviceNotFound) => {
+            "no Ledger found, check that it's plugged in and unlocked".into()
+        }
+        TransportError::UnknownAPDUCode(0x5515)
+        | TransportError::BadRetcode(Code::UnlockDeviceError) => {
+            "the Ledger is locked, unlock it and try again".into()
+        }
+        TransportError::UnknownAPDUCode(0x6511)
+        | TransportError::BadRetcode(Code::ClaNotSupported | Code::InsNotSupported) => {
+            "the Ethereum app isn't open on the Ledger, open it and try again".into()
+        }
+        TransportError::BadRetcode(Code::ConditionsNotSatisfied) => {
+            "the request was rejected on the Ledger".into()
+        }
+        _ => format!("Ledger error: {error}"),
+    }
+}
@@ -4,15 +4,30 @@
 macro_rules! greyln {
     ($($msg:expr),*) => {{
         let msg = format!($($msg),*);
-        println!("{}", msg.grey())
+        crate::output::diagnostic(msg.grey())
+    }};
+}
+
+/// Prints an uncolored diagnostic line, which goes wherever `greyln!` output does.
+macro_rules! infoln {
+    ($($msg:expr),*) => {{
+        crate::output::diagnostic(format!($($msg),*))
     }};
 }
 
 macro_rules! egreyln {
     ($($msg:expr),*) => {{
         let msg = format!($($msg),*);
-        eprintln!("{}", msg.grey())
+        eprintln!("{}", crate::output::stamp(msg.grey()))
+    }};
+}
+
+/// Emits a warning of the given [`Warning`](crate::warnings::Warning) kind, failing if it was
+/// passed to `--deny`.
+macro_rules! warnln {
+    ($warning:ident, $($msg:expr),*) => {{
+        crate::warnings::warn(crate::warnings::Warning::$warning, format!($($msg),*))
     }};
 }
 
-pub(crate) use {egreyln, greyln};
+pub(crate) use {egreyln, greyln, infoln, warnln};
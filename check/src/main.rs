@@ -1,23 +1,55 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
-use clap::{ArgGroup, Args, Parser};
-use ethers::types::{H160, U256};
-use eyre::{eyre, Context, Result};
-use std::path::PathBuf;
+use alloy_primitives::B256;
+use cargo_stylus_util::{sys, text};
+use clap::{
+    error::ErrorKind, ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum, ValueHint,
+};
+use ethers::types::{H160, H256, U256};
+use eyre::{bail, eyre, Context, Result};
+use std::{iter, path::PathBuf};
 use tokio::runtime::Builder;
+use wasmparser::WasmFeatures;
 
+mod activate;
+mod bump;
 mod cache;
+mod capabilities;
 mod check;
+mod config;
 mod constants;
+mod contract_version;
+mod decode;
 mod deploy;
+mod deployments;
 mod docker;
+mod explorer;
 mod export_abi;
+mod find_activation;
+mod gas_oracle;
+mod hardhat;
+mod inspect_version;
+mod invocation;
+#[cfg(feature = "ledger")]
+mod ledger;
 mod macros;
 mod new;
+mod nonce;
+mod offline;
+mod output;
+mod package;
 mod project;
+mod repro;
+mod schema;
+mod size;
+mod summary;
+mod verifiable;
 mod verify;
 mod wallet;
+mod warmup;
+mod warnings;
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(name = "check")]
@@ -28,27 +60,97 @@ mod wallet;
 struct Opts {
     #[command(subcommand)]
     command: Apis,
+    /// Prefix each diagnostic line with an ISO-8601 timestamp, e.g. to correlate CI logs.
+    #[arg(long, global = true)]
+    prefix_output_with_timestamp: bool,
+    /// Fail immediately on anything that needs the network, such as RPC calls. Builds run
+    /// cargo offline.
+    #[arg(long, global = true)]
+    disable_network: bool,
+    /// Silence the warning with this code, like STYLUS_W001. May be repeated.
+    #[arg(long, global = true, value_name = "CODE", value_parser = warnings::parse_code)]
+    allow: Vec<warnings::Warning>,
+    /// Treat the warning with this code as an error, like STYLUS_W001. May be repeated.
+    #[arg(long, global = true, value_name = "CODE", value_parser = warnings::parse_code)]
+    deny: Vec<warnings::Warning>,
+    /// Settings file to read defaults from, instead of the nearest stylus.toml.
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Ignore any stylus.toml, using only the command line and built-in defaults.
+    #[arg(long, global = true, conflicts_with = "config")]
+    no_config: bool,
+}
+
+impl Apis {
+    /// Fills in the flags left unset on the command line from the settings file's defaults.
+    fn apply_config(&mut self) -> Result<()> {
+        let (common_cfg, auth) = match self {
+            Apis::Activate(config) => (&mut config.common_cfg, Some(&mut config.auth)),
+            Apis::Bump(config) => (&mut config.common_cfg, Some(&mut config.auth)),
+            Apis::Cache(config) => match &mut config.command {
+                Some(CacheCommand::Forecast(config)) => (&mut config.common_cfg, None),
+                Some(CacheCommand::Status(config)) => (&mut config.common_cfg, None),
+                None => (&mut config.common_cfg, Some(&mut config.auth)),
+            },
+            Apis::Check(config) => (&mut config.common_cfg, None),
+            Apis::Deploy(config) => (&mut config.check_config.common_cfg, Some(&mut config.auth)),
+            Apis::Verify(config) => (&mut config.common_cfg, None),
+            Apis::Package(config) => (&mut config.common_cfg, None),
+            Apis::Warmup(config) => (&mut config.common_cfg, None),
+            Apis::FindActivation(config) => (&mut config.common_cfg, None),
+            Apis::InspectVersion(config) => (&mut config.common_cfg, None),
+            Apis::Version(config) => (&mut config.common_cfg, None),
+            _ => return Ok(()),
+        };
+        let defaults = config::StylusConfig::load()?.defaults;
+        defaults.apply(common_cfg)?;
+        if let Some(auth) = auth {
+            defaults.apply_auth(auth);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 enum Apis {
     /// Create a new Rust project.
     New {
         /// Project name.
-        name: PathBuf,
-        /// Create a minimal program.
-        #[arg(long)]
+        #[arg(required_unless_present = "list_templates")]
+        name: Option<PathBuf>,
+        /// Create a minimal program, the same as `--template minimal`.
+        #[arg(long, conflicts_with = "template")]
         minimal: bool,
+        /// Template to create the project from (see `--list-templates`).
+        #[arg(long)]
+        template: Option<String>,
+        /// Print the available templates and exit.
+        #[arg(long, conflicts_with_all = ["minimal", "template"])]
+        list_templates: bool,
     },
     /// Export a Solidity ABI.
+    // Frees `--version` for the registry entry's version.
+    #[command(disable_version_flag = true)]
     ExportAbi {
         /// The output file (defaults to stdout).
         #[arg(long)]
         output: Option<PathBuf>,
-        /// Write a JSON ABI instead using solc. Requires solc.
+        /// Write a JSON ABI instead, using solc if it's installed.
         #[arg(long)]
         json: bool,
+        /// Write Rust bindings using alloy's `sol!` macro instead.
+        #[arg(long, conflicts_with = "json")]
+        rust_alloy: bool,
+        #[command(flatten)]
+        registry: RegistryConfig,
     },
+    /// Activate an already deployed contract, e.g. after a failed activation.
+    #[command(alias = "a")]
+    Activate(ActivateConfig),
+    /// Replace a stuck pending tx with one paying higher fees, at the same nonce and with the
+    /// same data.
+    Bump(BumpConfig),
     /// Cache a contract using the Stylus CacheManager for Arbitrum chains.
     Cache(CacheConfig),
     /// Check a contract.
@@ -66,23 +168,80 @@ enum Apis {
         #[arg()]
         rust_version: String,
 
+        #[command(flatten)]
+        image: ImageConfig,
+
         /// Stylus subcommand.
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         stylus: Vec<String>,
     },
+    /// Compare a local build against one made in the reproducible Docker image.
+    ReproReport(ReproReportConfig),
     /// Verify the deployment of a Stylus program.
     #[command(alias = "v")]
     Verify(VerifyConfig),
+    /// Build the program and bundle it for distribution as a zip archive.
+    ///
+    /// The archive holds `code.bin`, the compressed WASM exactly as deployed onchain;
+    /// `abi.sol`, its Solidity interface; `abi.json`, its JSON ABI, if solc is installed;
+    /// and `build-info.json`, describing the toolchain and hashes of the build.
+    Package(PackageConfig),
+    /// Compile the program and its dependencies without doing anything else, so that later
+    /// commands with the same build flags reuse the target directory, e.g. in an early CI stage.
+    Warmup(WarmupConfig),
+    /// Find the transaction that activated a deployed program.
+    ///
+    /// Scans ArbWasm's activation events with one `eth_getLogs` request per 10,000 blocks,
+    /// so narrow the range with `--from-block` when possible.
+    FindActivation(FindActivationConfig),
+    /// Print the Stylus version a deployed program targets and what it expects of the chain.
+    InspectVersion(InspectVersionConfig),
+    /// Print the contract version a deployed program embeds with `--contract-version`.
+    Version(ContractVersionConfig),
+    /// Print the version, subcommands and signers this cargo stylus supports.
+    Capabilities {
+        /// Print them as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the JSON Schema of a command's JSON output.
+    #[command(hide = true)]
+    JsonSchema {
+        /// Name of the JSON output, e.g. `activation-calldata`.
+        output: String,
+    },
+    /// Decode a program's deployment calldata locally.
+    #[command(group(ArgGroup::new("input").required(true).args(&["file", "stdin"])))]
+    DecodeCalldata {
+        /// File containing the hex-encoded calldata.
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Read the hex-encoded calldata from stdin.
+        #[arg(long)]
+        stdin: bool,
+    },
 }
 
 #[derive(Args, Clone, Debug)]
 struct CommonConfig {
-    /// Arbitrum RPC endpoint.
-    #[arg(short, long, default_value = "https://sepolia-rollup.arbitrum.io/rpc")]
-    endpoint: String,
+    /// Arbitrum RPC endpoint, in which `${VAR}` is replaced by that environment variable
+    /// [default: the --chain's, or https://sepolia-rollup.arbitrum.io/rpc]
+    #[arg(short, long)]
+    endpoint: Option<String>,
+    /// Known chain to connect to, checking the endpoint is on it. Deploying to a mainnet
+    /// requires naming it here.
+    #[arg(long, value_parser = check::parse_chain)]
+    chain: Option<&'static constants::ChainPreset>,
     /// Whether to use stable Rust.
     #[arg(long)]
     rust_stable: bool,
+    /// Workspace member to build, required when run from a virtual workspace root.
+    #[arg(short, long)]
+    package: Option<String>,
+    /// Build even if Cargo.toml lacks a stylus-sdk dependency, warning instead of failing.
+    /// A library that isn't built as a cdylib is always an error, since it yields no WASM.
+    #[arg(long)]
+    allow_non_stylus: bool,
     /// Whether to print debug info.
     #[arg(long)]
     verbose: bool,
@@ -93,24 +252,218 @@ struct CommonConfig {
     /// in project's directory tree are included.
     #[arg(long)]
     source_files_for_project_hash: Vec<String>,
+    /// Hash Cargo.toml and Cargo.lock with absolute paths into the project or home directory
+    /// replaced by placeholders, so the project hash is the same on every machine. Deploy and
+    /// verify must agree on this.
+    #[arg(long)]
+    normalize_manifest_paths: bool,
+    /// Hash sources that the project's .gitignore files exclude. Deploy and verify must agree
+    /// on this.
+    #[arg(long)]
+    no_ignore: bool,
+    /// Run rustc through this wrapper, such as sccache, instead of any set by RUSTC_WRAPPER.
+    #[arg(long)]
+    rustc_wrapper: Option<PathBuf>,
+    /// Extra argument to append to the cargo build command, such as `--config` or a `-Z` flag.
+    /// May be repeated. Arguments that change codegen must be passed again to verify.
+    #[arg(long, value_name = "ARG", allow_hyphen_values = true)]
+    cargo_arg: Vec<String>,
+    /// Write the exact cargo command, build environment and toolchain of the build to this
+    /// JSON file, such as `build-invocation.json`, for auditors reproducing it. Credentials
+    /// in the environment are redacted.
+    #[arg(long, value_name = "PATH")]
+    build_invocation: Option<PathBuf>,
+    /// Semantic version to embed in the program's WASM as a custom section, readable onchain
+    /// with `cargo stylus version`. Deploy and verify must agree on this.
+    #[arg(long, value_name = "SEMVER", value_parser = semver::Version::parse)]
+    contract_version: Option<semver::Version>,
     #[arg(long)]
     /// Optional max fee per gas in gwei units.
     max_fee_per_gas_gwei: Option<U256>,
+    /// Optional max fee per gas in wei units.
+    #[arg(long, conflicts_with = "max_fee_per_gas_gwei", value_parser = text::parse_u256)]
+    max_fee_per_gas_wei: Option<U256>,
+    /// Optional max priority fee per gas (tip) in gwei units. Requires a max fee per gas.
+    #[arg(long, value_parser = text::parse_u256)]
+    max_priority_fee_per_gas_gwei: Option<U256>,
+    /// File to persist and increment nonces in across invocations, so that a batch of
+    /// commands sends transactions with sequential nonces. The file is locked while each tx is
+    /// sent, so commands may run in parallel.
+    #[arg(long)]
+    nonce_file: Option<PathBuf>,
+    /// Gas oracle to fetch suggested fees from when no fee is set, falling back to the node.
+    #[arg(long)]
+    gas_oracle_url: Option<String>,
+    /// When no fee is set, compute the max fee from the node's recent fee history: the next
+    /// block's base fee plus the median priority fee, times `--gas-multiplier`. Falls back to
+    /// `eth_gasPrice` if the node has no fee history.
+    #[arg(long, conflicts_with = "gas_oracle_url")]
+    auto_gas: bool,
+    /// Multiplier for the `--auto-gas` estimate, giving headroom if fees rise.
+    #[arg(long, default_value = "1.2", requires = "auto_gas", value_parser = gas_oracle::parse_multiplier)]
+    gas_multiplier: f64,
+    /// Highest max fee per gas in gwei, such as 0.5, that `--auto-gas` may pick. Larger
+    /// estimates are capped.
+    #[arg(long, value_name = "GWEI", requires = "auto_gas", value_parser = text::parse_gwei)]
+    max_gas_cap_gwei: Option<U256>,
+    /// Timeout in seconds for each RPC request.
+    #[arg(long, default_value = "30")]
+    rpc_timeout_secs: u64,
+    /// How many times to retry an RPC request after a transient failure, such as a 502.
+    /// Requests the node rejects, and sending txs, aren't retried.
+    #[arg(long, default_value = "3")]
+    rpc_retries: u32,
+    /// Delay in milliseconds before the first RPC retry, doubled on each subsequent one.
+    #[arg(long, default_value = "500")]
+    rpc_retry_delay_ms: u64,
+    /// Endpoint to fail over to when the primary one is unreachable. Can be repeated. Txs are
+    /// only ever sent to the primary endpoint, so that one is never broadcast twice.
+    #[arg(long)]
+    fallback_endpoint: Vec<String>,
+    /// Log every JSON-RPC request and response, truncating large values.
+    #[arg(long)]
+    verbose_rpc: bool,
+    /// Header to send with every RPC request, as "Name: Value", such as an `Authorization`
+    /// header for an authenticated gateway. May be repeated. The value may hold `${VAR}`,
+    /// which is read from the environment.
+    #[arg(long, value_name = "HEADER", value_parser = text::parse_header)]
+    rpc_header: Vec<(String, String)>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ActivateConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Wallet source to use.
+    #[command(flatten)]
+    auth: AuthOpts,
+    /// Deployed Stylus program address to activate.
+    #[arg(long)]
+    address: H160,
+    /// Percent to bump the estimated activation data fee by.
+    #[arg(long, default_value = "20")]
+    data_fee_bump_percent: u64,
+    /// Gas limit for the activation transaction (estimated if not set).
+    #[arg(long, value_parser = text::parse_u256)]
+    activation_gas_limit: Option<U256>,
+    /// Only perform gas estimation.
+    #[arg(long)]
+    estimate_gas: bool,
+    /// Write the activation transaction's target, value and data to a file instead of
+    /// sending it, e.g. for submission through a multisig.
+    #[arg(long, conflicts_with = "key")]
+    emit_activation_calldata: Option<PathBuf>,
+    /// If activation fails, trace it with the node's call tracer to show where it reverted.
+    #[arg(long)]
+    check_reverts_with_trace: bool,
+}
+
+/// How `export-abi` wraps the ABI for a contract registry.
+#[derive(Args, Clone, Debug)]
+pub struct RegistryConfig {
+    /// Write the JSON ABI inside a registry entry with the contract's name, version and address.
+    #[arg(long, requires_all = ["name", "version"], conflicts_with_all = ["json", "rust_alloy"])]
+    registry_entry: bool,
+    /// Contract name for the registry entry.
+    #[arg(long, requires = "registry_entry")]
+    name: Option<String>,
+    /// Contract version for the registry entry.
+    #[arg(long, requires = "registry_entry")]
+    version: Option<String>,
+    /// Address of the deployed contract, for the registry entry.
+    #[arg(long, requires = "registry_entry")]
+    address: Option<H160>,
+    /// Registry file holding a JSON array of entries to add the entry to, replacing any with the
+    /// same name and version. It's created if missing.
+    #[arg(long, requires = "registry_entry", conflicts_with = "output")]
+    merge_into: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct BumpConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Wallet source to use, which must have sent the stuck tx.
+    #[command(flatten)]
+    auth: AuthOpts,
+    /// Hash of the pending tx to replace.
+    #[arg(long)]
+    tx: H256,
 }
 
 #[derive(Args, Clone, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CacheConfig {
+    #[command(subcommand)]
+    command: Option<CacheCommand>,
     #[command(flatten)]
     common_cfg: CommonConfig,
     /// Wallet source to use.
     #[command(flatten)]
     auth: AuthOpts,
     /// Deployed and activated program address to cache.
-    #[arg(long)]
-    program_address: H160,
-    /// Bid, in wei, to place on the desired program to cache
+    // Optional only so that it can be left out for a subcommand.
+    #[arg(long, required = true)]
+    program_address: Option<H160>,
+    /// Bid, in wei, to place on the desired program to cache (defaults to the minimum bid)
     #[arg(short, long, hide(true))]
     bid: Option<u64>,
+    /// Bid, in ETH, to place on the desired program to cache
+    #[arg(long, conflicts_with = "bid", value_parser = cache::parse_eth, hide(true))]
+    bid_eth: Option<U256>,
+    /// Without a bid, bid the CacheManager's current minimum plus this percentage of it.
+    #[arg(long, conflicts_with_all = ["bid", "bid_eth"])]
+    bid_buffer_percent: Option<u64>,
+    /// Place the bid without asking for confirmation.
+    #[arg(short, long)]
+    yes: bool,
+    /// JSON ABI of the chain's CacheManager, for when it differs from the built-in one.
+    /// `placeBid` and `getMinBid` are encoded from it, passing the program's codehash to
+    /// `bytes32` parameters and its address to `address` ones, and its errors decode reverts.
+    #[arg(long)]
+    cache_manager_abi: Option<PathBuf>,
+    /// Don't bid if paying the bid and gas would leave the account with less than this, in wei.
+    #[arg(long, value_parser = text::parse_u256)]
+    min_balance_wei: Option<U256>,
+    /// Don't bid if paying the bid and gas would leave the account with less than this, in ETH.
+    #[arg(long, conflicts_with = "min_balance_wei", value_parser = cache::parse_eth)]
+    min_balance_eth: Option<U256>,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum CacheCommand {
+    /// Forecast the bid needed to keep a program cached, without placing one.
+    Forecast(CacheForecastConfig),
+    /// Print whether a program is cached, its bid, and the CacheManager's current parameters.
+    Status(CacheStatusConfig),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct CacheStatusConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Deployed and activated program address to look up.
+    #[arg(long)]
+    program_address: H160,
+    /// List every CacheManager entry, which also finds the program's bid. This reads the whole
+    /// cache, so it gets slower as the cache grows.
+    #[arg(long)]
+    list_entries: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct CacheForecastConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Deployed and activated program address to forecast for.
+    #[arg(long)]
+    program_address: H160,
+    /// How far ahead to forecast, in days, up to a century.
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..=36500))]
+    horizon_days: u64,
+    /// Print the forecast as JSON.
+    #[arg(long)]
+    json: bool,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -123,6 +476,38 @@ pub struct CheckConfig {
     /// Where to deploy and activate the program (defaults to a random address).
     #[arg(long)]
     program_address: Option<H160>,
+    /// Fail if any warnings are emitted.
+    #[arg(long)]
+    fail_on_warnings: bool,
+    /// Print the byte size of each section of the WASM, largest first.
+    #[arg(long)]
+    dump_wasm_sections: bool,
+    /// Maximum size in bytes of the deployment's init code.
+    #[arg(long, default_value_t = constants::MAX_INIT_CODE_SIZE)]
+    max_init_code_size: usize,
+    /// Report whether the local build is identical to the program deployed at this address.
+    #[arg(long)]
+    compare_with: Option<H160>,
+    /// Fail unless the project hash matches this one, e.g. one committed alongside the sources.
+    #[arg(long, conflicts_with = "wasm_file")]
+    expect_hash: Option<B256>,
+    /// Name of the entrypoint the WASM must export, for builds that rename it. The chain still
+    /// calls `user_entrypoint`, so deploy rejects it.
+    #[arg(long, default_value = constants::ENTRYPOINT_SYMBOL)]
+    entrypoint_symbol: String,
+    /// Print the WASM and compressed sizes, the compression ratio, and the size of each section.
+    #[arg(long)]
+    size_report: bool,
+    /// Print the size report as JSON on stdout, sending other output to stderr.
+    #[arg(long)]
+    size_report_json: bool,
+    /// How close to the size limit, as a percentage of it, the size report flags the program.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(0..=100))]
+    size_margin_percent: u64,
+    /// Accept a WASM feature, like `simd`, beyond those the chain is known to allow. Repeat for
+    /// each feature.
+    #[arg(long, value_parser = project::parse_wasm_feature)]
+    allow_wasm_feature: Vec<WasmFeatures>,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -135,20 +520,326 @@ struct DeployConfig {
     /// Only perform gas estimation.
     #[arg(long)]
     estimate_gas: bool,
+    /// When estimating gas, skip every simulation of the activation, including the data fee check,
+    /// and only estimate the deployment.
+    #[arg(long, requires = "estimate_gas")]
+    no_activate_sim_on_estimate: bool,
+    /// Gas limit for the deployment transaction (estimated if not set).
+    #[arg(long, value_parser = text::parse_u256)]
+    gas_limit: Option<U256>,
+    /// Gas limit for the activation transaction (estimated if not set).
+    #[arg(long, value_parser = text::parse_u256)]
+    activation_gas_limit: Option<U256>,
+    /// If activation fails, trace it with the node's call tracer to show where it reverted.
+    #[arg(long)]
+    check_reverts_with_trace: bool,
+    /// Append the deployment's outcome to the JSON array in this file, creating it if needed.
+    /// The file is locked while it's written, so parallel runs can share it.
+    #[arg(long, value_name = "PATH")]
+    summary_json_append: Option<PathBuf>,
+    /// After activating, wait until ArbWasm reports the program active before succeeding.
+    #[arg(long)]
+    poll_for_activation_state: bool,
+    /// How long in seconds to wait for the program to be reported active.
+    #[arg(long, default_value = "60", requires = "poll_for_activation_state")]
+    activation_poll_timeout_secs: u64,
+    /// Only succeed once the deployment and activation txs are confirmed and ArbWasm reports
+    /// the program active, then print its address, codehash and block.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "estimate_gas",
+            "emit_activation_calldata",
+            "dry_run_output",
+            "serialized_tx",
+            "poll_for_activation_state",
+        ]
+    )]
+    wait: bool,
+    /// How many blocks, counting the one a tx is mined in, to wait for with --wait.
+    #[arg(
+        long,
+        default_value_t = 1,
+        requires = "wait",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    confirmations: u64,
+    /// How long in seconds --wait waits in total before printing the tx hashes and failing.
+    #[arg(long, default_value_t = 300, requires = "wait")]
+    wait_timeout_secs: u64,
+    /// Refuse to deploy if the estimated deployment and activation gas exceeds this budget.
+    #[arg(long, value_parser = text::parse_u256)]
+    max_gas: Option<U256>,
+    /// Refuse to send each tx if the base fee is above this many gwei at the time.
+    #[arg(long, value_parser = text::parse_u256)]
+    max_acceptable_base_fee_gwei: Option<U256>,
+    /// Deploy even if the estimated gas exceeds --max-gas or the base fee exceeds
+    /// --max-acceptable-base-fee-gwei.
+    #[arg(long)]
+    force: bool,
+    /// Also deploy a proxy that delegates to the program.
+    #[arg(long, value_enum)]
+    proxy: Option<ProxyKind>,
+    /// Initializer to call once the program is activated, e.g. `initialize(address,uint256)`.
+    /// The call goes to the proxy when one is deployed.
+    #[arg(long, conflicts_with = "emit_activation_calldata")]
+    constructor_signature: Option<String>,
+    /// Argument to pass to the initializer, in the order of its signature (repeatable).
+    #[arg(
+        long = "constructor-args",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        requires = "constructor_signature"
+    )]
+    constructor_args: Vec<String>,
+    /// Accept that the initializer goes out as its own tx after deploy and activation, so
+    /// anyone watching the mempool can call it first. Required by `--constructor-signature`.
+    #[arg(long, requires = "constructor_signature")]
+    allow_separate_init_tx: bool,
+    /// Write the activation transaction's target, value and data to a file instead of
+    /// sending it, e.g. for submission through a multisig.
+    #[arg(long)]
+    emit_activation_calldata: Option<PathBuf>,
+    /// Print only the deployed program's address to stdout, sending everything else to stderr.
+    #[arg(long, conflicts_with = "estimate_gas")]
+    output_address_only: bool,
+    /// Deploy with CREATE2 using this 32-byte hex salt, so the program's address depends only on
+    /// the salt, the init code and the deployer, and is the same on every chain. The deployment
+    /// is skipped if code already exists at that address.
+    #[arg(
+        long,
+        value_name = "HEX32",
+        value_parser = text::parse_h256,
+        conflicts_with_all = ["dry_run_output", "check_duplicates"]
+    )]
+    salt: Option<H256>,
+    /// CREATE2 deployer contract to deploy through with `--salt`.
+    #[arg(long, default_value = constants::CREATE2_DEPLOYER, requires = "salt")]
+    create2_deployer: H160,
+    /// File recording broadcast deployments, so an interrupted deploy can be safely re-run.
+    /// Always written, so it belongs in `.gitignore` unless it's meant to be shared.
+    #[arg(long, default_value = constants::DEPLOYMENT_RECORD_FILE)]
+    deployment_record: PathBuf,
+    /// Before deploying, look in the deployment record for identical code already deployed on
+    /// this chain, and offer to reuse it.
+    #[arg(long)]
+    check_duplicates: bool,
+    /// Address book of approved deployers, one address per line. The deploy fails before
+    /// building if the wallet's address, or the `--sender` of an exported tx, isn't listed.
+    #[arg(long, value_name = "PATH", conflicts_with = "serialized_tx")]
+    allowed_deployers: Option<PathBuf>,
+    /// Build and estimate everything a deploy would do, then print the signer, predicted
+    /// address, and each tx's calldata, value and gas, without signing or sending anything.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "estimate_gas",
+            "dry_run_output",
+            "serialized_tx",
+            "emit_activation_calldata",
+            "check_duplicates",
+            "wait",
+            "watch_and_deploy",
+        ]
+    )]
+    dry_run: bool,
+    /// Record the deployment for hardhat-deploy in `deployments/<NETWORK>/<NAME>.json`.
+    #[arg(long, value_name = "NETWORK", conflicts_with = "estimate_gas")]
+    hardhat_deploy: Option<String>,
+    /// Name of the hardhat-deploy deployment (defaults to the package name).
+    #[arg(long, value_name = "NAME", requires = "hardhat_deploy")]
+    hardhat_deploy_name: Option<String>,
+    /// Refuse to deploy unless the build looks reproducible enough to verify later: no
+    /// uncommitted sources, a lockfile, a pinned toolchain and a deterministic build script.
+    #[arg(long)]
+    require_verifiable: bool,
+    /// Rebuild and redeploy whenever the project's sources change (local dev nodes only).
+    #[arg(long)]
+    watch_and_deploy: bool,
+    /// Write the unsigned deploy tx as JSON to this file instead of sending it, so that it can
+    /// be signed on another machine.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "estimate_gas",
+            "proxy",
+            "constructor_signature",
+            "hardhat_deploy",
+            "watch_and_deploy",
+            "output_address_only",
+        ]
+    )]
+    dry_run_output: Option<PathBuf>,
+    /// Account that will sign the exported tx (defaults to the wallet's address).
+    #[arg(long, requires = "dry_run_output")]
+    sender: Option<H160>,
+    /// Nonce for the exported tx (defaults to the sender's next nonce).
+    #[arg(long, requires = "dry_run_output")]
+    nonce: Option<u64>,
+    /// Broadcast a tx signed elsewhere, given as hex or as a file holding it, instead of
+    /// building and deploying the program.
+    #[arg(long, conflicts_with_all = ["dry_run_output", "estimate_gas", "watch_and_deploy"])]
+    serialized_tx: Option<String>,
+}
+
+/// Kinds of proxies that can be deployed in front of a program.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ProxyKind {
+    /// An EIP-1167 minimal proxy.
+    Minimal,
 }
 
 #[derive(Args, Clone, Debug)]
+pub struct PackageConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Zip archive to write.
+    #[arg(long)]
+    output: PathBuf,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct WarmupConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct InspectVersionConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Address of the deployed program.
+    #[arg(long)]
+    program_address: H160,
+    /// Hash of the program's deployment tx, to read the version byte after its prelude.
+    #[arg(long)]
+    deployment_tx: Option<H256>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ContractVersionConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Address of the deployed program.
+    #[arg(long)]
+    program_address: H160,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct FindActivationConfig {
+    #[command(flatten)]
+    common_cfg: CommonConfig,
+    /// Address of the deployed program.
+    #[arg(long)]
+    program_address: H160,
+    /// First block to scan.
+    #[arg(long, default_value_t = 0)]
+    from_block: u64,
+    /// Last block to scan (defaults to the latest block).
+    #[arg(long)]
+    to_block: Option<u64>,
+}
+
+/// Where the reproducible Docker image comes from.
+#[derive(Args, Clone, Debug)]
+pub struct ImageConfig {
+    /// Pin the base image to this digest, like sha256:<64 hex digits>, failing if it can't be
+    /// found or pulled.
+    #[arg(long, value_parser = docker::parse_digest)]
+    image_digest: Option<String>,
+    /// Base image to build from instead of rust:<RUST_VERSION>, e.g. from a private registry.
+    #[arg(long)]
+    base_image: Option<String>,
+    /// Build the image from this Dockerfile instead, which must install cargo stylus.
+    #[arg(long, conflicts_with_all = ["image_digest", "base_image"])]
+    dockerfile: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ReproReportConfig {
+    /// Rust version of the reproducible Docker image.
+    rust_version: String,
+    #[command(flatten)]
+    image: ImageConfig,
+    /// Whether to use stable Rust.
+    #[arg(long)]
+    rust_stable: bool,
+    /// Workspace member to build, required when run from a virtual workspace root.
+    #[arg(short, long)]
+    package: Option<String>,
+    /// The path to source files to include in the project hash, which
+    /// is included in the contract deployment init code transaction
+    /// to be used for verification of deployment integrity.
+    #[arg(long)]
+    source_files_for_project_hash: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug)]
+#[command(group(ArgGroup::new("deployment").args(&["deployment_tx", "program_address"])))]
 pub struct VerifyConfig {
     #[command(flatten)]
     common_cfg: CommonConfig,
 
-    /// Hash of the deployment transaction.
+    /// Hash of the deployment transaction. May be repeated to check several candidates,
+    /// stopping at the first one that matches.
+    #[arg(long, required_unless_present_any = ["batch", "program_address", "emit_local_init_code", "deployments_file"])]
+    deployment_tx: Vec<String>,
+    /// Verify every deployment tx, such as identical contracts created by a factory, instead
+    /// of stopping at the first match. The project is built once for all of them.
+    #[arg(long, conflicts_with_all = ["program_address", "batch", "explorer_api_url"])]
+    each: bool,
+    /// File of deployment tx hashes to verify, one per line, as with `--each`. Blank lines
+    /// and lines starting with `#` are skipped.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["program_address", "batch", "explorer_api_url"])]
+    deployments_file: Option<PathBuf>,
+    /// Address of the deployed program, for when the deployment tx isn't known. The blocks
+    /// between `--from-block` and `--to-block` are searched for the tx that created it.
     #[arg(long)]
-    deployment_tx: String,
+    program_address: Option<H160>,
+    /// First block to search for the program's deployment tx.
+    #[arg(long, requires = "program_address")]
+    from_block: Option<u64>,
+    /// Last block to search for the program's deployment tx (defaults to the latest block).
+    #[arg(long, requires = "program_address")]
+    to_block: Option<u64>,
+    /// Verify several contracts, listed in a JSON manifest that maps each contract's
+    /// directory to its deployment tx hash (or a list of candidate hashes).
+    #[arg(long, conflicts_with = "deployment")]
+    batch: Option<PathBuf>,
+    /// Print the verdict as JSON, or the batch's verdicts with `--batch`. Exits with an error
+    /// if the deployment didn't verify.
+    #[arg(long)]
+    json: bool,
+    /// Append the verdict to the JSON array in this file, creating it if needed. The file is
+    /// locked while it's written, so parallel runs can share it.
+    #[arg(long, value_name = "PATH")]
+    summary_json_append: Option<PathBuf>,
+    /// Compare the deployment against this init code, as hex or a file of hex, instead of
+    /// rebuilding the project. The compressed program without its deployment prelude works too.
+    #[arg(long, value_name = "HEX_OR_FILE", conflicts_with = "batch")]
+    local_init_code: Option<String>,
+    /// Write the init code the local project would be deployed with to this file, as hex,
+    /// and exit without comparing it to a deployment. Needs no network.
+    #[arg(long, conflicts_with_all = ["deployment", "batch", "local_init_code", "json"])]
+    emit_local_init_code: Option<PathBuf>,
+    /// Etherscan-compatible explorer API to query for the program's verification status.
+    #[arg(long)]
+    explorer_api_url: Option<String>,
+    /// API key for the explorer.
+    #[arg(long, env = explorer::EXPLORER_API_KEY_ENV, hide_env_values = true)]
+    explorer_api_key: Option<String>,
+    /// Also verify a deployment whose prelude differs only in its version byte, with a warning.
+    /// By default, the deployment must match byte for byte.
+    #[arg(long)]
+    lenient_prelude_version: bool,
+    /// Also find the activation tx of a verified deployment and report its data fee and the
+    /// parameters ArbWasm logged, so they can be cross-checked.
+    #[arg(long, conflicts_with_all = ["batch", "each", "deployments_file", "emit_local_init_code"])]
+    check_activation: bool,
 }
 
 #[derive(Clone, Debug, Args)]
-#[clap(group(ArgGroup::new("key").required(true).args(&["private_key_path", "private_key", "keystore_path"])))]
+#[clap(group(ArgGroup::new("key").args(&["private_key_path", "private_key", "keystore_path", "keystore_dir", "mnemonic_path", "ledger"])))]
 struct AuthOpts {
     /// File path to a text file containing a hex-encoded private key.
     #[arg(long)]
@@ -157,11 +848,53 @@ struct AuthOpts {
     #[arg(long)]
     private_key: Option<String>,
     /// Path to an Ethereum wallet keystore file (e.g. clef).
-    #[arg(long)]
+    #[arg(long, value_hint = ValueHint::FilePath)]
     keystore_path: Option<String>,
+    /// Directory of keystore files (e.g. geth's keystore dir) to pick `--account` from.
+    #[arg(long, requires = "account")]
+    keystore_dir: Option<PathBuf>,
+    /// Address of the account to unlock from `--keystore-dir`.
+    #[arg(long, requires = "keystore_dir")]
+    account: Option<H160>,
     /// Keystore password file.
     #[arg(long)]
     keystore_password_path: Option<PathBuf>,
+    /// File path to a text file containing a BIP-39 mnemonic phrase.
+    #[arg(long)]
+    mnemonic_path: Option<PathBuf>,
+    /// Index of the account to derive from the mnemonic, at m/44'/60'/0'/0/{index}.
+    #[arg(long, requires = "mnemonic_path", default_value_t = 0)]
+    mnemonic_account_index: u32,
+    /// Derivation path of the account to derive from the mnemonic, instead of its index.
+    #[arg(
+        long,
+        requires = "mnemonic_path",
+        conflicts_with = "mnemonic_account_index"
+    )]
+    hd_path: Option<String>,
+    /// Sign with a Ledger running the Ethereum app, confirming each tx on the device.
+    #[arg(long)]
+    ledger: bool,
+    /// Index of the Ledger account to use, at m/44'/60'/{index}'/0/0 as in Ledger Live.
+    #[arg(long, requires = "ledger", default_value_t = 0)]
+    ledger_account_index: usize,
+}
+
+/// Checks that a command to run in the reproducible container parses, so that mistakes are
+/// caught before the image is built. Subcommands of the other cargo stylus tools are left to them.
+fn check_reproducible_command(command_line: &[String]) -> Result<()> {
+    let Some(subcommand) = command_line.first() else {
+        bail!("missing the stylus subcommand to run");
+    };
+    if Opts::command().find_subcommand(subcommand).is_none() {
+        return Ok(());
+    }
+    let args = iter::once("cargo-stylus").chain(command_line.iter().map(String::as_str));
+    match Opts::try_parse_from(args) {
+        Ok(_) => Ok(()),
+        Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => Ok(()),
+        Err(e) => bail!("{}", e.render().to_string().trim_end()),
+    }
 }
 
 fn main() -> Result<()> {
@@ -170,19 +903,86 @@ fn main() -> Result<()> {
     runtime.block_on(main_impl(args))
 }
 
-async fn main_impl(args: Opts) -> Result<()> {
+async fn main_impl(mut args: Opts) -> Result<()> {
     macro_rules! run {
         ($expr:expr, $($msg:expr),+) => {
             $expr.wrap_err_with(|| eyre!($($msg),+))?
         };
     }
 
+    if args.prefix_output_with_timestamp {
+        output::prefix_with_timestamp();
+    }
+    if args.disable_network {
+        sys::disable_network();
+    }
+    warnings::configure(args.allow, args.deny);
+    config::configure(args.config, args.no_config);
+    run!(args.command.apply_config(), "failed to load settings");
+
     match args.command {
-        Apis::New { name, minimal } => {
-            run!(new::new(&name, minimal), "failed to open new project");
+        Apis::New {
+            name,
+            minimal,
+            template,
+            list_templates,
+        } => {
+            if list_templates {
+                new::list_templates();
+            } else {
+                let template = match minimal {
+                    true => Some("minimal".to_string()),
+                    false => template,
+                };
+                let name = name.unwrap_or_default();
+                run!(
+                    new::new(&name, template.as_deref()),
+                    "failed to open new project"
+                );
+            }
+        }
+        Apis::ExportAbi {
+            registry, output, ..
+        } if registry.registry_entry => {
+            run!(
+                export_abi::export_registry_entry(&registry, output),
+                "failed to export registry entry"
+            );
+        }
+        Apis::ExportAbi {
+            json,
+            rust_alloy,
+            output,
+            ..
+        } => {
+            run!(
+                export_abi::export_abi(output, json, rust_alloy),
+                "failed to export abi"
+            );
+        }
+        Apis::Activate(config) => {
+            run!(
+                activate::activate_program(&config).await,
+                "stylus activate failed"
+            );
+        }
+        Apis::Bump(config) => {
+            run!(bump::bump(&config).await, "failed to bump tx");
+        }
+        Apis::Cache(CacheConfig {
+            command: Some(CacheCommand::Forecast(config)),
+            ..
+        }) => {
+            run!(
+                cache::forecast(&config).await,
+                "failed to forecast cache bids"
+            );
         }
-        Apis::ExportAbi { json, output } => {
-            run!(export_abi::export_abi(output, json), "failed to export abi");
+        Apis::Cache(CacheConfig {
+            command: Some(CacheCommand::Status(config)),
+            ..
+        }) => {
+            run!(cache::status(&config).await, "failed to get cache status");
         }
         Apis::Cache(config) => {
             run!(cache::cache_program(&config).await, "stylus cache failed");
@@ -190,21 +990,71 @@ async fn main_impl(args: Opts) -> Result<()> {
         Apis::Check(config) => {
             run!(check::check(&config).await, "stylus checks failed");
         }
+        Apis::Deploy(config) if config.watch_and_deploy => {
+            run!(watch::watch_and_deploy(config).await, "failed to watch");
+        }
         Apis::Deploy(config) => {
             run!(deploy::deploy(config).await, "failed to deploy");
         }
         Apis::Reproducible {
             rust_version,
+            image,
             stylus,
         } => {
             run!(
-                docker::run_reproducible(&rust_version, &stylus),
+                check_reproducible_command(&stylus),
+                "invalid command to run reproducibly"
+            );
+            run!(
+                docker::run_reproducible(&rust_version, &image, &stylus),
                 "failed reproducible run"
             );
         }
+        Apis::ReproReport(config) => {
+            run!(repro::repro_report(&config), "failed to compare builds");
+        }
         Apis::Verify(config) => {
             run!(verify::verify(config).await, "failed to verify");
         }
+        Apis::Package(config) => {
+            run!(package::package(&config), "failed to package");
+        }
+        Apis::Warmup(config) => {
+            run!(warmup::warmup(&config), "failed to warm up the build");
+        }
+        Apis::FindActivation(config) => {
+            run!(
+                find_activation::find_activation(&config).await,
+                "failed to find activation"
+            );
+        }
+        Apis::InspectVersion(config) => {
+            run!(
+                inspect_version::inspect_version(&config).await,
+                "failed to inspect version"
+            );
+        }
+        Apis::Version(config) => {
+            run!(
+                contract_version::print_contract_version(&config).await,
+                "failed to read contract version"
+            );
+        }
+        Apis::Capabilities { json } => {
+            run!(
+                capabilities::print_capabilities(json),
+                "failed to print capabilities"
+            );
+        }
+        Apis::JsonSchema { output } => {
+            run!(schema::print_json_schema(&output), "failed to print schema");
+        }
+        Apis::DecodeCalldata { file, stdin } => {
+            run!(
+                decode::decode_calldata(file, stdin),
+                "failed to decode calldata"
+            );
+        }
     }
     Ok(())
 }
@@ -1,31 +1,119 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
-use crate::constants::{GITHUB_TEMPLATE_REPO, GITHUB_TEMPLATE_REPO_MINIMAL};
+use crate::constants::{TemplateSource, TEMPLATES};
 use cargo_stylus_util::{
     color::{Color, GREY},
     sys,
 };
 use eyre::{bail, Context, Result};
-use std::{env::current_dir, path::Path};
+use std::{env::current_dir, fs, path::Path};
 
-/// Creates a new Stylus project in the current directory
-pub fn new(name: &Path, minimal: bool) -> Result<()> {
-    let repo = match minimal {
-        true => GITHUB_TEMPLATE_REPO_MINIMAL,
-        false => GITHUB_TEMPLATE_REPO,
+/// Files generated around a bundled template's `src/lib.rs`, with `{{name}}` and `{{crate_name}}`
+/// filled in.
+const BUNDLED_FILES: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        include_str!("../templates/common/Cargo.toml.template"),
+    ),
+    (
+        "rust-toolchain.toml",
+        include_str!("../templates/common/rust-toolchain.toml"),
+    ),
+    ("src/main.rs", include_str!("../templates/common/main.rs")),
+    (".gitignore", include_str!("../templates/common/gitignore")),
+];
+
+/// Creates a new Stylus project in the current directory from the named template, or the
+/// default one.
+pub fn new(name: &Path, template: Option<&str>) -> Result<()> {
+    let (_, source, _) = match template {
+        Some(template) => {
+            let Some(entry) = TEMPLATES.iter().find(|(name, ..)| *name == template) else {
+                let names: Vec<_> = TEMPLATES.iter().map(|(name, ..)| *name).collect();
+                bail!(
+                    "unknown template {template}, expected one of: {}",
+                    names.join(", ")
+                );
+            };
+            entry
+        }
+        None => &TEMPLATES[0],
     };
-    let output = sys::new_command("git")
-        .arg("clone")
-        .arg(repo)
-        .arg(name)
-        .output()
-        .wrap_err("git clone failed")?;
-
-    if !output.status.success() {
-        bail!("git clone command failed");
+    match source {
+        TemplateSource::Repo(repo) => {
+            sys::ensure_network("cloning the template")?;
+            let output = sys::new_command("git")
+                .arg("clone")
+                .arg(repo)
+                .arg(name)
+                .output()
+                .wrap_err("git clone failed")?;
+
+            if !output.status.success() {
+                bail!("git clone command failed");
+            }
+        }
+        TemplateSource::Bundled(lib) => generate(name, lib)?,
     }
     let path = current_dir().wrap_err("no current dir")?.join(name);
     println!("{GREY}new project at: {}", path.to_string_lossy().mint());
     Ok(())
 }
+
+/// Writes a project with the given `src/lib.rs`, named after its directory.
+fn generate(dir: &Path, lib: &str) -> Result<()> {
+    if dir.exists() {
+        bail!("{} already exists", dir.to_string_lossy());
+    }
+    let Some(name) = dir.file_name().map(|name| name.to_string_lossy()) else {
+        bail!("no project name in {}", dir.to_string_lossy());
+    };
+    let crate_name = name.replace('-', "_");
+    let files = BUNDLED_FILES.iter().copied().chain([("src/lib.rs", lib)]);
+    for (path, contents) in files {
+        let contents = contents
+            .replace("{{name}}", &name)
+            .replace("{{crate_name}}", &crate_name);
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).wrap_err_with(|| format!("failed to create {parent:?}"))?;
+        }
+        fs::write(&path, contents).wrap_err_with(|| format!("failed to write {path:?}"))?;
+    }
+    Ok(())
+}
+
+/// Prints the templates `new` can create projects from.
+pub fn list_templates() {
+    let width = TEMPLATES.iter().map(|(name, ..)| name.len()).max();
+    let width = width.unwrap_or_default();
+    for (name, _, description) in TEMPLATES {
+        println!(
+            "{}  {}",
+            format!("{name:width$}").mint(),
+            description.grey()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bundled_templates() -> Result<()> {
+        let dir = tempdir()?;
+        let project = dir.path().join("my-token");
+        generate(&project, "// lib")?;
+
+        let manifest = fs::read_to_string(project.join("Cargo.toml"))?;
+        assert!(manifest.contains("name = \"my-token\""));
+        let main = fs::read_to_string(project.join("src/main.rs"))?;
+        assert!(main.contains("my_token::print_abi"));
+        assert_eq!(fs::read_to_string(project.join("src/lib.rs"))?, "// lib");
+        assert!(generate(&project, "// lib").is_err());
+        Ok(())
+    }
+}
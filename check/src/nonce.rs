@@ -0,0 +1,114 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{deploy::SignerClient, macros::*};
+use cargo_stylus_util::color::Color;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{BlockNumber, U256},
+};
+use eyre::{Result, WrapErr};
+use fs2::FileExt;
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Next nonce to use for each sender, keyed by `<chain id>:<address>`.
+type Nonces = BTreeMap<String, U256>;
+
+/// A nonce file, locked for as long as the value lives.
+///
+/// The nonce file lets a batch of commands use sequential nonces without each one racing
+/// the node's view of pending transactions. Commands hold the lock from reading their nonce
+/// until they've sent the tx and recorded it, so parallel commands never hand out the same
+/// nonce.
+pub struct NonceFile {
+    file: File,
+    path: PathBuf,
+    nonces: Nonces,
+}
+
+impl NonceFile {
+    /// Opens the nonce file, creating it if needed, and blocks until it's exclusively locked.
+    pub fn lock(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .wrap_err_with(|| format!("failed to open nonce file {path:?}"))?;
+        file.lock_exclusive()
+            .wrap_err_with(|| format!("failed to lock nonce file {path:?}"))?;
+
+        let mut json = String::new();
+        file.read_to_string(&mut json)
+            .wrap_err_with(|| format!("failed to read nonce file {path:?}"))?;
+        let nonces = match json.trim().is_empty() {
+            true => Nonces::new(),
+            false => serde_json::from_str(&json)
+                .wrap_err_with(|| format!("invalid nonce file {path:?}"))?,
+        };
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            nonces,
+        })
+    }
+
+    /// Returns the nonce the next transaction from the client's signer should use.
+    ///
+    /// It's checked against the chain so that a stale file never reuses a nonce that has
+    /// already been consumed. A file ahead of the chain is still trusted, since the node may
+    /// not have seen the batch's earlier txs yet, but it's warned about because a dropped tx
+    /// leaves a gap that stalls every later one.
+    pub async fn next_nonce(&self, client: &SignerClient) -> Result<U256> {
+        let stored = self.nonces.get(&key(client)).copied();
+        let pending = client
+            .get_transaction_count(client.address(), Some(BlockNumber::Pending.into()))
+            .await
+            .wrap_err("failed to get nonce")?;
+        let path = self.path.to_string_lossy();
+        Ok(match stored {
+            Some(nonce) if nonce == pending => nonce,
+            Some(nonce) if nonce > pending => {
+                warnln!(
+                    NonceFileAhead,
+                    "nonce file {} is ahead of the chain ({nonce} > {pending}), so the tx won't \
+                     be mined until the earlier nonces are; if one was dropped, remove the \
+                     entry to continue from the chain's nonce",
+                    path.yellow()
+                )?;
+                nonce
+            }
+            Some(nonce) => {
+                warnln!(
+                    StaleNonceFile,
+                    "nonce file {} is behind the chain ({nonce} < {pending}), using the chain's nonce",
+                    path.yellow()
+                )?;
+                pending
+            }
+            None => pending,
+        })
+    }
+
+    /// Records that `nonce` has been used, so the next command continues from the one after.
+    pub fn record(&mut self, client: &SignerClient, nonce: U256) -> Result<()> {
+        self.nonces.insert(key(client), nonce + 1);
+        let json = serde_json::to_string_pretty(&self.nonces)?;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file
+            .write_all(json.as_bytes())
+            .wrap_err_with(|| format!("failed to write nonce file {:?}", self.path))
+    }
+}
+
+fn key(client: &SignerClient) -> String {
+    format!("{}:{:?}", client.signer().chain_id(), client.address())
+}
@@ -0,0 +1,167 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    config, deploy,
+    macros::*,
+    wallet::{self, StylusSigner},
+    DeployConfig,
+};
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    rpc::RpcClient,
+};
+use ethers::{
+    providers::{Middleware, Provider},
+    signers::Signer,
+    types::{
+        transaction::eip2718::TypedTransaction, BlockNumber, Eip1559TransactionRequest, H160, U256,
+        U64,
+    },
+    utils::rlp::Rlp,
+};
+use eyre::{bail, eyre, Result, WrapErr};
+use std::{fs, path::Path};
+
+/// Writes the deployment tx, with everything but its signature filled in, to a JSON file for
+/// signing on another machine. The sender is `--sender`, or else the address of the signer,
+/// which is resolved here unless one is given.
+pub async fn write_unsigned_deploy(
+    cfg: &DeployConfig,
+    code: &[u8],
+    path: &Path,
+    signer: Option<StylusSigner>,
+) -> Result<()> {
+    let common_cfg = &cfg.check_config.common_cfg;
+    let provider = common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?
+        .as_u64();
+    common_cfg.check_chain(chain_id)?;
+    let sender = match (cfg.sender, signer) {
+        (Some(sender), _) => sender,
+        (None, Some(signer)) => signer.address(),
+        (None, None) => wallet::resolve_signer(&cfg.auth, chain_id)
+            .await
+            .wrap_err("pass --sender to export a tx without a wallet")?
+            .address(),
+    };
+    let common_cfg = &config::network(chain_id)?.apply_fees(common_cfg);
+
+    let nonce = match cfg.nonce {
+        Some(nonce) => U256::from(nonce),
+        None => provider
+            .get_transaction_count(sender, Some(BlockNumber::Pending.into()))
+            .await
+            .wrap_err("failed to get nonce")?,
+    };
+    let mut tx = Eip1559TransactionRequest::new()
+        .from(sender)
+        .data(deploy::program_deployment_calldata(code))
+        .value(0)
+        .nonce(nonce)
+        .chain_id(chain_id);
+
+    let gas = match cfg.gas_limit {
+        Some(gas) => gas,
+        None => provider
+            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+            .await
+            .wrap_err("failed to estimate deploy gas")?,
+    };
+    let max_fee = match common_cfg.max_fee_per_gas()? {
+        Some(max_fee) => max_fee,
+        None => suggest_max_fee(&provider).await?,
+    };
+    let tip = common_cfg.max_priority_fee_per_gas()?.unwrap_or_default();
+    tx = tx
+        .gas(gas)
+        .max_fee_per_gas(max_fee)
+        .max_priority_fee_per_gas(tip);
+
+    // ethers leaves the chain ID and type out, since nodes fill them in when sending.
+    let mut json = serde_json::to_value(&tx)?;
+    json["chainId"] = format!("{chain_id:#x}").into();
+    json["type"] = "0x2".into();
+    let json = serde_json::to_string_pretty(&json)?;
+    fs::write(path, json).wrap_err_with(|| format!("failed to write {path:?}"))?;
+    let contract = ethers::utils::get_contract_address(sender, nonce);
+    greyln!(
+        "wrote unsigned deploy tx to {}",
+        path.to_string_lossy().lavender()
+    );
+    greyln!(
+        "once signed and broadcast with --serialized-tx, it deploys to {}",
+        contract.debug_lavender()
+    );
+    Ok(())
+}
+
+/// Twice the latest base fee, leaving room for it to rise before the tx is signed and sent.
+async fn suggest_max_fee(provider: &Provider<RpcClient>) -> Result<U256> {
+    let block = provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .wrap_err("failed to get latest block")?
+        .ok_or(eyre!("no latest block"))?;
+    let Some(base_fee) = block.base_fee_per_gas else {
+        bail!("latest block has no base fee, pass --max-fee-per-gas-gwei");
+    };
+    Ok(base_fee * 2)
+}
+
+/// Broadcasts a tx signed elsewhere, given as hex or as a file holding the hex, and waits for
+/// it to be mined.
+pub async fn broadcast_serialized(cfg: &DeployConfig, serialized: &str) -> Result<()> {
+    let raw = match Path::new(serialized).is_file() {
+        true => fs::read_to_string(serialized)
+            .wrap_err_with(|| format!("failed to read {serialized:?}"))?,
+        false => serialized.to_string(),
+    };
+    let raw = raw.trim();
+    let bytes =
+        hex::decode(raw.strip_prefix("0x").unwrap_or(raw)).wrap_err("serialized tx is not hex")?;
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&bytes))
+        .map_err(|error| eyre!("serialized tx is not a signed tx: {error}"))?;
+    let sender: H160 = signature.recover(tx.sighash())?;
+
+    let provider = cfg.check_config.common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?;
+    if let Some(tx_chain_id) = tx.chain_id() {
+        if tx_chain_id.as_u64() != chain_id.as_u64() {
+            bail!(
+                "serialized tx is for chain {tx_chain_id}, but the endpoint is on chain {chain_id}"
+            );
+        }
+    }
+    let common_cfg = &cfg.check_config.common_cfg;
+    common_cfg.check_chain(chain_id.as_u64())?;
+    common_cfg.check_mainnet(chain_id.as_u64())?;
+    greyln!(
+        "broadcasting tx from {} with nonce {}",
+        sender.debug_lavender(),
+        tx.nonce().copied().unwrap_or_default()
+    );
+
+    let pending = provider
+        .send_raw_transaction(bytes.into())
+        .await
+        .wrap_err("failed to broadcast serialized tx")?;
+    let tx_hash = pending.tx_hash();
+    let Some(receipt) = pending.await.wrap_err("tx failed to complete")? else {
+        bail!("failed to get receipt for tx {}", tx_hash.lavender());
+    };
+    if receipt.status != Some(U64::from(1)) {
+        bail!("serialized tx reverted {}", tx_hash.debug_red());
+    }
+    if let Some(contract) = receipt.contract_address {
+        greyln!("deployed code at address: {}", contract.debug_lavender());
+    }
+    greyln!("tx hash: {}", tx_hash.debug_lavender());
+    Ok(())
+}
@@ -0,0 +1,41 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use chrono::{SecondsFormat, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether diagnostics go to stderr, leaving stdout for a command's result.
+static DIAGNOSTICS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Whether diagnostics are prefixed with the time they were printed.
+static TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+
+/// Sends all further diagnostics to stderr, e.g. so a script can read a result from stdout.
+pub fn diagnostics_to_stderr() {
+    DIAGNOSTICS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Prefixes all further diagnostics with an ISO-8601 timestamp. Command results, such as
+/// `--json` output, are printed separately and never prefixed.
+pub fn prefix_with_timestamp() {
+    TIMESTAMPS.store(true, Ordering::Relaxed);
+}
+
+/// Prints a diagnostic line, as `greyln!` does.
+pub fn diagnostic(line: String) {
+    let line = stamp(line);
+    match DIAGNOSTICS_TO_STDERR.load(Ordering::Relaxed) {
+        true => eprintln!("{line}"),
+        false => println!("{line}"),
+    }
+}
+
+/// Prefixes each line of a diagnostic with the current time, if enabled.
+pub fn stamp(text: String) -> String {
+    if !TIMESTAMPS.load(Ordering::Relaxed) {
+        return text;
+    }
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let lines: Vec<_> = text.lines().map(|line| format!("{now} {line}")).collect();
+    lines.join("\n")
+}
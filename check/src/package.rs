@@ -0,0 +1,207 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    constants::TOOLCHAIN_FILE_NAME,
+    export_abi,
+    macros::*,
+    project::{self, BuildConfig},
+    PackageConfig,
+};
+use alloy_primitives::keccak256;
+use cargo_stylus_util::{color::Color, sys};
+use chrono::{DateTime, Datelike, Timelike};
+use eyre::{eyre, Result, WrapErr};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    env, fs,
+    io::{Seek, Write},
+    path::PathBuf,
+    process::Command,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// Metadata about how a packaged program was built, stored as `build-info.json`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// Version of cargo stylus that built the package.
+    pub cargo_stylus_version: String,
+    /// Channel of the project's Rust toolchain file.
+    pub rust_toolchain: String,
+    /// Output of `cargo --version`.
+    pub cargo_version: String,
+    /// Hex-encoded hash of the project's sources, as embedded in the WASM.
+    pub project_hash: String,
+    /// Hex-encoded keccak hash of `code.bin`, which is the program's codehash onchain.
+    pub codehash: String,
+    /// Byte size of the uncompressed WASM.
+    pub wasm_size: usize,
+    /// Byte size of `code.bin`.
+    pub code_size: usize,
+    /// When the sources were last changed, in seconds since the Unix epoch, taken from
+    /// `SOURCE_DATE_EPOCH` or the last git commit.
+    pub build_timestamp: Option<i64>,
+}
+
+/// Builds the program and writes a zip archive with its compressed WASM, ABI and build info.
+pub fn package(cfg: &PackageConfig) -> Result<()> {
+    let common = &cfg.common_cfg;
+    let build_cfg = BuildConfig {
+        allow_non_stylus: common.allow_non_stylus,
+        normalize_manifest_paths: common.normalize_manifest_paths,
+        no_ignore: common.no_ignore,
+        rustc_wrapper: common.rustc_wrapper.clone(),
+        cargo_args: common.cargo_arg.clone(),
+        invocation_file: common.build_invocation.clone(),
+        ..BuildConfig::new(common.rust_stable, common.package.clone())
+    };
+    let wasm = project::build_dylib(build_cfg.clone())?;
+    let cargo_version = project::cargo_version(&build_cfg)?;
+    let project_hash = project::project_hash(
+        &cargo_version,
+        common.source_files_for_project_hash.clone(),
+        &build_cfg,
+    )?;
+    let (wasm_bytes, code) =
+        project::compress_wasm(&wasm, project_hash, common.contract_version.as_ref())?;
+
+    let solidity = export_abi::solidity_abi()?;
+    let json = match sys::command_exists("solc") {
+        true => Some(export_abi::json_abi(&solidity)?),
+        false => {
+            warnln!(
+                SolcMissing,
+                "solc not found, leaving abi.json out of the package"
+            )?;
+            None
+        }
+    };
+
+    let build_info = BuildInfo {
+        cargo_stylus_version: env!("CARGO_PKG_VERSION").to_string(),
+        rust_toolchain: project::extract_toolchain_channel(&PathBuf::from(TOOLCHAIN_FILE_NAME))?,
+        cargo_version: String::from_utf8_lossy(&cargo_version).trim().to_string(),
+        project_hash: hex::encode(project_hash),
+        codehash: hex::encode(keccak256(&code)),
+        wasm_size: wasm_bytes.len(),
+        code_size: code.len(),
+        build_timestamp: build_timestamp(env::var("SOURCE_DATE_EPOCH").ok())?,
+    };
+    let timestamp = build_info.build_timestamp;
+    let build_info = serde_json::to_vec_pretty(&build_info)?;
+
+    let mut entries = vec![
+        ("code.bin", code),
+        ("abi.sol", solidity),
+        ("build-info.json", build_info),
+    ];
+    if let Some(json) = json {
+        entries.insert(2, ("abi.json", json));
+    }
+    let file = fs::File::create(&cfg.output)
+        .wrap_err_with(|| format!("failed to create {:?}", cfg.output))?;
+    write_zip(file, &entries, timestamp).wrap_err("failed to write package")?;
+
+    greyln!(
+        "wrote package to {}",
+        cfg.output.to_string_lossy().lavender()
+    );
+    Ok(())
+}
+
+/// The time to record for a build, so that packaging the same sources twice yields the same
+/// archive. Following the reproducible builds convention, `SOURCE_DATE_EPOCH` takes precedence,
+/// then the time of the last git commit. The wall clock is never used.
+fn build_timestamp(source_date_epoch: Option<String>) -> Result<Option<i64>> {
+    if let Some(epoch) = source_date_epoch {
+        let epoch = epoch.trim();
+        let seconds = epoch
+            .parse()
+            .map_err(|_| eyre!("SOURCE_DATE_EPOCH should be a number of seconds, not {epoch}"))?;
+        return Ok(Some(seconds));
+    }
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .output();
+    Ok(match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        }
+        _ => None,
+    })
+}
+
+/// Writes a zip archive whose entries are all stamped with the given time, or with the
+/// earliest time zip supports if there's none.
+fn write_zip<W: Write + Seek>(
+    out: W,
+    entries: &[(&str, Vec<u8>)],
+    timestamp: Option<i64>,
+) -> Result<()> {
+    let mut options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    if let Some(time) = timestamp.and_then(|t| DateTime::from_timestamp(t, 0)) {
+        let time = zip::DateTime::from_date_and_time(
+            time.year().clamp(1980, 2107) as u16,
+            time.month() as u8,
+            time.day() as u8,
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+        );
+        if let Ok(time) = time {
+            options = options.last_modified_time(time);
+        }
+    }
+
+    let mut zip = ZipWriter::new(out);
+    for (name, contents) in entries {
+        zip.start_file(*name, options)?;
+        zip.write_all(contents)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use zip::ZipArchive;
+
+    #[test]
+    fn test_build_timestamp() -> Result<()> {
+        let epoch = build_timestamp(Some(" 1700000000\n".into()))?;
+        assert_eq!(epoch, Some(1700000000));
+        assert!(build_timestamp(Some("yesterday".into())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_packages_are_reproducible() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let wasm = dir.path().join("program.wasm");
+        let program = wasmer::wat2wasm(
+            br#"(module (func (export "user_entrypoint") (param i32) (result i32) i32.const 0))"#,
+        )
+        .unwrap();
+        fs::write(&wasm, program)?;
+
+        let package = |name: &str, timestamp: Option<i64>| -> Result<Vec<u8>> {
+            let (_, code) = project::compress_wasm(&wasm, [7; 32], None)?;
+            let entries = [("code.bin", code), ("abi.sol", b"interface I {}".to_vec())];
+            let path = dir.path().join(name);
+            write_zip(fs::File::create(&path)?, &entries, timestamp)?;
+            Ok(fs::read(path)?)
+        };
+        let first = package("first.zip", Some(1700000000))?;
+        assert_eq!(first, package("second.zip", Some(1700000000))?);
+        assert_ne!(first, package("later.zip", Some(1800000000))?);
+
+        let mut archive = ZipArchive::new(Cursor::new(first))?;
+        let time = archive.by_name("code.bin")?.last_modified();
+        assert_eq!((time.year(), time.month(), time.day()), (2023, 11, 14));
+        Ok(())
+    }
+}
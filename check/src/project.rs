@@ -3,25 +3,30 @@
 
 use crate::{
     constants::{
-        BROTLI_COMPRESSION_LEVEL, EOF_PREFIX_NO_DICT, PROJECT_HASH_SECTION_NAME, RUST_TARGET,
-        TOOLCHAIN_FILE_NAME,
+        BROTLI_COMPRESSION_LEVEL, BROTLI_WINDOW_BITS, CHAIN_PRESETS, CONTRACT_VERSION_SECTION_NAME,
+        EOF_PREFIX_NO_DICT, HOST_MODULES, PROJECT_HASH_SECTION_NAME, RUST_TARGET,
+        STYLUS_WASM_FEATURES, TOOLCHAIN_FILE_NAME,
     },
+    invocation::BuildInvocation,
     macros::*,
 };
-use brotli2::read::BrotliEncoder;
+use brotli2::{read::BrotliDecoder, write::BrotliEncoder, CompressParams};
 use cargo_stylus_util::{color::Color, sys};
 use eyre::{bail, eyre, Result, WrapErr};
 use glob::glob;
+use semver::Version;
 use std::process::Command;
 use std::{
     env::current_dir,
     fs,
-    io::Read,
+    io::{self, Read},
     path::{Path, PathBuf},
     process,
+    rc::Rc,
 };
 use tiny_keccak::{Hasher, Keccak};
 use toml::Value;
+use wasmparser::WasmFeatures;
 
 #[derive(Default, Clone, PartialEq)]
 pub enum OptLevel {
@@ -34,12 +39,27 @@ pub enum OptLevel {
 pub struct BuildConfig {
     pub opt_level: OptLevel,
     pub stable: bool,
+    /// Workspace member to build, if any.
+    pub package: Option<String>,
+    /// Build even if the manifest doesn't look like a Stylus program's.
+    pub allow_non_stylus: bool,
+    /// Hash manifests with machine-specific absolute paths replaced by placeholders.
+    pub normalize_manifest_paths: bool,
+    /// Hash sources that `.gitignore` files exclude.
+    pub no_ignore: bool,
+    /// Wrapper to run rustc through, overriding `RUSTC_WRAPPER`.
+    pub rustc_wrapper: Option<PathBuf>,
+    /// Extra arguments appended to the cargo build command.
+    pub cargo_args: Vec<String>,
+    /// Where to record the cargo command and environment of a successful build.
+    pub invocation_file: Option<PathBuf>,
 }
 
 impl BuildConfig {
-    pub fn new(stable: bool) -> Self {
+    pub fn new(stable: bool, package: Option<String>) -> Self {
         Self {
             stable,
+            package,
             ..Default::default()
         }
     }
@@ -49,36 +69,174 @@ impl BuildConfig {
 pub enum BuildError {
     #[error("could not find WASM in release dir ({path}).")]
     NoWasmFound { path: PathBuf },
+    #[error(
+        "{path} is a virtual workspace manifest with no package of its own.\n\
+         Please select a workspace member with `-p <package>` or run from the contract's directory."
+    )]
+    VirtualManifest { path: PathBuf },
 }
 
-/// Build a Rust project to WASM and return the path to the compiled WASM file.
-pub fn build_dylib(cfg: BuildConfig) -> Result<PathBuf> {
-    let cwd: PathBuf = current_dir().map_err(|e| eyre!("could not get current dir: {e}"))?;
+/// The kind of Cargo manifest found in a project directory.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ManifestKind {
+    /// A single package.
+    Package,
+    /// A workspace root with no package of its own.
+    Virtual,
+    /// A workspace root that is also a package.
+    Mixed,
+}
 
-    let mut cmd = sys::new_command("cargo");
+/// Reads the Cargo.toml at the given path and determines what kind of manifest it is.
+pub fn manifest_kind(manifest_path: &Path) -> Result<ManifestKind> {
+    let contents = fs::read_to_string(manifest_path)
+        .wrap_err_with(|| eyre!("failed to read {}", manifest_path.display()))?;
+    let manifest: Value = toml::from_str(&contents)
+        .wrap_err_with(|| eyre!("failed to parse {}", manifest_path.display()))?;
 
-    if !cfg.stable {
-        cmd.arg("+nightly");
+    let package = manifest.get("package").is_some();
+    let workspace = manifest.get("workspace").is_some();
+    Ok(match (package, workspace) {
+        (false, true) => ManifestKind::Virtual,
+        (true, true) => ManifestKind::Mixed,
+        _ => ManifestKind::Package,
+    })
+}
+
+/// Reads the name of the package at the given manifest.
+pub fn package_name(manifest_path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(manifest_path)
+        .wrap_err_with(|| eyre!("failed to read {}", manifest_path.display()))?;
+    let manifest: Value = toml::from_str(&contents)
+        .wrap_err_with(|| eyre!("failed to parse {}", manifest_path.display()))?;
+    let name = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(Value::as_str);
+    match name {
+        Some(name) => Ok(name.to_string()),
+        None => bail!("{} has no package name", manifest_path.display()),
     }
+}
 
-    cmd.arg("build");
-    cmd.arg("--lib");
+/// Returns the reasons the package at the given manifest doesn't look like a Stylus program,
+/// which would otherwise surface as confusing errors late in the build.
+pub fn stylus_manifest_problems(manifest_path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(manifest_path)
+        .wrap_err_with(|| eyre!("failed to read {}", manifest_path.display()))?;
+    let manifest: Value = toml::from_str(&contents)
+        .wrap_err_with(|| eyre!("failed to parse {}", manifest_path.display()))?;
 
-    if !cfg.stable {
-        cmd.arg("-Z");
-        cmd.arg("build-std=std,panic_abort");
-        cmd.arg("-Z");
-        cmd.arg("build-std-features=panic_immediate_abort");
+    let mut dependencies = vec![manifest.get("dependencies")];
+    if let Some(Value::Table(targets)) = manifest.get("target") {
+        dependencies.extend(targets.values().map(|target| target.get("dependencies")));
     }
+    let has_sdk = dependencies
+        .into_iter()
+        .flatten()
+        .any(|deps| deps.get("stylus-sdk").is_some());
 
-    if cfg.opt_level == OptLevel::Z {
-        cmd.arg("--config");
-        cmd.arg("profile.release.opt-level='z'");
+    let mut problems = vec![];
+    if !has_sdk {
+        problems
+            .push("there's no stylus-sdk dependency, add one with `cargo add stylus-sdk`".into());
     }
+    Ok(problems)
+}
 
-    let output = cmd
-        .arg("--release")
-        .arg(format!("--target={RUST_TARGET}"))
+/// The manifest section that builds a package's library as WASM, while keeping the `lib`
+/// crate type that an `export-abi` binary links against.
+pub const CDYLIB_SNIPPET: &str = "[lib]\ncrate-type = [\"lib\", \"cdylib\"]";
+
+/// Explains why the package at the given manifest won't compile to WASM, if its library
+/// isn't built as a cdylib. Cargo would otherwise build successfully, just without any WASM.
+pub fn crate_type_problem(manifest_path: &Path) -> Result<Option<String>> {
+    let contents = fs::read_to_string(manifest_path)
+        .wrap_err_with(|| eyre!("failed to read {}", manifest_path.display()))?;
+    let manifest: Value = toml::from_str(&contents)
+        .wrap_err_with(|| eyre!("failed to parse {}", manifest_path.display()))?;
+
+    let dir = manifest_path.parent().unwrap_or(Path::new(""));
+    let lib = manifest.get("lib");
+    if lib.is_none() && !dir.join("src").join("lib.rs").exists() {
+        return Ok(Some(
+            "the package has no library, only binaries, but Stylus programs are built from \
+             one: move the program into src/lib.rs"
+                .into(),
+        ));
+    }
+    let crate_types = lib
+        .and_then(|lib| lib.get("crate-type"))
+        .and_then(Value::as_array);
+    let Some(types) = crate_types else {
+        return Ok(Some(
+            "the library has no crate-type, so it's built as an rlib rather than a cdylib".into(),
+        ));
+    };
+    if types.iter().any(|t| t.as_str() == Some("cdylib")) {
+        return Ok(None);
+    }
+    let types: Vec<_> = types.iter().map(Value::to_string).collect();
+    Ok(Some(format!(
+        "the library's crate-type is [{}], which doesn't include cdylib",
+        types.join(", ")
+    )))
+}
+
+/// Build a Rust project to WASM and return the path to the compiled WASM file.
+pub fn build_dylib(cfg: BuildConfig) -> Result<PathBuf> {
+    let cwd: PathBuf = current_dir().map_err(|e| eyre!("could not get current dir: {e}"))?;
+
+    let manifest_path = cwd.join("Cargo.toml");
+    if cfg.package.is_none() {
+        match manifest_kind(&manifest_path)? {
+            ManifestKind::Virtual => bail!(BuildError::VirtualManifest {
+                path: manifest_path
+            }),
+            ManifestKind::Mixed => greyln!(
+                "building the workspace root package, use `-p <package>` to select another member"
+            ),
+            ManifestKind::Package => {}
+        }
+
+        if let Some(problem) = crate_type_problem(&manifest_path)? {
+            bail!(
+                "{} can't be built as a Stylus program: {problem}.\n\
+                 Stylus programs must be compiled as a cdylib to produce WASM. Add this to \
+                 the manifest, merging it into any [lib] section it has:\n\n{CDYLIB_SNIPPET}",
+                manifest_path.display()
+            );
+        }
+        let problems = stylus_manifest_problems(&manifest_path)?;
+        if !problems.is_empty() && !cfg.allow_non_stylus {
+            bail!(
+                "{} doesn't look like a Stylus program:\n  {}\n\
+                 Pass --allow-non-stylus to build it anyway.",
+                manifest_path.display(),
+                problems.join("\n  ")
+            );
+        }
+        for problem in problems {
+            warnln!(NotStylusProject, "{problem}")?;
+        }
+    }
+
+    // Cargo inherits RUSTC_WRAPPER and RUSTC_WORKSPACE_WRAPPER, such as sccache, from our
+    // environment. Wrappers only cache or observe compilation, so they don't change the WASM.
+    if !cfg.cargo_args.is_empty() {
+        warnln!(
+            ExtraCargoArgs,
+            "building with extra cargo args {}, which must be passed again to verify \
+             the deployment if they change codegen",
+            cfg.cargo_args.join(" ")
+        )?;
+    }
+    let mut cargo = sys::new_command("cargo");
+    if let Some(wrapper) = &cfg.rustc_wrapper {
+        cargo.env("RUSTC_WRAPPER", wrapper);
+    }
+    let output = cargo
+        .args(build_args(&cfg))
         .output()
         .wrap_err("failed to execute cargo build")?;
 
@@ -86,12 +244,59 @@ pub fn build_dylib(cfg: BuildConfig) -> Result<PathBuf> {
         egreyln!("cargo build command failed");
         process::exit(1);
     }
+    if let Some(path) = &cfg.invocation_file {
+        BuildInvocation::new(&cfg, &cwd)?.write(path)?;
+    }
+
+    let wasm_file_path = find_wasm(&cwd.join("target"), &cfg)?;
+
+    let (wasm, code) =
+        compress_wasm(&wasm_file_path, [0u8; 32], None).wrap_err("failed to compress WASM")?;
+
+    greyln!(
+        "contract size: {}",
+        crate::check::format_file_size(code.len(), 16, 24)
+    );
+    greyln!(
+        "wasm size: {}",
+        crate::check::format_file_size(wasm.len(), 96, 128)
+    );
+    Ok(wasm_file_path)
+}
 
-    let release_path = cwd
-        .join("target")
-        .join(RUST_TARGET)
-        .join("release")
-        .join("deps");
+/// Arguments to pass to cargo to build the project to WASM.
+pub fn build_args(cfg: &BuildConfig) -> Vec<String> {
+    let mut args: Vec<String> = vec![];
+    if !cfg.stable {
+        args.push("+nightly".into());
+    }
+    args.extend(["build".into(), "--lib".into()]);
+
+    if let Some(package) = &cfg.package {
+        args.extend(["--package".into(), package.clone()]);
+    }
+
+    if !cfg.stable {
+        args.extend(["-Z".into(), "build-std=std,panic_abort".into()]);
+        args.extend([
+            "-Z".into(),
+            "build-std-features=panic_immediate_abort".into(),
+        ]);
+    }
+
+    if cfg.opt_level == OptLevel::Z {
+        args.extend(["--config".into(), "profile.release.opt-level='z'".into()]);
+    }
+
+    args.push("--release".into());
+    args.push(format!("--target={RUST_TARGET}"));
+    args.extend(cfg.cargo_args.iter().cloned());
+    args
+}
+
+/// Finds the WASM a build produced in the given target directory.
+pub fn find_wasm(target_dir: &Path, cfg: &BuildConfig) -> Result<PathBuf> {
+    let release_path = target_dir.join(RUST_TARGET).join("release").join("deps");
 
     // Gets the files in the release folder.
     let release_files: Vec<PathBuf> = fs::read_dir(&release_path)
@@ -101,49 +306,63 @@ pub fn build_dylib(cfg: BuildConfig) -> Result<PathBuf> {
         .filter(|r| r.is_file())
         .collect();
 
+    // Workspaces share a target dir, so pick the selected member's WASM by its crate name.
+    let crate_name = cfg.package.as_ref().map(|p| p.replace('-', "_"));
+
     let wasm_file_path = release_files
         .into_iter()
         .find(|p| {
             if let Some(ext) = p.file_name() {
-                return ext.to_string_lossy().contains(".wasm");
+                let ext = ext.to_string_lossy();
+                if let Some(name) = &crate_name {
+                    let stem = p.file_stem().unwrap_or_default().to_string_lossy();
+                    return stem.replace('-', "_") == *name && ext.ends_with(".wasm");
+                }
+                return ext.contains(".wasm");
             }
             false
         })
         .ok_or(BuildError::NoWasmFound { path: release_path })?;
-
-    let (wasm, code) =
-        compress_wasm(&wasm_file_path, [0u8; 32]).wrap_err("failed to compress WASM")?;
-
-    greyln!(
-        "contract size: {}",
-        crate::check::format_file_size(code.len(), 16, 24)
-    );
-    greyln!(
-        "wasm size: {}",
-        crate::check::format_file_size(wasm.len(), 96, 128)
-    );
     Ok(wasm_file_path)
 }
 
-fn all_paths(root_dir: &Path, source_file_patterns: Vec<String>) -> Result<Vec<PathBuf>> {
+/// Lists the project's source files, sorted by path. With no patterns, these are its `.rs` files
+/// and manifests, skipping `target`, `.git` and, if `use_gitignore`, whatever the project's
+/// `.gitignore` files exclude.
+pub fn all_paths(
+    root_dir: &Path,
+    source_file_patterns: Vec<String>,
+    use_gitignore: bool,
+) -> Result<Vec<PathBuf>> {
     let mut files = Vec::<PathBuf>::new();
-    let mut directories = Vec::<PathBuf>::new();
-    directories.push(root_dir.to_path_buf()); // Using `from` directly
+    let mut directories = Vec::<(PathBuf, Vec<Rc<Gitignore>>)>::new();
+    directories.push((root_dir.to_path_buf(), vec![])); // Using `from` directly
 
     let glob_paths = expand_glob_patterns(source_file_patterns)?;
+    // Explicitly listed files are hashed even if ignored.
+    let use_gitignore = use_gitignore && glob_paths.is_empty();
 
-    while let Some(dir) = directories.pop() {
+    while let Some((dir, mut ignores)) = directories.pop() {
+        if use_gitignore {
+            if let Some(gitignore) = Gitignore::read(&dir)? {
+                ignores.push(Rc::new(gitignore));
+            }
+        }
         for entry in fs::read_dir(&dir)
             .map_err(|e| eyre!("Unable to read directory {}: {e}", dir.display()))?
         {
             let entry = entry.map_err(|e| eyre!("Error finding file in {}: {e}", dir.display()))?;
             let path = entry.path();
+            let is_dir = path.is_dir();
+            if Gitignore::ignores(&ignores, &path, is_dir) {
+                continue;
+            }
 
-            if path.is_dir() {
+            if is_dir {
                 if path.ends_with("target") || path.ends_with(".git") {
                     continue; // Skip "target" and ".git" directories
                 }
-                directories.push(path);
+                directories.push((path, ignores.clone()));
             } else if path.file_name().map_or(false, |f| {
                 // If the user has has specified a list of source file patterns, check if the file
                 // matches the pattern.
@@ -163,9 +382,104 @@ fn all_paths(root_dir: &Path, source_file_patterns: Vec<String>) -> Result<Vec<P
             }
         }
     }
+    files.sort();
     Ok(files)
 }
 
+/// The patterns of a `.gitignore` file, which apply to the paths beneath its directory.
+///
+/// Only the project's own `.gitignore` files are read, not those of enclosing directories or
+/// the user's global excludes, so the same project lists the same files wherever it's checked
+/// out, including in the reproducible build container.
+struct Gitignore {
+    dir: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+struct IgnoreRule {
+    pattern: glob::Pattern,
+    /// The rule re-includes what an earlier one excluded.
+    negated: bool,
+    /// The rule only matches directories.
+    dir_only: bool,
+    /// The rule matches paths relative to the `.gitignore`, rather than file names at any depth.
+    anchored: bool,
+}
+
+impl Gitignore {
+    fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(".gitignore");
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| eyre!("failed to read {}: {e}", path.display()))?;
+        let rules = text.lines().filter_map(IgnoreRule::parse).collect();
+        Ok(Some(Self {
+            dir: dir.to_path_buf(),
+            rules,
+        }))
+    }
+
+    /// Whether the last rule matching the path excludes it, with deeper files taking precedence.
+    fn ignores(gitignores: &[Rc<Self>], path: &Path, is_dir: bool) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let mut ignored = false;
+        for gitignore in gitignores {
+            let Ok(relative) = path.strip_prefix(&gitignore.dir) else {
+                continue;
+            };
+            let name = path.file_name().unwrap_or_default();
+            for rule in &gitignore.rules {
+                if rule.dir_only && !is_dir {
+                    continue;
+                }
+                let target = match rule.anchored {
+                    true => relative.as_os_str(),
+                    false => name,
+                };
+                if rule
+                    .pattern
+                    .matches_with(&target.to_string_lossy(), options)
+                {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        // Like git, skip patterns that aren't valid rather than failing.
+        let pattern = glob::Pattern::new(line).ok()?;
+        Some(Self {
+            pattern,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
+
 pub fn extract_toolchain_channel(toolchain_file_path: &PathBuf) -> Result<String> {
     let toolchain_file_contents = std::fs::read_to_string(toolchain_file_path).wrap_err(
         "expected to find a rust-toolchain.toml file in project directory \
@@ -193,7 +507,16 @@ pub fn extract_toolchain_channel(toolchain_file_path: &PathBuf) -> Result<String
 }
 
 pub fn hash_files(source_file_patterns: Vec<String>, cfg: BuildConfig) -> Result<[u8; 32]> {
-    let mut keccak = Keccak::v256();
+    let hash = project_hash(&cargo_version(&cfg)?, source_file_patterns, &cfg)?;
+    greyln!(
+        "Project hash computed on deployment: {:?}",
+        hex::encode(hash)
+    );
+    Ok(hash)
+}
+
+/// Output of `cargo --version` for the toolchain the build uses.
+pub fn cargo_version(cfg: &BuildConfig) -> Result<Vec<u8>> {
     let mut cmd = Command::new("cargo");
     if !cfg.stable {
         cmd.arg("+nightly");
@@ -205,25 +528,80 @@ pub fn hash_files(source_file_patterns: Vec<String>, cfg: BuildConfig) -> Result
     if !output.status.success() {
         bail!("cargo version command failed");
     }
-    keccak.update(&output.stdout);
+    Ok(output.stdout)
+}
+
+/// Hashes the project's sources along with the toolchain that builds them, as reported by
+/// `cargo --version`.
+pub fn project_hash(
+    cargo_version: &[u8],
+    source_file_patterns: Vec<String>,
+    cfg: &BuildConfig,
+) -> Result<[u8; 32]> {
+    let mut keccak = Keccak::v256();
+    keccak.update(cargo_version);
     if cfg.opt_level == OptLevel::Z {
         keccak.update(&[0]);
     } else {
         keccak.update(&[1]);
     }
 
+    hash_sources(&mut keccak, Path::new("."), source_file_patterns, cfg)?;
+
+    let mut hash = [0u8; 32];
+    keccak.finalize(&mut hash);
+    Ok(hash)
+}
+
+/// Hashes the declared source files of the project rooted at `root`.
+///
+/// Only source contents and their paths relative to the root are hashed, so the result
+/// is independent of where the project lives and of anything the build produces.
+/// With `normalize_manifest_paths`, absolute paths in `Cargo.toml` and `Cargo.lock` that
+/// point into the project or the user's home directory are hashed as placeholders too.
+fn hash_sources(
+    keccak: &mut Keccak,
+    root: &Path,
+    source_file_patterns: Vec<String>,
+    cfg: &BuildConfig,
+) -> Result<()> {
     let mut buf = vec![0u8; 0x100000];
+    let machine_paths = match cfg.normalize_manifest_paths {
+        true => machine_paths(root),
+        false => vec![],
+    };
 
-    let mut hash_file = |filename: &Path| -> Result<()> {
+    let mut hash_file = |path: &Path| -> Result<()> {
+        // Hash paths as `./<relative path>` regardless of the root.
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let filename = Path::new(".").join(relative);
         keccak.update(&(filename.as_os_str().len() as u64).to_be_bytes());
         keccak.update(filename.as_os_str().as_encoded_bytes());
-        let mut file = std::fs::File::open(filename)
-            .map_err(|e| eyre!("failed to open file {}: {e}", filename.display()))?;
+
+        let is_manifest = ["Cargo.toml", "Cargo.lock"]
+            .iter()
+            .any(|name| path.file_name() == Some(name.as_ref()));
+        if is_manifest && !machine_paths.is_empty() {
+            let mut contents = fs::read_to_string(path)
+                .map_err(|e| eyre!("failed to read file {}: {e}", path.display()))?;
+            for (prefix, placeholder) in &machine_paths {
+                // Only whole path components, so `/a/proj` doesn't touch `/a/proj2`.
+                contents = contents
+                    .replace(&format!("{prefix}/"), &format!("{placeholder}/"))
+                    .replace(&format!("\"{prefix}\""), &format!("\"{placeholder}\""));
+            }
+            keccak.update(&(contents.len() as u64).to_be_bytes());
+            keccak.update(contents.as_bytes());
+            return Ok(());
+        }
+
+        let mut file = std::fs::File::open(path)
+            .map_err(|e| eyre!("failed to open file {}: {e}", path.display()))?;
         keccak.update(&file.metadata().unwrap().len().to_be_bytes());
         loop {
             let bytes_read = file
                 .read(&mut buf)
-                .map_err(|e| eyre!("Unable to read file {}: {e}", filename.display()))?;
+                .map_err(|e| eyre!("Unable to read file {}: {e}", path.display()))?;
             if bytes_read == 0 {
                 break;
             }
@@ -234,31 +612,58 @@ pub fn hash_files(source_file_patterns: Vec<String>, cfg: BuildConfig) -> Result
 
     // Fetch the Rust toolchain toml file from the project root. Assert that it exists and add it to the
     // files in the directory to hash.
-    let toolchain_file_path = PathBuf::from(".").as_path().join(TOOLCHAIN_FILE_NAME);
+    let toolchain_file_path = root.join(TOOLCHAIN_FILE_NAME);
     let _ = std::fs::metadata(&toolchain_file_path).wrap_err(
         "expected to find a rust-toolchain.toml file in project directory \
          to specify your Rust toolchain for reproducible verification",
     )?;
 
-    let mut paths = all_paths(PathBuf::from(".").as_path(), source_file_patterns)?;
+    let mut paths = all_paths(root, source_file_patterns, !cfg.no_ignore)?;
     paths.push(toolchain_file_path);
     paths.sort();
 
     for filename in paths.iter() {
+        if is_build_artifact(filename.strip_prefix(root).unwrap_or(filename)) {
+            bail!(
+                "refusing to include build output {} in the project hash",
+                filename.display()
+            );
+        }
         greyln!(
             "File used for deployment hash: {}",
             filename.as_os_str().to_string_lossy()
         );
         hash_file(filename)?;
     }
+    Ok(())
+}
 
-    let mut hash = [0u8; 32];
-    keccak.finalize(&mut hash);
-    greyln!(
-        "Project hash computed on deployment: {:?}",
-        hex::encode(hash)
-    );
-    Ok(hash)
+/// Absolute path prefixes that differ between machines, longest first, with the
+/// placeholders they're hashed as: the project root, then the user's home directory.
+fn machine_paths(root: &Path) -> Vec<(String, &'static str)> {
+    let mut paths = vec![];
+    if let Ok(root) = root.canonicalize() {
+        paths.push((root, "."));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        paths.push((PathBuf::from(home), "~"));
+    }
+    paths
+        .into_iter()
+        .filter(|(path, _)| path.parent().is_some())
+        .map(|(path, placeholder)| (path.to_string_lossy().into_owned(), placeholder))
+        .collect()
+}
+
+/// Whether a path points at something produced by a build rather than a source file.
+fn is_build_artifact(path: &Path) -> bool {
+    if path.components().any(|c| c.as_os_str() == "target") {
+        return true;
+    }
+    let ext = path.extension().unwrap_or_default();
+    ["wasm", "rlib", "so", "dylib", "d"]
+        .iter()
+        .any(|a| ext == *a)
 }
 
 fn expand_glob_patterns(patterns: Vec<String>) -> Result<Vec<PathBuf>> {
@@ -275,24 +680,49 @@ fn expand_glob_patterns(patterns: Vec<String>) -> Result<Vec<PathBuf>> {
 }
 
 /// Reads a WASM file at a specified path and returns its brotli compressed bytes.
-pub fn compress_wasm(wasm: &PathBuf, project_hash: [u8; 32]) -> Result<(Vec<u8>, Vec<u8>)> {
+pub fn compress_wasm(
+    wasm: &PathBuf,
+    project_hash: [u8; 32],
+    contract_version: Option<&Version>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
     let wasm =
         fs::read(wasm).wrap_err_with(|| eyre!("failed to read Wasm {}", wasm.to_string_lossy()))?;
 
-    let wasm = add_project_hash_to_wasm_file(&wasm, project_hash)
+    let mut wasm = add_project_hash_to_wasm_file(&wasm, project_hash)
         .wrap_err("failed to add project hash to wasm file as custom section")?;
+    if let Some(version) = contract_version {
+        wasm = add_contract_version(&wasm, version)?;
+    }
     let wasm = wasmer::wat2wasm(&wasm).wrap_err("failed to parse Wasm")?;
+    let contract_code = compress_code(wasm.as_ref())?;
 
-    let mut compressor = BrotliEncoder::new(&*wasm, BROTLI_COMPRESSION_LEVEL);
-    let mut compressed_bytes = vec![];
-    compressor
-        .read_to_end(&mut compressed_bytes)
-        .wrap_err("failed to compress WASM bytes")?;
+    Ok((wasm.into_owned(), contract_code))
+}
 
-    let mut contract_code = hex::decode(EOF_PREFIX_NO_DICT).unwrap();
-    contract_code.extend(compressed_bytes);
+/// Brotli compresses WASM after the Stylus EOF prefix, streaming it through the encoder so
+/// that the encoder's memory is bounded by its window rather than the size of the input.
+fn compress_code(mut wasm: impl Read) -> Result<Vec<u8>> {
+    let prefix = hex::decode(EOF_PREFIX_NO_DICT).unwrap();
+    let mut params = CompressParams::new();
+    params
+        .quality(BROTLI_COMPRESSION_LEVEL)
+        .lgwin(BROTLI_WINDOW_BITS);
+    let mut encoder = BrotliEncoder::from_params(prefix, &params);
+    io::copy(&mut wasm, &mut encoder).wrap_err("failed to compress WASM bytes")?;
+    encoder.finish().wrap_err("failed to compress WASM bytes")
+}
 
-    Ok((wasm.to_vec(), contract_code))
+/// Decompresses a Stylus program's onchain code back into its WASM.
+pub fn decompress_code(code: &[u8]) -> Result<Vec<u8>> {
+    let prefix = hex::decode(EOF_PREFIX_NO_DICT).unwrap();
+    let Some(compressed) = code.strip_prefix(prefix.as_slice()) else {
+        bail!("code does not start with Stylus prefix {EOF_PREFIX_NO_DICT}");
+    };
+    let mut wasm = vec![];
+    BrotliDecoder::new(compressed)
+        .read_to_end(&mut wasm)
+        .wrap_err("failed to decompress WASM")?;
+    Ok(wasm)
 }
 
 // Adds the hash of the project's source files to the wasm as a custom section
@@ -304,7 +734,7 @@ fn add_project_hash_to_wasm_file(
 ) -> Result<Vec<u8>> {
     let section_exists = has_project_hash_section(wasm_file_bytes)?;
     if section_exists {
-        greyln!("Wasm file bytes already contains a custom section with a project hash, not overwriting'");
+        warnln!(ProjectHashExists, "Wasm file bytes already contains a custom section with a project hash, not overwriting")?;
         return Ok(wasm_file_bytes.to_vec());
     }
     Ok(add_custom_section(wasm_file_bytes, project_hash))
@@ -315,7 +745,7 @@ pub fn has_project_hash_section(wasm_file_bytes: &[u8]) -> Result<bool> {
     for payload in parser.parse_all(wasm_file_bytes) {
         if let wasmparser::Payload::CustomSection(reader) = payload? {
             if reader.name() == PROJECT_HASH_SECTION_NAME {
-                println!(
+                greyln!(
                     "Found the project hash custom section name {}",
                     hex::encode(reader.data())
                 );
@@ -326,6 +756,195 @@ pub fn has_project_hash_section(wasm_file_bytes: &[u8]) -> Result<bool> {
     Ok(false)
 }
 
+/// Appends a custom section holding the contract version, after the project hash so that the
+/// same sources and version always produce the same WASM.
+fn add_contract_version(wasm: &[u8], version: &Version) -> Result<Vec<u8>> {
+    let version = version.to_string();
+    match contract_version(wasm)? {
+        Some(existing) if existing == version => return Ok(wasm.to_vec()),
+        Some(existing) => bail!("wasm already embeds contract version {existing}, not {version}"),
+        None => {}
+    }
+    let mut bytes = wasm.to_vec();
+    wasm_gen::write_custom_section(
+        &mut bytes,
+        CONTRACT_VERSION_SECTION_NAME,
+        version.as_bytes(),
+    );
+    Ok(bytes)
+}
+
+/// Reads the contract version a WASM embeds with `--contract-version`, if any.
+pub fn contract_version(wasm: &[u8]) -> Result<Option<String>> {
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        if let wasmparser::Payload::CustomSection(reader) = payload? {
+            if reader.name() == CONTRACT_VERSION_SECTION_NAME {
+                let version = String::from_utf8(reader.data().to_vec())
+                    .wrap_err("contract version isn't UTF-8")?;
+                return Ok(Some(version));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Checks the shape of a Stylus program without needing the chain: that it exports a
+/// `fn(i32) -> i32` named `entrypoint`, and only imports from the host modules.
+pub fn check_structure(wasm_file_bytes: &[u8], entrypoint: &str) -> Result<()> {
+    use wasmparser::{CompositeInnerType, ExternalKind, Payload, TypeRef, ValType};
+
+    let mut types = vec![];
+    let mut functions = vec![];
+    let mut export = None;
+    for payload in wasmparser::Parser::new(0).parse_all(wasm_file_bytes) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                for group in reader {
+                    types.extend(group?.into_types().map(|ty| ty.composite_type.inner));
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if !HOST_MODULES.contains(&import.module) {
+                        bail!(
+                            "wasm imports {}::{}, but Stylus programs may only import from {}",
+                            import.module,
+                            import.name,
+                            HOST_MODULES.join(" and ")
+                        );
+                    }
+                    if let TypeRef::Func(ty) = import.ty {
+                        functions.push(ty);
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for ty in reader {
+                    functions.push(ty?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for item in reader {
+                    let item = item?;
+                    if item.name == entrypoint && item.kind == ExternalKind::Func {
+                        export = Some(item.index);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(index) = export else {
+        bail!("wasm doesn't export an entrypoint function named {entrypoint}");
+    };
+    let ty = functions
+        .get(index as usize)
+        .and_then(|ty| types.get(*ty as usize));
+    let signature_ok = match ty {
+        Some(CompositeInnerType::Func(ty)) => {
+            ty.params() == [ValType::I32] && ty.results() == [ValType::I32]
+        }
+        _ => false,
+    };
+    if !signature_ok {
+        bail!("entrypoint {entrypoint} should take and return an i32");
+    }
+    Ok(())
+}
+
+/// The WASM features a chain accepts, falling back to ArbOS's for chains without a preset.
+pub fn chain_wasm_features(chain_id: u64) -> WasmFeatures {
+    CHAIN_PRESETS
+        .iter()
+        .find(|preset| preset.chain_id == chain_id)
+        .map_or(STYLUS_WASM_FEATURES, |preset| preset.wasm_features)
+}
+
+/// Parses a WASM feature named like `bulk-memory`, for `--allow-wasm-feature`.
+pub fn parse_wasm_feature(name: &str) -> Result<WasmFeatures, String> {
+    let flag = name.to_uppercase().replace('-', "_");
+    WasmFeatures::from_name(&flag).ok_or_else(|| {
+        let names: Vec<_> = WasmFeatures::all().iter_names().map(feature_name).collect();
+        format!(
+            "unknown WASM feature {name}, expected one of: {}",
+            names.join(", ")
+        )
+    })
+}
+
+fn feature_name((flag, _): (&str, WasmFeatures)) -> String {
+    flag.to_lowercase().replace('_', "-")
+}
+
+/// Fails unless the WASM validates with only the allowed features, naming the ones it uses that
+/// aren't allowed.
+pub fn check_wasm_features(wasm_file_bytes: &[u8], allowed: WasmFeatures) -> Result<()> {
+    let validates = |features| {
+        wasmparser::Validator::new_with_features(features)
+            .validate_all(wasm_file_bytes)
+            .is_ok()
+    };
+    if validates(allowed) {
+        return Ok(());
+    }
+    let all = WasmFeatures::all();
+    if let Err(error) = wasmparser::Validator::new_with_features(all).validate_all(wasm_file_bytes)
+    {
+        bail!("wasm is invalid: {error}");
+    }
+    // A feature is used if the WASM stops validating once it alone is taken away.
+    let used: Vec<_> = all
+        .difference(allowed)
+        .iter_names()
+        .filter(|(_, feature)| !validates(all.difference(*feature)))
+        .map(feature_name)
+        .collect();
+    match used.is_empty() {
+        true => bail!("wasm uses a combination of WASM features the chain doesn't allow"),
+        false => bail!(
+            "wasm uses {}, which the chain doesn't allow (see --allow-wasm-feature)",
+            used.join(", ")
+        ),
+    }
+}
+
+/// Returns the name and byte size of each section in the WASM, largest first.
+/// Custom sections are named after their own name, e.g. `custom "name"`.
+pub fn wasm_section_sizes(wasm_file_bytes: &[u8]) -> Result<Vec<(String, usize)>> {
+    let mut sections = vec![];
+    for payload in wasmparser::Parser::new(0).parse_all(wasm_file_bytes) {
+        let payload = payload?;
+        let Some((id, range)) = payload.as_section() else {
+            continue;
+        };
+        let name = match &payload {
+            wasmparser::Payload::CustomSection(reader) => format!("custom {:?}", reader.name()),
+            _ => match id {
+                1 => "type",
+                2 => "import",
+                3 => "function",
+                4 => "table",
+                5 => "memory",
+                6 => "global",
+                7 => "export",
+                8 => "start",
+                9 => "element",
+                10 => "code",
+                11 => "data",
+                12 => "data count",
+                13 => "tag",
+                _ => "unknown",
+            }
+            .to_string(),
+        };
+        sections.push((name, range.len()));
+    }
+    sections.sort_by(|a, b| b.1.cmp(&a.1));
+    Ok(sections)
+}
+
 fn add_custom_section(wasm_file_bytes: &[u8], project_hash: [u8; 32]) -> Vec<u8> {
     let mut bytes = vec![];
     bytes.extend_from_slice(wasm_file_bytes);
@@ -336,6 +955,7 @@ fn add_custom_section(wasm_file_bytes: &[u8], project_hash: [u8; 32]) -> Vec<u8>
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::constants::ENTRYPOINT_SYMBOL;
     use std::fs::{self, File};
     use std::io::Write;
     use tempfile::tempdir;
@@ -384,6 +1004,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_all_paths() -> Result<()> {
         let dir = tempdir()?;
         let dir_path = dir.path();
@@ -416,6 +1037,7 @@ mod test {
                 dir_path.as_os_str().to_string_lossy(),
                 "**/*.rs"
             )],
+            true,
         )?;
 
         // Check that the correct files are included
@@ -427,4 +1049,271 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_check_structure() -> Result<()> {
+        let wasm = |wat: &str| wasmer::wat2wasm(wat.as_bytes()).unwrap().into_owned();
+        let program = wasm(
+            r#"(module
+                (import "vm_hooks" "read_args" (func (param i32)))
+                (func (export "renamed_entrypoint") (param i32) (result i32) i32.const 0))"#,
+        );
+        check_structure(&program, "renamed_entrypoint")?;
+        let err = check_structure(&program, ENTRYPOINT_SYMBOL).unwrap_err();
+        assert!(err.to_string().contains("named user_entrypoint"));
+
+        let program = wasm(
+            r#"(module
+                (import "env" "abort" (func))
+                (func (export "user_entrypoint") (param i32) (result i32) i32.const 0))"#,
+        );
+        let err = check_structure(&program, ENTRYPOINT_SYMBOL).unwrap_err();
+        assert!(err.to_string().contains("env::abort"));
+
+        let program = wasm(r#"(module (func (export "user_entrypoint") (param i64)))"#);
+        assert!(check_structure(&program, ENTRYPOINT_SYMBOL).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_wasm_features() -> Result<()> {
+        let wasm = |wat: &str| wasmer::wat2wasm(wat.as_bytes()).unwrap().into_owned();
+        let program = wasm(
+            r#"(module (memory 1)
+                (func (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))))"#,
+        );
+        check_wasm_features(&program, STYLUS_WASM_FEATURES)?;
+
+        let program = wasm(r#"(module (func (drop (f32.const 1)) (drop (v128.const i64x2 0 0))))"#);
+        let err = check_wasm_features(&program, STYLUS_WASM_FEATURES).unwrap_err();
+        assert!(err.to_string().contains("wasm uses simd, which"));
+        let allowed = STYLUS_WASM_FEATURES | parse_wasm_feature("simd").unwrap();
+        check_wasm_features(&program, allowed)?;
+        assert!(parse_wasm_feature("fancy-floats").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_paths_respects_gitignore() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        for dir in ["src", "vendor/dep", "generated"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        for file in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/bindings.rs",
+            "src/keep.rs",
+            "vendor/dep/lib.rs",
+            "generated/abi.rs",
+        ] {
+            fs::write(root.join(file), "")?;
+        }
+        fs::write(root.join(".gitignore"), "# deps\n/vendor/\nbindings.rs\n")?;
+        fs::write(root.join("generated/.gitignore"), "*.rs\n")?;
+        fs::write(root.join("src/.gitignore"), "*.rs\n!keep.rs\n!lib.rs\n")?;
+
+        let found = all_paths(root, vec![], true)?;
+        let expected: Vec<_> = ["Cargo.toml", "src/keep.rs", "src/lib.rs"]
+            .iter()
+            .map(|file| root.join(file))
+            .collect();
+        assert_eq!(found, expected);
+        assert_eq!(all_paths(root, vec![], false)?.len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_kind() -> Result<()> {
+        let dir = tempdir()?;
+        let manifest_path = dir.path().join("Cargo.toml");
+
+        let manifest = r#"
+            [package]
+            name = "counter"
+        "#;
+        std::fs::write(&manifest_path, manifest)?;
+        assert_eq!(manifest_kind(&manifest_path)?, ManifestKind::Package);
+
+        let manifest = r#"
+            [workspace]
+            members = ["contracts/*"]
+        "#;
+        std::fs::write(&manifest_path, manifest)?;
+        assert_eq!(manifest_kind(&manifest_path)?, ManifestKind::Virtual);
+
+        let manifest = r#"
+            [package]
+            name = "counter"
+
+            [workspace]
+            members = ["contracts/*"]
+        "#;
+        std::fs::write(&manifest_path, manifest)?;
+        assert_eq!(manifest_kind(&manifest_path)?, ManifestKind::Mixed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stylus_manifest_problems() -> Result<()> {
+        let dir = tempdir()?;
+        let manifest_path = dir.path().join("Cargo.toml");
+
+        let manifest = r#"
+            [package]
+            name = "counter"
+
+            [dependencies]
+            stylus-sdk = "0.6.0"
+
+            [lib]
+            crate-type = ["lib", "cdylib"]
+        "#;
+        std::fs::write(&manifest_path, manifest)?;
+        assert!(stylus_manifest_problems(&manifest_path)?.is_empty());
+        assert!(crate_type_problem(&manifest_path)?.is_none());
+
+        let manifest = r#"
+            [package]
+            name = "counter"
+
+            [target.'cfg(target_arch = "wasm32")'.dependencies]
+            stylus-sdk = { workspace = true }
+        "#;
+        std::fs::write(&manifest_path, manifest)?;
+        assert!(stylus_manifest_problems(&manifest_path)?.is_empty());
+        let problem = crate_type_problem(&manifest_path)?.unwrap();
+        assert!(problem.contains("no library"));
+
+        std::fs::create_dir(dir.path().join("src"))?;
+        std::fs::write(dir.path().join("src").join("lib.rs"), "")?;
+        let problem = crate_type_problem(&manifest_path)?.unwrap();
+        assert!(problem.contains("rlib"));
+
+        std::fs::write(
+            &manifest_path,
+            "[package]\nname = \"counter\"\n[lib]\ncrate-type = [\"rlib\"]\n",
+        )?;
+        assert_eq!(stylus_manifest_problems(&manifest_path)?.len(), 1);
+        let problem = crate_type_problem(&manifest_path)?.unwrap();
+        assert!(problem.contains("[\"rlib\"]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_sources_ignores_location_and_build_outputs() -> Result<()> {
+        let hash_project = |target_contents: &[u8]| -> Result<[u8; 32]> {
+            let dir = tempdir()?;
+            let root = dir.path();
+            fs::create_dir_all(root.join("src"))?;
+            fs::write(
+                root.join(TOOLCHAIN_FILE_NAME),
+                "[toolchain]\nchannel = \"1.80\"\n",
+            )?;
+            fs::write(root.join("Cargo.toml"), "[package]\nname = \"counter\"\n")?;
+            fs::write(root.join("src").join("lib.rs"), "pub fn counter() {}\n")?;
+
+            let release = root.join("target").join(RUST_TARGET).join("release");
+            fs::create_dir_all(&release)?;
+            fs::write(release.join("counter.wasm"), target_contents)?;
+            fs::write(release.join("build.rs"), target_contents)?;
+
+            let mut keccak = Keccak::v256();
+            hash_sources(&mut keccak, root, vec![], &BuildConfig::default())?;
+            let mut hash = [0u8; 32];
+            keccak.finalize(&mut hash);
+            Ok(hash)
+        };
+
+        // Two separate project dirs whose target dirs hold different build outputs.
+        let first = hash_project(b"first machine")?;
+        let second = hash_project(&[0u8; 4096])?;
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_sources_normalizes_manifest_paths() -> Result<()> {
+        let hash_project = |normalize: bool| -> Result<[u8; 32]> {
+            let dir = tempdir()?;
+            let root = dir.path().canonicalize()?;
+            fs::write(
+                root.join(TOOLCHAIN_FILE_NAME),
+                "[toolchain]\nchannel = \"1.80\"\n",
+            )?;
+            let manifest = format!(
+                "[package]\nname = \"counter\"\n\n[dependencies]\nshared = {{ path = \"{}\" }}\n",
+                root.join("shared").display()
+            );
+            fs::write(root.join("Cargo.toml"), manifest)?;
+
+            let mut keccak = Keccak::v256();
+            let cfg = BuildConfig {
+                normalize_manifest_paths: normalize,
+                ..Default::default()
+            };
+            hash_sources(&mut keccak, &root, vec![], &cfg)?;
+            let mut hash = [0u8; 32];
+            keccak.finalize(&mut hash);
+            Ok(hash)
+        };
+
+        // Each project lives under a different absolute prefix.
+        assert_ne!(hash_project(false)?, hash_project(false)?);
+        assert_eq!(hash_project(true)?, hash_project(true)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_code_round_trips() -> Result<()> {
+        // A large input with some structure so brotli has real work to do.
+        let wasm: Vec<u8> = (0..0x80123usize)
+            .map(|i| ((i * 7919) % 251) as u8 ^ (i >> 12) as u8)
+            .collect();
+
+        let code = compress_code(wasm.as_slice())?;
+        assert!(code.starts_with(&hex::decode(EOF_PREFIX_NO_DICT)?));
+        assert_eq!(decompress_code(&code)?, wasm);
+
+        // Streaming mustn't change the code, or programs would no longer match their deployments.
+        let mut previous = hex::decode(EOF_PREFIX_NO_DICT)?;
+        brotli2::read::BrotliEncoder::new(wasm.as_slice(), BROTLI_COMPRESSION_LEVEL)
+            .read_to_end(&mut previous)?;
+        assert_eq!(code, previous);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_sources_rejects_build_artifacts() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(
+            root.join(TOOLCHAIN_FILE_NAME),
+            "[toolchain]\nchannel = \"1.80\"\n",
+        )?;
+        fs::write(root.join("counter.wasm"), "wasm")?;
+
+        let pattern = format!("{}/*.wasm", root.to_string_lossy());
+        let mut keccak = Keccak::v256();
+        let Err(err) = hash_sources(&mut keccak, root, vec![pattern], &BuildConfig::default())
+        else {
+            panic!("expected an error");
+        };
+        assert!(err.to_string().contains("refusing to include build output"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_version_section() -> Result<()> {
+        let wasm = b"\0asm\x01\0\0\0";
+        assert_eq!(contract_version(wasm)?, None);
+
+        let version = Version::parse("1.2.0-rc.1")?;
+        let versioned = add_contract_version(wasm, &version)?;
+        assert_eq!(contract_version(&versioned)?.as_deref(), Some("1.2.0-rc.1"));
+        assert_eq!(add_contract_version(&versioned, &version)?, versioned);
+        assert!(add_contract_version(&versioned, &Version::new(2, 0, 0)).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,167 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    constants::RUST_TARGET,
+    deploy, docker,
+    macros::*,
+    project::{self, BuildConfig},
+    ReproReportConfig,
+};
+use cargo_stylus_util::{color::Color, sys};
+use eyre::{bail, eyre, Result, WrapErr};
+use std::{env, fs, path::Path};
+
+/// Where the Docker build writes its artifacts, so it doesn't clobber the local build.
+const DOCKER_TARGET_DIR: &str = "target/repro-docker";
+
+/// Toolchain details that can make two builds of the same sources differ.
+struct Environment {
+    /// Output of `cargo --version`, which is part of the project hash.
+    cargo_version: Vec<u8>,
+    /// Fields reported by `rustc -vV`, such as the release and LLVM version.
+    rustc: Vec<(String, String)>,
+    /// Extra flags passed to rustc through the environment.
+    rustflags: String,
+}
+
+/// Builds the project locally and in the reproducible Docker image, then reports whether the
+/// two builds match and how their environments differ.
+pub fn repro_report(cfg: &ReproReportConfig) -> Result<()> {
+    let build_cfg = BuildConfig::new(cfg.rust_stable, cfg.package.clone());
+    let toolchain: &[&str] = match cfg.rust_stable {
+        true => &[],
+        false => &["+nightly"],
+    };
+
+    greyln!("building locally");
+    let local_wasm = project::build_dylib(build_cfg.clone())?;
+    let local_env = Environment {
+        cargo_version: project::cargo_version(&build_cfg)?,
+        rustc: parse_rustc_version(&local_output("rustc", &[toolchain, &["-vV"]].concat())?),
+        rustflags: env::var("RUSTFLAGS").unwrap_or_default(),
+    };
+
+    greyln!(
+        "building in Docker with Rust {}",
+        cfg.rust_version.lavender()
+    );
+    let docker = |command_line: &[&str]| {
+        docker::reproducible_output(&cfg.rust_version, &cfg.image, command_line)
+    };
+    let build_args = project::build_args(&build_cfg);
+    let mut command_line = vec!["cargo"];
+    command_line.extend(build_args.iter().map(String::as_str));
+    command_line.extend(["--target-dir", DOCKER_TARGET_DIR]);
+    docker(&command_line)?;
+    let docker_wasm = project::find_wasm(Path::new(DOCKER_TARGET_DIR), &build_cfg)?;
+    let docker_env = Environment {
+        cargo_version: docker(&[&["cargo"], toolchain, &["--version"]].concat())?,
+        rustc: parse_rustc_version(&docker(&[&["rustc"], toolchain, &["-vV"]].concat())?),
+        rustflags: String::from_utf8_lossy(&docker(&["sh", "-c", "printf %s \"$RUSTFLAGS\""])?)
+            .into_owned(),
+    };
+
+    let local_bytes = fs::read(&local_wasm).wrap_err("failed to read local WASM")?;
+    let docker_bytes = fs::read(&docker_wasm).wrap_err("failed to read Docker WASM")?;
+    let local_init_code = init_code(&local_wasm, &local_env, cfg, &build_cfg)?;
+    let docker_init_code = init_code(&docker_wasm, &docker_env, cfg, &build_cfg)?;
+
+    println!();
+    report("wasm", &local_bytes, &docker_bytes);
+    report("init code", &local_init_code, &docker_init_code);
+
+    println!("environment:");
+    println!("  target: {RUST_TARGET}");
+    println!("  cargo flags: {}", build_args.join(" "));
+    let mut differences = 0;
+    let mut compare = |name: &str, local: &str, docker: &str| {
+        if local != docker {
+            differences += 1;
+            println!("  {name}: local {} / docker {}", local.red(), docker.red());
+        } else if !local.is_empty() {
+            println!("  {name}: {local}");
+        }
+    };
+    compare(
+        "cargo",
+        String::from_utf8_lossy(&local_env.cargo_version).trim(),
+        String::from_utf8_lossy(&docker_env.cargo_version).trim(),
+    );
+    let lookup = |fields: &[(String, String)], key: &str| {
+        let field = fields.iter().find(|(k, _)| k == key);
+        field.map(|(_, v)| v.clone()).unwrap_or_default()
+    };
+    let mut keys: Vec<&String> = local_env.rustc.iter().map(|(k, _)| k).collect();
+    for (key, _) in &docker_env.rustc {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    for key in keys {
+        compare(
+            &format!("rustc {key}"),
+            &lookup(&local_env.rustc, key),
+            &lookup(&docker_env.rustc, key),
+        );
+    }
+    compare("RUSTFLAGS", &local_env.rustflags, &docker_env.rustflags);
+    if differences == 0 {
+        println!("  no differences found");
+    }
+    Ok(())
+}
+
+/// Computes the init code a deployment of the WASM built in the given environment would use.
+fn init_code(
+    wasm: &Path,
+    env: &Environment,
+    cfg: &ReproReportConfig,
+    build_cfg: &BuildConfig,
+) -> Result<Vec<u8>> {
+    let patterns = cfg.source_files_for_project_hash.clone();
+    let project_hash = project::project_hash(&env.cargo_version, patterns, build_cfg)?;
+    let (_, code) = project::compress_wasm(&wasm.to_path_buf(), project_hash, None)?;
+    Ok(deploy::program_deployment_calldata(&code))
+}
+
+fn report(name: &str, local: &[u8], docker: &[u8]) {
+    match first_divergence(local, docker) {
+        None => println!("{name}: {} ({} bytes)", "match".mint(), local.len()),
+        Some(offset) => println!(
+            "{name}: {} at byte {offset} (local {} bytes, docker {} bytes)",
+            "differs".red(),
+            local.len(),
+            docker.len()
+        ),
+    }
+}
+
+/// Offset of the first byte at which the two differ, if any.
+fn first_divergence(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(offset) => Some(offset),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+fn local_output(program: &str, args: &[&str]) -> Result<Vec<u8>> {
+    let output = sys::new_command(program)
+        .args(args)
+        .output()
+        .map_err(|e| eyre!("failed to execute {program}: {e}"))?;
+    if !output.status.success() {
+        bail!("{program} {} failed", args.join(" "));
+    }
+    Ok(output.stdout)
+}
+
+/// Splits `rustc -vV` output into its `key: value` fields.
+fn parse_rustc_version(output: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
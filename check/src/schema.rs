@@ -0,0 +1,65 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    cache::CacheForecast,
+    capabilities::Capabilities,
+    deploy::ActivationCalldata,
+    export_abi::RegistryEntry,
+    invocation::BuildInvocation,
+    package::BuildInfo,
+    size::SizeReport,
+    summary::Summary,
+    verify::{BatchReport, DeploymentsReport, VerifyResult},
+};
+use eyre::{bail, Result};
+use schemars::{schema::RootSchema, schema_for};
+use serde_json::Value;
+
+/// Names of the JSON outputs with a schema, as passed to `json-schema`.
+pub const OUTPUTS: &[&str] = &[
+    "activation-calldata",
+    "build-info",
+    "build-invocation",
+    "cache-forecast",
+    "capabilities",
+    "registry-entry",
+    "size-report",
+    "summary",
+    "verify",
+    "verify-batch",
+    "verify-deployments",
+];
+
+fn schema(output: &str) -> Option<RootSchema> {
+    Some(match output {
+        "activation-calldata" => schema_for!(ActivationCalldata),
+        "build-info" => schema_for!(BuildInfo),
+        "build-invocation" => schema_for!(BuildInvocation),
+        "cache-forecast" => schema_for!(CacheForecast),
+        "capabilities" => schema_for!(Capabilities),
+        "registry-entry" => schema_for!(RegistryEntry),
+        "size-report" => schema_for!(SizeReport),
+        "summary" => schema_for!(Vec<Summary>),
+        "verify" => schema_for!(VerifyResult),
+        "verify-batch" => schema_for!(BatchReport),
+        "verify-deployments" => schema_for!(DeploymentsReport),
+        _ => return None,
+    })
+}
+
+/// Prints the JSON Schema of a command's JSON output, tagged with the version of the tool.
+pub fn print_json_schema(output: &str) -> Result<()> {
+    let Some(mut schema) = schema(output) else {
+        bail!(
+            "no JSON schema for {output}, expected one of: {}",
+            OUTPUTS.join(", ")
+        );
+    };
+    schema.schema.extensions.insert(
+        "x-cargo-stylus-version".into(),
+        Value::String(env!("CARGO_PKG_VERSION").into()),
+    );
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{macros::*, project};
+use bytesize::ByteSize;
+use cargo_stylus_util::color::Color;
+use eyre::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Where a program's bytes go, as printed by `check --size-report`.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeReport {
+    /// Byte size of the WASM, including the project hash section.
+    pub wasm_size: usize,
+    /// Byte size of the compressed code stored onchain.
+    pub compressed_size: usize,
+    /// How many times smaller compression made the WASM.
+    pub compression_ratio: f64,
+    /// Most bytes of compressed code the chain accepts.
+    pub size_limit: u64,
+    /// The compressed size as a percentage of the limit.
+    pub percent_of_limit: f64,
+    /// Whether the compressed size is within `--size-margin-percent` of the limit.
+    pub near_limit: bool,
+    /// Sections of the WASM, largest first.
+    pub sections: Vec<SectionSize>,
+}
+
+/// One section of the WASM.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionSize {
+    /// The section's kind, such as `code` or `data`, or `custom` with its name.
+    pub name: String,
+    /// Byte size of the section's contents.
+    pub size: usize,
+    /// The section's share of the WASM, as a percentage.
+    pub percent: f64,
+}
+
+impl SizeReport {
+    /// Breaks down a WASM file and the code it compresses to, against the chain's size limit.
+    pub fn new(wasm: &[u8], code: &[u8], size_limit: u64, margin_percent: u64) -> Result<Self> {
+        let percent = |size: usize, total: u64| 100. * size as f64 / total.max(1) as f64;
+        let sections = project::wasm_section_sizes(wasm)?
+            .into_iter()
+            .map(|(name, size)| SectionSize {
+                name,
+                size,
+                percent: percent(size, wasm.len() as u64),
+            })
+            .collect();
+        let percent_of_limit = percent(code.len(), size_limit);
+        Ok(Self {
+            wasm_size: wasm.len(),
+            compressed_size: code.len(),
+            compression_ratio: wasm.len() as f64 / code.len().max(1) as f64,
+            size_limit,
+            percent_of_limit,
+            near_limit: percent_of_limit >= 100. - margin_percent as f64,
+            sections,
+        })
+    }
+
+    /// Prints the report, as JSON on stdout or as diagnostics.
+    pub fn print(&self, json: bool) -> Result<()> {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self)?);
+            return Ok(());
+        }
+        let size = |size: usize| ByteSize::b(size as u64);
+        greyln!("size report:");
+        greyln!("  wasm: {}", size(self.wasm_size).mint());
+        greyln!(
+            "  compressed: {} ({:.2}x smaller)",
+            size(self.compressed_size).mint(),
+            self.compression_ratio
+        );
+        let usage = format!(
+            "{:.1}% of the {} limit",
+            self.percent_of_limit,
+            ByteSize::b(self.size_limit)
+        );
+        match self.near_limit {
+            true => greyln!("  usage: {}", usage.pink()),
+            false => greyln!("  usage: {}", usage.mint()),
+        }
+        greyln!("  sections:");
+        for section in &self.sections {
+            greyln!(
+                "    {}: {} ({:.1}%)",
+                section.name,
+                size(section.size),
+                section.percent
+            );
+        }
+        if self.near_limit {
+            let position = match self.percent_of_limit > 100. {
+                true => "over",
+                false => "close to",
+            };
+            let advice =
+                format!("the compressed code is {position} the limit, shrink the largest sections");
+            greyln!("{}", advice.yellow());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_size_report() -> Result<()> {
+        // A module with an empty type section and a custom section named "hi".
+        let wasm = [
+            b"\0asm".as_slice(),
+            &[1, 0, 0, 0],
+            &[1, 1, 0],
+            &[0, 3, 2, b'h', b'i'],
+        ]
+        .concat();
+        let report = SizeReport::new(&wasm, &[0; 9], 10, 10)?;
+        assert_eq!(report.wasm_size, 16);
+        assert_eq!(report.compression_ratio, 16. / 9.);
+        assert_eq!(report.percent_of_limit, 90.);
+        assert!(report.near_limit);
+        assert!(!SizeReport::new(&wasm, &[0; 9], 20, 10)?.near_limit);
+
+        let sections: Vec<_> = report.sections.iter().map(|s| (&*s.name, s.size)).collect();
+        assert_eq!(sections, [("custom \"hi\"", 3), ("type", 1)]);
+        Ok(())
+    }
+}
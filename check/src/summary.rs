@@ -0,0 +1,128 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    macros::*,
+    verify::{BatchReport, DeploymentsReport, VerifyResult},
+};
+use cargo_stylus_util::color::Color;
+use ethers::types::{H160, H256};
+use eyre::{bail, Result, WrapErr};
+use fs2::FileExt;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The outcome of one run, as appended by `--summary-json-append`.
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Summary<'a> {
+    Deploy(&'a DeploySummary),
+    Verify(&'a VerifyResult),
+    VerifyBatch(&'a BatchReport),
+    VerifyDeployments(&'a DeploymentsReport),
+}
+
+/// Outcome of `deploy`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploySummary {
+    pub chain_id: u64,
+    #[schemars(with = "String")]
+    pub contract_address: H160,
+    /// The deployment tx, unless the program was already deployed at the address.
+    #[schemars(with = "Option<String>")]
+    pub deployment_tx: Option<H256>,
+    /// Hex-encoded keccak hash of the deployed code.
+    pub codehash: String,
+    /// Whether the program is activated, rather than left for `activate` to finish.
+    pub activated: bool,
+    /// Address of the minimal proxy in front of the program, if one was deployed.
+    #[schemars(with = "Option<String>")]
+    pub proxy: Option<H160>,
+}
+
+/// Appends a run's summary to the JSON array at `path`, creating the file if needed.
+///
+/// The file is locked while it's rewritten, so parallel runs appending to the same file
+/// each add their entry rather than overwriting one another's.
+pub fn append(path: &Path, summary: Summary<'_>) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .wrap_err_with(|| format!("failed to open summary file {path:?}"))?;
+    file.lock_exclusive()
+        .wrap_err_with(|| format!("failed to lock summary file {path:?}"))?;
+
+    let mut text = String::new();
+    file.read_to_string(&mut text)
+        .wrap_err_with(|| format!("failed to read summary file {path:?}"))?;
+    let mut entries = match text.trim().is_empty() {
+        true => vec![],
+        false => match serde_json::from_str(&text) {
+            Ok(Value::Array(entries)) => entries,
+            _ => bail!("summary file {path:?} should hold a JSON array"),
+        },
+    };
+    entries.push(serde_json::to_value(summary)?);
+
+    let json = serde_json::to_string_pretty(&entries)?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(json.as_bytes())
+        .wrap_err_with(|| format!("failed to write summary file {path:?}"))?;
+    greyln!("appended summary to {}", path.to_string_lossy().lavender());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs, thread};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parallel_appends() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("summary.json");
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let summary = DeploySummary {
+                        chain_id: i,
+                        contract_address: H160::zero(),
+                        deployment_tx: None,
+                        codehash: String::new(),
+                        activated: true,
+                        proxy: None,
+                    };
+                    append(&path, Summary::Deploy(&summary))
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap()?;
+        }
+        let entries: Vec<Value> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(entries.len(), 8);
+        assert_eq!(entries[0]["command"], "deploy");
+
+        fs::write(&path, "{}")?;
+        let report = BatchReport {
+            verified: 0,
+            failed: 0,
+            contracts: vec![],
+        };
+        assert!(append(&path, Summary::VerifyBatch(&report)).is_err());
+        Ok(())
+    }
+}
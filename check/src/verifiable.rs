@@ -0,0 +1,174 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{constants::TOOLCHAIN_FILE_NAME, project};
+use eyre::Result;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Environment variables cargo sets for build scripts that don't depend on the machine.
+const DETERMINISTIC_BUILD_ENV_VARS: &[&str] = &[
+    "OUT_DIR",
+    "TARGET",
+    "HOST",
+    "PROFILE",
+    "OPT_LEVEL",
+    "DEBUG",
+    "NUM_JOBS",
+];
+
+/// Snippets of build script code that read inputs outside the project's sources.
+const NONDETERMINISTIC_BUILD_INPUTS: &[(&str, &str)] = &[
+    ("SystemTime", "the clock"),
+    ("Instant::now", "the clock"),
+    ("chrono::", "the clock"),
+    ("Command::new", "external commands"),
+    ("reqwest", "the network"),
+    ("TcpStream", "the network"),
+];
+
+/// Runs the heuristics for whether a later `cargo stylus verify` could reproduce the build of
+/// the project rooted at `root`, returning what each failed check found.
+pub fn verifiability_problems(
+    root: &Path,
+    source_file_patterns: Vec<String>,
+    use_gitignore: bool,
+) -> Result<Vec<String>> {
+    let sources = project::all_paths(root, source_file_patterns, use_gitignore)?;
+    let mut problems = vec![];
+    problems.extend(uncommitted_sources(root, &sources));
+    problems.extend(missing_lockfile(root));
+    problems.extend(floating_toolchain(root));
+    problems.extend(nondeterministic_build_script(root));
+    Ok(problems)
+}
+
+/// Flags hashed sources with changes git hasn't committed, since they can't be checked out
+/// again to verify the deployment.
+fn uncommitted_sources(root: &Path, sources: &[PathBuf]) -> Option<String> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // Paths are relative to `root` and git isn't needed outside a repository.
+    let changed = git(&["ls-files", "--modified", "--others", "--exclude-standard"])?;
+    let staged = git(&["diff", "--cached", "--name-only", "--relative"])?;
+
+    let mut hashed: HashSet<PathBuf> = sources
+        .iter()
+        .map(|path| path.strip_prefix(root).unwrap_or(path).to_path_buf())
+        .collect();
+    hashed.insert(PathBuf::from(TOOLCHAIN_FILE_NAME));
+    let mut dirty: Vec<&str> = changed
+        .lines()
+        .chain(staged.lines())
+        .filter(|path| hashed.contains(Path::new(path)))
+        .collect();
+    dirty.sort();
+    dirty.dedup();
+    match dirty.is_empty() {
+        true => None,
+        false => Some(format!(
+            "dirty tree: uncommitted changes to {}",
+            dirty.join(", ")
+        )),
+    }
+}
+
+/// Flags a project without a `Cargo.lock` here or in a parent workspace, since its
+/// dependencies would float to whatever is newest.
+fn missing_lockfile(root: &Path) -> Option<String> {
+    let root = root.canonicalize().unwrap_or(root.to_path_buf());
+    match root.ancestors().any(|dir| dir.join("Cargo.lock").is_file()) {
+        true => None,
+        false => {
+            Some("floating lockfile: there's no Cargo.lock, so dependencies may change".into())
+        }
+    }
+}
+
+/// Flags a toolchain channel like `stable` that names no particular release.
+fn floating_toolchain(root: &Path) -> Option<String> {
+    let channel = project::extract_toolchain_channel(&root.join(TOOLCHAIN_FILE_NAME)).ok()?;
+    match channel.chars().any(|c| c.is_ascii_digit()) {
+        true => None,
+        false => Some(format!(
+            "floating toolchain: {TOOLCHAIN_FILE_NAME} uses channel {channel}, \
+             which changes with each release"
+        )),
+    }
+}
+
+/// Flags a build script that reads the clock, the environment, external commands or the
+/// network, any of which may differ when verifying.
+fn nondeterministic_build_script(root: &Path) -> Option<String> {
+    let code = fs::read_to_string(root.join("build.rs")).ok()?;
+    let mut inputs: Vec<String> = NONDETERMINISTIC_BUILD_INPUTS
+        .iter()
+        .filter(|(snippet, _)| code.contains(snippet))
+        .map(|(_, input)| input.to_string())
+        .collect();
+    for (start, _) in code.match_indices("env::var(\"") {
+        let name = &code[start + "env::var(\"".len()..];
+        let name = name.split('"').next().unwrap_or_default();
+        if !name.starts_with("CARGO") && !DETERMINISTIC_BUILD_ENV_VARS.contains(&name) {
+            inputs.push(format!("the {name} environment variable"));
+        }
+    }
+    inputs.dedup();
+    if inputs.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "non-deterministic build.rs: it reads {}",
+        inputs.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verifiability_problems() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"counter\"\n")?;
+        fs::write(
+            root.join(TOOLCHAIN_FILE_NAME),
+            "[toolchain]\nchannel = \"stable\"\n",
+        )?;
+        fs::write(
+            root.join("build.rs"),
+            "fn main() {\n    let _ = std::env::var(\"OUT_DIR\");\n    \
+             let _ = std::env::var(\"GIT_SHA\");\n    let _ = std::time::SystemTime::now();\n}\n",
+        )?;
+
+        let problems = verifiability_problems(root, vec![], true)?;
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].starts_with("floating lockfile"));
+        assert!(problems[1].contains("channel stable"));
+        assert!(problems[2].ends_with("it reads the clock, the GIT_SHA environment variable"));
+
+        fs::write(root.join("Cargo.lock"), "version = 3\n")?;
+        fs::write(
+            root.join(TOOLCHAIN_FILE_NAME),
+            "[toolchain]\nchannel = \"1.80\"\n",
+        )?;
+        fs::write(root.join("build.rs"), "fn main() {}\n")?;
+        assert!(verifiability_problems(root, vec![], true)?.is_empty());
+        Ok(())
+    }
+}
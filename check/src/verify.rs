@@ -3,41 +3,580 @@
 
 #![allow(clippy::println_empty_string)]
 
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use eyre::{bail, eyre};
+use eyre::{bail, eyre, WrapErr};
 
 use ethers::middleware::Middleware;
-use ethers::types::H256;
+use ethers::types::{H160, H256};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use alloy_primitives::keccak256;
+
 use crate::{
-    check,
+    check, constants,
     deploy::{self, extract_compressed_wasm, extract_program_evm_deployment_prelude},
-    project, CheckConfig, VerifyConfig,
+    explorer, find_activation,
+    macros::*,
+    output, project,
+    summary::{self, Summary},
+    CheckConfig, VerifyConfig,
+};
+use cargo_stylus_util::{
+    color::{Color, DebugColor},
+    sys, text,
 };
-use cargo_stylus_util::{color::Color, sys};
 
 #[derive(Debug, Deserialize, Serialize)]
 struct RpcResult {
     input: String,
 }
 
+/// Contracts to verify with `--batch`, mapping each contract's directory (or its
+/// `Cargo.toml`), relative to the manifest, to one or more candidate deployment txs.
+type BatchManifest = BTreeMap<String, DeploymentTxs>;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DeploymentTxs {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Outcome of `verify --batch`, as printed with `--json`.
+#[derive(Serialize, JsonSchema)]
+pub struct BatchReport {
+    /// Number of contracts that matched one of their deployment txs.
+    pub verified: usize,
+    /// Number of contracts that didn't match or couldn't be checked.
+    pub failed: usize,
+    pub contracts: Vec<BatchVerdict>,
+}
+
+/// Outcome of `verify --each` or `--deployments-file`, as printed with `--json`.
+#[derive(Serialize, JsonSchema)]
+pub struct DeploymentsReport {
+    /// Number of deployment txs that matched the local project.
+    pub verified: usize,
+    /// Number of deployment txs that didn't match or weren't found.
+    pub failed: usize,
+    pub deployments: Vec<VerifyResult>,
+}
+
+/// Outcome of verifying one contract of a batch.
+#[derive(Serialize, JsonSchema)]
+pub struct BatchVerdict {
+    /// The contract's entry in the manifest.
+    pub contract: String,
+    pub verified: bool,
+    /// The deployment tx that matched the local project.
+    pub deployment_tx: Option<String>,
+    /// Why the contract couldn't be checked, if it couldn't.
+    pub error: Option<String>,
+}
+
+/// Outcome of comparing a deployment tx against the local project, as printed by `verify --json`.
+#[derive(Serialize, JsonSchema)]
+pub struct VerifyResult {
+    pub verified: bool,
+    /// What differed, if the deployment didn't verify.
+    pub reason: Option<String>,
+    /// The deployment tx that matched, or the last candidate if none did.
+    pub deployment_tx: String,
+    /// Hex-encoded deployment prelude of the locally reconstructed init code.
+    pub local_prelude: String,
+    /// Hex-encoded deployment prelude of the deployment tx.
+    pub onchain_prelude: String,
+    /// Byte length of the locally reconstructed compressed program.
+    pub local_code_len: usize,
+    /// Byte length of the deployment tx's compressed program.
+    pub onchain_code_len: usize,
+    /// Whether the deployment matches except for the version byte after its prelude, which
+    /// only verifies with `--lenient-prelude-version`.
+    pub prelude_version_mismatch: bool,
+    /// The program's activation, with `--check-activation`, if one was found since the
+    /// deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation: Option<ActivationReport>,
+}
+
+/// An activation of a verified deployment's codehash, as reported by `verify --check-activation`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ActivationReport {
+    /// Hash of the activation tx.
+    pub activation_tx: String,
+    /// Block the activation tx was mined in.
+    pub block: u64,
+    /// Account that sent the activation tx.
+    #[schemars(with = "String")]
+    pub sender: H160,
+    /// Program the activation tx activated, which may be an earlier deployment of the same code.
+    #[schemars(with = "String")]
+    pub program: H160,
+    /// Wei sent with the activation tx, of which any beyond the data fee was refunded.
+    pub value_sent: String,
+    /// Data fee ArbWasm charged, in wei.
+    pub data_fee: String,
+    /// Stylus version the program was activated for.
+    pub stylus_version: u16,
+    /// Hex-encoded hash of the module ArbWasm compiled the program to.
+    pub module_hash: String,
+}
+
 pub async fn verify(cfg: VerifyConfig) -> eyre::Result<()> {
-    let provider = sys::new_provider(&cfg.common_cfg.endpoint)?;
-    let hash = cargo_stylus_util::text::decode0x(cfg.deployment_tx)?;
-    if hash.len() != 32 {
-        bail!("Invalid hash");
+    if let Some(manifest) = &cfg.batch {
+        return verify_batch(&cfg, manifest).await;
+    }
+    if let Some(path) = &cfg.emit_local_init_code {
+        return emit_local_init_code(&cfg, path).await;
+    }
+    if cfg.each || cfg.deployments_file.is_some() {
+        return verify_each(&cfg).await;
+    }
+    if cfg.json {
+        output::diagnostics_to_stderr();
     }
-    let Some(result) = provider
-        .get_transaction(H256::from_slice(&hash))
+    let result = verify_deployment(&cfg).await?;
+    if let Some(path) = &cfg.summary_json_append {
+        summary::append(path, Summary::Verify(&result))?;
+    }
+    if cfg.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        if !result.verified {
+            bail!("deployment did not verify against the local project");
+        }
+    }
+    Ok(())
+}
+
+/// Verifies the project in the current directory against its candidate deployment txs,
+/// stopping at the first that matches.
+async fn verify_deployment(cfg: &VerifyConfig) -> eyre::Result<VerifyResult> {
+    let provider = cfg.common_cfg.provider()?;
+    let hashes = match cfg.program_address {
+        Some(address) => vec![find_deployment_tx(cfg, &provider, address).await?],
+        None => cfg
+            .deployment_tx
+            .iter()
+            .map(|tx| parse_tx_hash(tx))
+            .collect::<eyre::Result<Vec<_>>>()?,
+    };
+
+    let mut txs = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        let Some(tx) = provider
+            .get_transaction(hash)
+            .await
+            .map_err(|e| eyre!("RPC failed: {e}"))?
+        else {
+            bail!("No transaction found for hash {hash:?}");
+        };
+        txs.push(tx);
+    }
+
+    let (init_code, deployment_data) = local_deployment(cfg).await?;
+    let multiple = txs.len() > 1;
+
+    let mut result = None;
+    for tx in &txs {
+        let input = creation_input(&tx.input, tx.to, &deployment_data);
+        let comparison = compare_deployment(
+            tx.hash,
+            input,
+            &deployment_data,
+            &init_code,
+            cfg.lenient_prelude_version,
+        );
+        if comparison.verified {
+            warn_prelude_version(&comparison, input, &deployment_data)?;
+            if multiple {
+                infoln!(
+                    "Verified - deployment tx {:?} matches local project's file hashes",
+                    tx.hash
+                );
+            } else {
+                infoln!("Verified - program matches local project's file hashes");
+            }
+            if let Some(api_url) = &cfg.explorer_api_url {
+                let Some(receipt) = provider
+                    .get_transaction_receipt(tx.hash)
+                    .await
+                    .map_err(|e| eyre!("RPC failed: {e}"))?
+                else {
+                    bail!("No receipt found for deployment tx");
+                };
+                let program = receipt
+                    .contract_address
+                    .ok_or(eyre!("deployment tx did not create a contract"))?;
+                explorer::check_explorer_verification(
+                    api_url,
+                    cfg.explorer_api_key.as_deref(),
+                    program,
+                    cfg.common_cfg.verbose,
+                )
+                .await?;
+            }
+            let mut comparison = comparison;
+            if cfg.check_activation {
+                comparison.activation = check_activation(&provider, tx.hash).await?;
+            }
+            return Ok(comparison);
+        }
+
+        if multiple {
+            infoln!("Deployment tx {:?}", tx.hash);
+        }
+        report_mismatch(&comparison);
+        hint_contract_version(cfg, input);
+        result = Some(comparison);
+    }
+    if multiple {
+        infoln!(
+            "{} - none of the {} deployment txs matched the local project",
+            "FAILED".red(),
+            txs.len()
+        );
+    }
+    result.ok_or_else(|| eyre!("no deployment txs to verify"))
+}
+
+/// Verifies each of the given deployment txs against one build of the local project, printing
+/// a verdict per tx and failing if any of them didn't verify.
+async fn verify_each(cfg: &VerifyConfig) -> eyre::Result<()> {
+    if cfg.json {
+        output::diagnostics_to_stderr();
+    }
+    let mut hashes = cfg
+        .deployment_tx
+        .iter()
+        .map(|tx| parse_tx_hash(tx))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    if let Some(path) = &cfg.deployments_file {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read deployments file {path:?}"))?;
+        for line in text.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                hashes.push(parse_tx_hash(line)?);
+            }
+        }
+    }
+    if hashes.is_empty() {
+        bail!("no deployment txs to verify");
+    }
+
+    let provider = cfg.common_cfg.provider()?;
+    let (init_code, deployment_data) = local_deployment(cfg).await?;
+    let mut deployments = vec![];
+    for hash in hashes {
+        let tx = provider
+            .get_transaction(hash)
+            .await
+            .map_err(|e| eyre!("RPC failed: {e}"))?;
+        let result = match &tx {
+            Some(tx) => {
+                let input = creation_input(&tx.input, tx.to, &deployment_data);
+                let result = compare_deployment(
+                    hash,
+                    input,
+                    &deployment_data,
+                    &init_code,
+                    cfg.lenient_prelude_version,
+                );
+                warn_prelude_version(&result, input, &deployment_data)?;
+                result
+            }
+            None => {
+                let mut result = compare_deployment(hash, &[], &deployment_data, &init_code, true);
+                result.reason = Some("tx not found".into());
+                result
+            }
+        };
+        match &result.reason {
+            None => infoln!("{} {hash:?}", "PASS".mint()),
+            Some(reason) => infoln!("{} {hash:?}: {reason}", "FAIL".red()),
+        }
+        deployments.push(result);
+    }
+
+    let verified = deployments.iter().filter(|d| d.verified).count();
+    let report = DeploymentsReport {
+        verified,
+        failed: deployments.len() - verified,
+        deployments,
+    };
+    if let Some(path) = &cfg.summary_json_append {
+        summary::append(path, Summary::VerifyDeployments(&report))?;
+    }
+    if cfg.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        infoln!(
+            "{} of {} deployments verified",
+            report.verified,
+            report.deployments.len()
+        );
+    }
+    if report.failed > 0 {
+        bail!(
+            "{} of {} deployments failed verification",
+            report.failed,
+            report.deployments.len()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the compressed program the local project deploys along with its deployment
+/// calldata, from `--local-init-code` or else by rebuilding the project.
+async fn local_deployment(cfg: &VerifyConfig) -> eyre::Result<(Vec<u8>, Vec<u8>)> {
+    if let Some(value) = &cfg.local_init_code {
+        return decode_local_init_code(value);
+    }
+    let init_code = build_init_code(cfg, true).await?;
+    let deployment_data = deploy::program_deployment_calldata(&init_code);
+    Ok((init_code, deployment_data))
+}
+
+/// Returns the init code a deployment tx ran. Txs that call a factory carry it inside their
+/// calldata, where it's found by the local deployment's prelude, less its version byte.
+fn creation_input<'a>(input: &'a [u8], to: Option<H160>, deployment_data: &[u8]) -> &'a [u8] {
+    let prelude = &deployment_data[..VERSION_OFFSET.min(deployment_data.len())];
+    if to.is_none() || input.starts_with(prelude) {
+        return input;
+    }
+    match input.windows(prelude.len()).position(|w| w == prelude) {
+        Some(start) => {
+            let end = (start + deployment_data.len()).min(input.len());
+            &input[start..end]
+        }
+        None => input,
+    }
+}
+
+/// Warns when a deployment verified despite a different version byte after its prelude.
+fn warn_prelude_version(result: &VerifyResult, onchain: &[u8], local: &[u8]) -> eyre::Result<()> {
+    if result.verified && result.prelude_version_mismatch {
+        warnln!(
+            PreludeVersionMismatch,
+            "deployment tx {} has prelude version byte {}, but the local project's is {}",
+            result.deployment_tx,
+            onchain[VERSION_OFFSET],
+            local[VERSION_OFFSET]
+        )?;
+    }
+    Ok(())
+}
+
+/// Finds the tx that deployed the program at `address`, by bisecting the block range for the
+/// first block with code at the address and then looking through that block's txs.
+///
+/// Reading old code needs an archive node. Only txs that deploy the program directly are found,
+/// not those that go through a factory contract.
+async fn find_deployment_tx<M: Middleware>(
+    cfg: &VerifyConfig,
+    provider: &M,
+    address: H160,
+) -> eyre::Result<H256> {
+    let code_at = |block: u64| async move {
+        provider
+            .get_code(address, Some(block.into()))
+            .await
+            .map(|code| !code.is_empty())
+            .map_err(|e| eyre!("failed to fetch code at block {block}: {e}"))
+    };
+    let to_block = match cfg.to_block {
+        Some(block) => block,
+        None => provider
+            .get_block_number()
+            .await
+            .map_err(|e| eyre!("failed to get block number: {e}"))?
+            .as_u64(),
+    };
+    let from_block = cfg.from_block.unwrap_or_default();
+    if from_block > to_block {
+        bail!("--from-block {from_block} is after --to-block {to_block}");
+    }
+    if !code_at(to_block).await? {
+        bail!(
+            "no program at address {} as of block {to_block}, check the address and network",
+            address.debug_red()
+        );
+    }
+    if code_at(from_block).await? {
+        bail!(
+            "program {} already existed at block {from_block}, so its deployment tx isn't in range",
+            address.debug_red()
+        );
+    }
+    greyln!(
+        "searching blocks {from_block} to {to_block} for the deployment of {}",
+        address.debug_mint()
+    );
+
+    // Code is missing at `low` and present at `high`.
+    let (mut low, mut high) = (from_block, to_block);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        match code_at(mid).await? {
+            true => high = mid,
+            false => low = mid,
+        }
+    }
+
+    let Some(block) = provider
+        .get_block(high)
         .await
-        .map_err(|e| eyre!("RPC failed: {e}"))?
+        .map_err(|e| eyre!("failed to get block {high}: {e}"))?
     else {
-        bail!("No code at address");
+        bail!("no block {high} found");
     };
+    for hash in block.transactions {
+        let receipt = provider
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| eyre!("RPC failed: {e}"))?;
+        if receipt.and_then(|r| r.contract_address) == Some(address) {
+            greyln!("found deployment tx {} in block {high}", hash.debug_mint());
+            return Ok(hash);
+        }
+    }
+    bail!(
+        "program {} was created in block {high}, but not by a deployment tx; \
+         it may have come from a factory contract, so pass the tx with --deployment-tx",
+        address.debug_red()
+    )
+}
+
+/// Verifies every contract listed in a batch manifest, then summarizes the verdicts.
+async fn verify_batch(cfg: &VerifyConfig, manifest: &Path) -> eyre::Result<()> {
+    if cfg.json {
+        output::diagnostics_to_stderr();
+    }
+    let text = fs::read_to_string(manifest)
+        .wrap_err_with(|| format!("failed to read batch manifest {manifest:?}"))?;
+    let entries: BatchManifest = serde_json::from_str(&text)
+        .wrap_err_with(|| format!("invalid batch manifest {manifest:?}"))?;
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    let cwd = env::current_dir().wrap_err("could not get current dir")?;
+
+    let mut contracts = vec![];
+    for (contract, txs) in entries {
+        let mut dir = base.join(&contract);
+        if dir.ends_with("Cargo.toml") {
+            dir.pop();
+        }
+        let mut entry_cfg = cfg.clone();
+        entry_cfg.deployment_tx = match txs {
+            DeploymentTxs::One(tx) => vec![tx],
+            DeploymentTxs::Many(txs) => txs,
+        };
 
+        greyln!("verifying {}", contract.lavender());
+        let result = match env::set_current_dir(cwd.join(&dir)) {
+            Ok(()) => verify_deployment(&entry_cfg).await,
+            Err(e) => Err(eyre!("could not enter {}: {e}", dir.to_string_lossy())),
+        };
+        env::set_current_dir(&cwd).wrap_err("could not return to the starting dir")?;
+
+        contracts.push(match result {
+            Ok(result) => BatchVerdict {
+                contract,
+                verified: result.verified,
+                deployment_tx: result.verified.then_some(result.deployment_tx),
+                error: None,
+            },
+            Err(error) => BatchVerdict {
+                contract,
+                verified: false,
+                deployment_tx: None,
+                error: Some(format!("{error:#}")),
+            },
+        });
+    }
+
+    let verified = contracts.iter().filter(|c| c.verified).count();
+    let report = BatchReport {
+        verified,
+        failed: contracts.len() - verified,
+        contracts,
+    };
+    if let Some(path) = &cfg.summary_json_append {
+        summary::append(path, Summary::VerifyBatch(&report))?;
+    }
+    if cfg.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_batch_summary(&report);
+    }
+    if report.failed > 0 {
+        bail!(
+            "{} of {} contracts failed verification",
+            report.failed,
+            report.contracts.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_batch_summary(report: &BatchReport) {
+    let width = report
+        .contracts
+        .iter()
+        .map(|c| c.contract.len())
+        .max()
+        .unwrap_or_default()
+        .max("contract".len());
+    println!("");
+    println!("{:width$}  verdict", "contract");
+    for verdict in &report.contracts {
+        let detail = match (&verdict.deployment_tx, &verdict.error) {
+            (Some(tx), _) => format!("{} {tx}", "verified".mint()),
+            (None, Some(error)) => format!("{} {error}", "error".red()),
+            (None, None) => "FAILED".red(),
+        };
+        println!("{:width$}  {detail}", verdict.contract);
+    }
+    println!(
+        "{} verified, {} failed",
+        report.verified.mint(),
+        match report.failed {
+            0 => report.failed.mint(),
+            _ => report.failed.red(),
+        }
+    );
+}
+
+fn parse_tx_hash(tx: &str) -> eyre::Result<H256> {
+    let hash = cargo_stylus_util::text::decode0x(tx)?;
+    if hash.len() != 32 {
+        bail!("Invalid hash {tx}");
+    }
+    Ok(H256::from_slice(&hash))
+}
+
+/// Builds the project and writes the init code a deployment of it would have, as hex, without
+/// touching the network.
+async fn emit_local_init_code(cfg: &VerifyConfig, path: &Path) -> eyre::Result<()> {
+    let init_code = build_init_code(cfg, false).await?;
+    let deployment_data = deploy::program_deployment_calldata(&init_code);
+    fs::write(path, hex::encode(deployment_data))
+        .wrap_err_with(|| format!("failed to write {path:?}"))?;
+    greyln!(
+        "wrote local init code to {}",
+        path.to_string_lossy().lavender()
+    );
+    Ok(())
+}
+
+/// Rebuilds the project from clean, after running the Stylus checks against the chain if
+/// `run_checks` is set, and returns its compressed program.
+async fn build_init_code(cfg: &VerifyConfig, run_checks: bool) -> eyre::Result<Vec<u8>> {
     let output = sys::new_command("cargo")
         .arg("clean")
         .output()
@@ -49,47 +588,261 @@ pub async fn verify(cfg: VerifyConfig) -> eyre::Result<()> {
         common_cfg: cfg.common_cfg.clone(),
         wasm_file: None,
         program_address: None,
+        fail_on_warnings: false,
+        dump_wasm_sections: false,
+        size_report: false,
+        size_report_json: false,
+        size_margin_percent: 10,
+        allow_wasm_feature: vec![],
+        max_init_code_size: constants::MAX_INIT_CODE_SIZE,
+        compare_with: None,
+        expect_hash: None,
+        entrypoint_symbol: constants::ENTRYPOINT_SYMBOL.into(),
     };
-    let _ = check::check(&check_cfg)
-        .await
-        .map_err(|e| eyre!("Stylus checks failed: {e}"))?;
+    if run_checks {
+        let _ = check::check(&check_cfg)
+            .await
+            .map_err(|e| eyre!("Stylus checks failed: {e}"))?;
+    }
     let build_cfg = project::BuildConfig {
         opt_level: project::OptLevel::default(),
         stable: cfg.common_cfg.rust_stable,
+        package: cfg.common_cfg.package.clone(),
+        allow_non_stylus: cfg.common_cfg.allow_non_stylus,
+        normalize_manifest_paths: cfg.common_cfg.normalize_manifest_paths,
+        no_ignore: cfg.common_cfg.no_ignore,
+        rustc_wrapper: cfg.common_cfg.rustc_wrapper.clone(),
+        cargo_args: cfg.common_cfg.cargo_arg.clone(),
+        invocation_file: cfg.common_cfg.build_invocation.clone(),
     };
     let wasm_file: PathBuf = project::build_dylib(build_cfg.clone())
         .map_err(|e| eyre!("could not build project to WASM: {e}"))?;
-    let project_hash =
-        project::hash_files(cfg.common_cfg.source_files_for_project_hash, build_cfg)?;
-    let (_, init_code) = project::compress_wasm(&wasm_file, project_hash)?;
-    let deployment_data = deploy::program_deployment_calldata(&init_code);
-    if deployment_data == *result.input {
-        println!("Verified - program matches local project's file hashes");
+    let project_hash = project::hash_files(
+        cfg.common_cfg.source_files_for_project_hash.clone(),
+        build_cfg,
+    )?;
+    let version = cfg.common_cfg.contract_version.as_ref();
+    let (_, init_code) = project::compress_wasm(&wasm_file, project_hash, version)?;
+    Ok(init_code)
+}
+
+/// Decodes the init code passed to `--local-init-code`, returning the compressed program it
+/// deploys along with the full deployment calldata.
+fn decode_local_init_code(value: &str) -> eyre::Result<(Vec<u8>, Vec<u8>)> {
+    let hex = match Path::new(value).is_file() {
+        true => fs::read_to_string(value).wrap_err_with(|| format!("failed to read {value}"))?,
+        false => value.to_string(),
+    };
+    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = text::decode0x(hex).wrap_err("--local-init-code is not valid hex")?;
+    if bytes.starts_with(&hex::decode(constants::EOF_PREFIX_NO_DICT).unwrap()) {
+        let deployment_data = deploy::program_deployment_calldata(&bytes);
+        return Ok((bytes, deployment_data));
+    }
+    deploy::parse_deployment_prelude(&bytes)
+        .wrap_err("--local-init-code is neither Stylus init code nor a compressed program")?;
+    Ok((extract_compressed_wasm(&bytes), bytes))
+}
+
+/// Where the Stylus version byte sits in deployment calldata, right after the prelude.
+const VERSION_OFFSET: usize = 42;
+
+/// Compares a deployment tx's input against the locally reconstructed deployment. With
+/// `lenient`, inputs that differ only in their version byte still verify.
+fn compare_deployment(
+    tx_hash: H256,
+    tx_input: &[u8],
+    deployment_data: &[u8],
+    init_code: &[u8],
+    lenient: bool,
+) -> VerifyResult {
+    let onchain_prelude = extract_program_evm_deployment_prelude(tx_input);
+    let local_prelude = extract_program_evm_deployment_prelude(deployment_data);
+    let exact = tx_input == deployment_data;
+    let prelude_version_mismatch = !exact
+        && tx_input.len() == deployment_data.len()
+        && tx_input.len() > VERSION_OFFSET
+        && tx_input
+            .iter()
+            .zip(deployment_data)
+            .enumerate()
+            .all(|(i, (a, b))| a == b || i == VERSION_OFFSET);
+    let verified = exact || (prelude_version_mismatch && lenient);
+    let reason = match (verified, onchain_prelude == local_prelude) {
+        (true, _) => None,
+        (false, _) if prelude_version_mismatch => Some("prelude version mismatch".into()),
+        (false, false) => Some("prelude mismatch".into()),
+        (false, true) => Some("compressed WASM bytecode mismatch".into()),
+    };
+    VerifyResult {
+        verified,
+        reason,
+        deployment_tx: format!("{tx_hash:?}"),
+        local_prelude: hex::encode(local_prelude),
+        onchain_prelude: hex::encode(onchain_prelude),
+        local_code_len: init_code.len(),
+        onchain_code_len: extract_compressed_wasm(tx_input).len(),
+        prelude_version_mismatch,
+        activation: None,
+    }
+}
+
+/// Finds the first activation of a deployment's codehash since the deployment and reports it.
+///
+/// Costs a receipt and code lookup, then one `eth_getLogs` request per 10,000 blocks from the
+/// deployment to the latest block, plus fetching the activation tx itself.
+async fn check_activation<M: Middleware>(
+    provider: &M,
+    deployment_tx: H256,
+) -> eyre::Result<Option<ActivationReport>> {
+    let rpc = |e: M::Error| eyre!("RPC failed: {e}");
+    let Some(receipt) = provider
+        .get_transaction_receipt(deployment_tx)
+        .await
+        .map_err(rpc)?
+    else {
+        bail!("No receipt found for deployment tx");
+    };
+    let program = receipt
+        .contract_address
+        .ok_or(eyre!("deployment tx did not create a contract"))?;
+    let deployed_in = receipt.block_number.unwrap_or_default().as_u64();
+    let code = provider.get_code(program, None).await.map_err(rpc)?;
+    let codehash = keccak256(&code);
+    let head = provider.get_block_number().await.map_err(rpc)?.as_u64();
+    infoln!("Searching blocks {deployed_in} to {head} for the program's activation");
+
+    let activations =
+        find_activation::scan_activations(provider, codehash.0, deployed_in, head).await?;
+    let Some(activation) = activations.into_iter().next() else {
+        warnln!(
+            ActivationNotFound,
+            "no activation of codehash {codehash} found since the deployment, it may have \
+             been activated earlier by another deployment of the same code"
+        )?;
+        return Ok(None);
+    };
+    let Some(tx) = provider
+        .get_transaction(activation.tx_hash)
+        .await
+        .map_err(rpc)?
+    else {
+        bail!("No transaction found for hash {:?}", activation.tx_hash);
+    };
+    let program_address = H160::from(activation.program.0 .0);
+    let report = ActivationReport {
+        activation_tx: format!("{:?}", activation.tx_hash),
+        block: activation.block,
+        sender: tx.from,
+        program: program_address,
+        value_sent: tx.value.to_string(),
+        data_fee: activation.data_fee.to_string(),
+        stylus_version: activation.version,
+        module_hash: hex::encode(activation.module_hash),
+    };
+    infoln!(
+        "Activated by tx {} in block {}",
+        report.activation_tx.mint(),
+        report.block
+    );
+    if program_address != program {
+        infoln!("  via program {}", program_address.debug_yellow());
+    }
+    infoln!("  sender: {:?}", report.sender);
+    infoln!(
+        "  data fee: {} wei (sent {} wei)",
+        report.data_fee,
+        report.value_sent
+    );
+    infoln!("  stylus version: {}", report.stylus_version);
+    infoln!("  module hash: {}", report.module_hash);
+    Ok(Some(report))
+}
+
+/// Points out when a deployment that didn't verify embeds a different contract version than the
+/// local build, since `--contract-version` must be passed to verify as it was to deploy.
+fn hint_contract_version(cfg: &VerifyConfig, tx_input: &[u8]) {
+    let wasm = project::decompress_code(&extract_compressed_wasm(tx_input));
+    let Ok(Ok(deployed)) = wasm.map(|wasm| project::contract_version(&wasm)) else {
+        return;
+    };
+    let local = cfg
+        .common_cfg
+        .contract_version
+        .as_ref()
+        .map(|v| v.to_string());
+    if deployed == local {
+        return;
+    }
+    match deployed {
+        Some(version) => infoln!(
+            "The deployment embeds contract version {version}, rebuild with --contract-version {version}"
+        ),
+        None => infoln!("The deployment embeds no contract version, drop --contract-version"),
+    }
+}
+
+fn report_mismatch(result: &VerifyResult) {
+    infoln!(
+        "{} - program deployment did not verify against local project's file hashes",
+        "FAILED".red()
+    );
+    if result.prelude_version_mismatch {
+        infoln!("Prelude version mismatch, which only --lenient-prelude-version accepts");
+        infoln!("Deployment tx prelude {}", result.onchain_prelude);
+        infoln!("Reconstructed prelude {}", result.local_prelude);
+    } else if result.local_prelude != result.onchain_prelude {
+        infoln!("Prelude mismatch");
+        infoln!("Deployment tx prelude {}", result.onchain_prelude);
+        infoln!("Reconstructed prelude {}", result.local_prelude);
     } else {
-        let tx_prelude = extract_program_evm_deployment_prelude(&*result.input);
-        let reconstructed_prelude = extract_program_evm_deployment_prelude(&deployment_data);
-        println!(
-            "{} - program deployment did not verify against local project's file hashes",
-            "FAILED".red()
-        );
-        if tx_prelude != reconstructed_prelude {
-            println!("Prelude mismatch");
-            println!("Deployment tx prelude {}", hex::encode(tx_prelude));
-            println!(
-                "Reconstructed prelude {}",
-                hex::encode(reconstructed_prelude)
-            );
-        } else {
-            println!("Compressed WASM bytecode mismatch");
-        }
-        println!(
-            "Compressed code length of locally reconstructed {}",
-            init_code.len()
-        );
-        println!(
-            "Compressed code length of deployment tx {}",
-            extract_compressed_wasm(&*result.input).len()
-        );
+        infoln!("Compressed WASM bytecode mismatch");
+    }
+    infoln!(
+        "Compressed code length of locally reconstructed {}",
+        result.local_code_len
+    );
+    infoln!(
+        "Compressed code length of deployment tx {}",
+        result.onchain_code_len
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_deployment() {
+        let code = hex::decode(constants::EOF_PREFIX_NO_DICT).unwrap();
+        let local = deploy::program_deployment_calldata(&code);
+        let mut onchain = local.clone();
+        onchain[VERSION_OFFSET] = 1;
+
+        let compare = |onchain: &[u8], lenient| {
+            compare_deployment(H256::zero(), onchain, &local, &code, lenient)
+        };
+        assert!(compare(&local, false).verified);
+        let exact = compare(&onchain, false);
+        assert!(!exact.verified);
+        assert_eq!(exact.reason.as_deref(), Some("prelude version mismatch"));
+        let lenient = compare(&onchain, true);
+        assert!(lenient.verified && lenient.prelude_version_mismatch);
+
+        onchain[0] ^= 1;
+        let corrupted = compare(&onchain, true);
+        assert!(!corrupted.verified && !corrupted.prelude_version_mismatch);
+        assert_eq!(corrupted.reason.as_deref(), Some("prelude mismatch"));
+    }
+
+    #[test]
+    fn test_creation_input() {
+        let code = hex::decode(constants::EOF_PREFIX_NO_DICT).unwrap();
+        let local = deploy::program_deployment_calldata(&code);
+        let factory_call = [&[0xde, 0xad][..], &local, &[0; 5]].concat();
+        let factory = Some(H160::repeat_byte(1));
+        assert_eq!(creation_input(&factory_call, factory, &local), local);
+        assert_eq!(creation_input(&factory_call, None, &local), factory_call);
+        assert_eq!(creation_input(&[1, 2], factory, &local), [1, 2]);
     }
-    Ok(())
 }
@@ -1,41 +1,366 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
+#[cfg(feature = "ledger")]
+use crate::ledger::{self, LedgerSigner};
 use crate::AuthOpts;
+use async_trait::async_trait;
 use cargo_stylus_util::text;
-use ethers::signers::LocalWallet;
-use eyre::{eyre, Context, Result};
-use std::fs;
-
-/// Loads a wallet for signing transactions.
-impl AuthOpts {
-    pub fn wallet(&self) -> Result<LocalWallet> {
-        macro_rules! wallet {
-            ($key:expr) => {{
-                let key = text::decode0x($key).wrap_err("invalid private key")?;
-                LocalWallet::from_bytes(&key).wrap_err("invalid private key")
-            }};
-        }
-
-        if let Some(key) = &self.private_key {
-            if key.is_empty() {
-                return Err(eyre!("empty private key"));
-            }
-            return wallet!(key);
+use ethers::{
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer, WalletError},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature, H160,
+    },
+};
+use eyre::{bail, eyre, Context, Result};
+use serde_json::Value;
+use std::{fs, path::Path};
+use zeroize::Zeroize;
+
+/// Signs txs with the wallet `AuthOpts` selects.
+#[derive(Debug)]
+pub enum StylusSigner {
+    Local(LocalWallet),
+    #[cfg(feature = "ledger")]
+    Ledger(LedgerSigner),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    #[error(transparent)]
+    Local(#[from] WalletError),
+    #[cfg(feature = "ledger")]
+    #[error("{0}")]
+    Ledger(String),
+}
+
+/// Resolves the signer `AuthOpts` selects for a chain, connecting to the Ledger if `--ledger`
+/// is set. Every command that signs goes through here, so a new wallet source only needs
+/// handling in this module.
+///
+/// This returns the [`StylusSigner`] enum rather than a `Box<dyn Signer>`, since ethers'
+/// `Signer` has generic methods and so can't be made into a trait object.
+pub async fn resolve_signer(auth: &AuthOpts, chain_id: u64) -> Result<StylusSigner> {
+    if auth.ledger {
+        #[cfg(feature = "ledger")]
+        return Ok(StylusSigner::Ledger(
+            ledger::connect(auth.ledger_account_index, chain_id).await?,
+        ));
+        #[cfg(not(feature = "ledger"))]
+        bail!("this cargo stylus was built without Ledger support, reinstall it with `--features ledger`");
+    }
+    let wallet = local_wallet(auth).wrap_err("failed to load wallet")?;
+    Ok(StylusSigner::Local(wallet.with_chain_id(chain_id)))
+}
+
+#[async_trait]
+impl Signer for StylusSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => ledger.sign_message(message).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => ledger.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => ledger.sign_typed_data(payload).await,
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            Self::Local(wallet) => wallet.address(),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => ledger.0.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            Self::Local(wallet) => wallet.chain_id(),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => ledger.0.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            Self::Local(wallet) => Self::Local(wallet.with_chain_id(chain_id)),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(ledger) => Self::Ledger(LedgerSigner(ledger.0.with_chain_id(chain_id))),
+        }
+    }
+}
+
+/// Fails unless `address` is in the address book at `path`, which lists one address per line.
+/// Blank lines are skipped, and anything after a `#` is a comment, such as the owner's name.
+pub fn check_allowed_deployer(path: &Path, address: H160) -> Result<()> {
+    let text = fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read allowed deployers {path:?}"))?;
+    let mut allowed = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
         }
+        let entry: H160 = line
+            .parse()
+            .map_err(|_| eyre!("{path:?} line {}: invalid address {line}", index + 1))?;
+        allowed.push(entry);
+    }
+    if !allowed.contains(&address) {
+        bail!(
+            "address {} isn't one of the {} allowed deployers in {}",
+            ethers::utils::to_checksum(&address, None),
+            allowed.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Loads a local wallet from a private key, keystore or mnemonic. Keys, phrases and passwords
+/// read from files are wiped from memory once the wallet is built.
+fn local_wallet(auth: &AuthOpts) -> Result<LocalWallet> {
+    if let Some(key) = &auth.private_key {
+        if key.is_empty() {
+            bail!("empty private key");
+        }
+        return wallet_from_key(key);
+    }
+
+    if let Some(file) = &auth.private_key_path {
+        let mut key = fs::read_to_string(file).wrap_err("could not open private key file")?;
+        let wallet = wallet_from_key(&key);
+        key.zeroize();
+        return wallet;
+    }
+
+    if let Some(file) = &auth.mnemonic_path {
+        return derive_from_mnemonic(auth, file);
+    }
+
+    let mut password = match &auth.keystore_password_path {
+        Some(path) => fs::read_to_string(path).wrap_err("could not open keystore password file")?,
+        None => String::new(),
+    };
+    let wallet = match (&auth.keystore_path, &auth.keystore_dir, auth.account) {
+        (Some(keystore), ..) => LocalWallet::decrypt_keystore(keystore, &password)
+            .wrap_err("could not decrypt keystore"),
+        (None, Some(dir), Some(account)) => unlock_account(dir, account, &password),
+        _ => Err(eyre!(
+            "no wallet given, please pass --private-key-path, --private-key, \
+             --keystore-path, --keystore-dir, --mnemonic-path or --ledger"
+        )),
+    };
+    password.zeroize();
+    wallet
+}
+
+/// Builds a wallet from a hex-encoded private key, such as a key file's contents with their
+/// trailing newline, wiping the decoded bytes afterwards. The length is checked first, since
+/// the signing key panics on anything but 32 bytes.
+fn wallet_from_key(key: &str) -> Result<LocalWallet> {
+    let mut bytes = text::decode0x(key.trim()).wrap_err("invalid private key")?;
+    if bytes.len() != 32 {
+        let len = bytes.len();
+        bytes.zeroize();
+        bail!("invalid private key, expected 32 bytes but got {len}");
+    }
+    let wallet = LocalWallet::from_bytes(&bytes).wrap_err("invalid private key");
+    bytes.zeroize();
+    wallet
+}
+
+/// Derives the account at `--hd-path`, or at `--mnemonic-account-index` of the default path,
+/// from a mnemonic file. The phrase is wiped from memory once the wallet is built.
+fn derive_from_mnemonic(auth: &AuthOpts, file: &Path) -> Result<LocalWallet> {
+    let mut phrase = fs::read_to_string(file).wrap_err("could not open mnemonic file")?;
+    phrase.truncate(phrase.trim_end().len());
+    let builder = MnemonicBuilder::<English>::default().phrase(phrase.as_str());
+    let builder = match &auth.hd_path {
+        Some(path) => builder.derivation_path(path),
+        None => builder.index(auth.mnemonic_account_index),
+    };
+    let wallet = builder
+        .wrap_err("invalid derivation path")
+        .and_then(|builder| builder.build().wrap_err("invalid mnemonic"));
+    phrase.zeroize();
+    wallet
+}
 
-        if let Some(file) = &self.private_key_path {
-            let key = fs::read_to_string(file).wrap_err("could not open private key file")?;
-            return wallet!(key);
+/// Unlocks the keystore for an account among those in a directory.
+///
+/// Geth-style keystores record their address in plain text, so those are matched directly.
+/// Keystores without one have to be decrypted to learn their address.
+fn unlock_account(dir: &Path, account: H160, password: &str) -> Result<LocalWallet> {
+    let entries =
+        fs::read_dir(dir).wrap_err_with(|| format!("could not read keystore dir {dir:?}"))?;
+    let mut anonymous = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let Ok(json) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(keystore) = serde_json::from_str::<Value>(&json) else {
+            continue;
+        };
+        if keystore.get("crypto").is_none() && keystore.get("Crypto").is_none() {
+            continue;
+        }
+        let address = keystore.get("address").and_then(Value::as_str);
+        match address.map(text::decode0x) {
+            Some(Ok(address)) if address == account.as_bytes() => {
+                return LocalWallet::decrypt_keystore(&path, password)
+                    .wrap_err_with(|| format!("could not decrypt keystore {path:?}"));
+            }
+            Some(_) => {}
+            None => anonymous.push(path),
         }
+    }
+    for path in anonymous {
+        if let Ok(wallet) = LocalWallet::decrypt_keystore(&path, password) {
+            if wallet.address() == account {
+                return Ok(wallet);
+            }
+        }
+    }
+    bail!("no keystore for account {account:?} found in {dir:?}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::{Args, FromArgMatches};
+    use tempfile::tempdir;
+
+    /// The first account of the well-known `test test ... junk` development mnemonic.
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    fn auth(args: &[&str]) -> AuthOpts {
+        let command = AuthOpts::augment_args(clap::Command::new("test"));
+        let matches = command.get_matches_from([&["test"], args].concat());
+        AuthOpts::from_arg_matches(&matches).unwrap()
+    }
+
+    async fn resolve_address(args: &[&str]) -> Result<H160> {
+        let signer = resolve_signer(&auth(args), 42).await?;
+        assert_eq!(signer.chain_id(), 42);
+        Ok(signer.address())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_private_key() -> Result<()> {
+        let address: H160 = ADDRESS.parse()?;
+        assert_eq!(resolve_address(&["--private-key", KEY]).await?, address);
+        assert!(resolve_address(&["--private-key", ""]).await.is_err());
+        assert!(resolve_address(&["--private-key", "0x1234"]).await.is_err());
+
+        let dir = tempdir()?;
+        let path = dir.path().join("key.txt");
+        fs::write(&path, format!("{KEY}\n"))?;
+        let path = path.to_string_lossy();
+        assert_eq!(
+            resolve_address(&["--private-key-path", &path]).await?,
+            address
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_keystore() -> Result<()> {
+        let dir = tempdir()?;
+        let mut rng = ethers::core::rand::thread_rng();
+        let key = text::decode0x(KEY)?;
+        LocalWallet::encrypt_keystore(dir.path(), &mut rng, key, "pass", Some("wallet.json"))?;
+        let password = dir.path().join("password.txt");
+        fs::write(&password, "pass")?;
+
+        let keystore = dir.path().join("wallet.json");
+        let keystore = keystore.to_string_lossy();
+        let password = password.to_string_lossy();
+        let args = [
+            "--keystore-path",
+            &keystore,
+            "--keystore-password-path",
+            &password,
+        ];
+        assert_eq!(resolve_address(&args).await?, ADDRESS.parse()?);
+        assert!(resolve_address(&args[..2]).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_mnemonic() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("mnemonic.txt");
+        fs::write(&path, format!("{MNEMONIC}\n"))?;
+        let path = path.to_string_lossy();
+        let address: H160 = ADDRESS.parse()?;
+        assert_eq!(resolve_address(&["--mnemonic-path", &path]).await?, address);
+        let hd_path = ["--mnemonic-path", &path, "--hd-path", "m/44'/60'/0'/0/0"];
+        assert_eq!(resolve_address(&hd_path).await?, address);
+        let index = ["--mnemonic-path", &path, "--mnemonic-account-index", "1"];
+        assert_ne!(resolve_address(&index).await?, address);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_no_wallet() {
+        let err = resolve_address(&[]).await.unwrap_err();
+        assert!(format!("{err:?}").contains("no wallet given"));
+        #[cfg(not(feature = "ledger"))]
+        assert!(resolve_address(&["--ledger"]).await.is_err());
+    }
+
+    #[test]
+    fn test_check_allowed_deployer() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("deployers.txt");
+        let alice = H160::repeat_byte(0xaa);
+        fs::write(&path, format!("# release team\n\n{alice:?}  # alice\n"))?;
+        check_allowed_deployer(&path, alice)?;
+        assert!(check_allowed_deployer(&path, H160::repeat_byte(0xbb)).is_err());
+
+        fs::write(&path, "0x1234\n")?;
+        let err = check_allowed_deployer(&path, alice).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        Ok(())
+    }
 
-        let keystore = self.keystore_path.as_ref().ok_or(eyre!("no keystore"))?;
-        let password = self
-            .keystore_password_path
-            .as_ref()
-            .map(fs::read_to_string)
-            .unwrap_or(Ok("".into()))?;
+    #[test]
+    fn test_unlock_account() -> Result<()> {
+        let dir = tempdir()?;
+        let mut rng = ethers::core::rand::thread_rng();
+        let (wallet, _) = LocalWallet::new_keystore(dir.path(), &mut rng, "pass", None)?;
+        fs::write(dir.path().join("notes.txt"), "not a keystore")?;
 
-        LocalWallet::decrypt_keystore(keystore, password).wrap_err("could not decrypt keystore")
+        let unlocked = unlock_account(dir.path(), wallet.address(), "pass")?;
+        assert_eq!(unlocked.address(), wallet.address());
+        assert!(unlock_account(dir.path(), H160::random(), "pass").is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,34 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    macros::*,
+    project::{self, BuildConfig},
+    WarmupConfig,
+};
+use cargo_stylus_util::color::{Color, DebugColor};
+use eyre::Result;
+use std::time::Instant;
+
+/// Compiles the project and its dependencies to WASM without checking or deploying it, so that
+/// later commands given the same build flags reuse the target directory.
+pub fn warmup(cfg: &WarmupConfig) -> Result<()> {
+    let common = &cfg.common_cfg;
+    let build_cfg = BuildConfig {
+        allow_non_stylus: common.allow_non_stylus,
+        normalize_manifest_paths: common.normalize_manifest_paths,
+        no_ignore: common.no_ignore,
+        rustc_wrapper: common.rustc_wrapper.clone(),
+        cargo_args: common.cargo_arg.clone(),
+        invocation_file: common.build_invocation.clone(),
+        ..BuildConfig::new(common.rust_stable, common.package.clone())
+    };
+    let start = Instant::now();
+    let wasm = project::build_dylib(build_cfg)?;
+    greyln!(
+        "warmed up the build cache in {}, building {}",
+        format!("{:.1?}", start.elapsed()).mint(),
+        wasm.debug_lavender()
+    );
+    Ok(())
+}
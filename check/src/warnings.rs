@@ -0,0 +1,204 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use cargo_stylus_util::color::Color;
+use eyre::{bail, Result};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+/// Every kind of warning cargo stylus emits. Each has a stable code that can be passed to
+/// `--allow` or `--deny`, so codes must never be renumbered or reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// The compressed program is approaching the chain's size limit.
+    ProgramSize,
+    /// The manifest doesn't look like a Stylus project, but `--allow-non-stylus` was set.
+    NotStylusProject,
+    /// The WASM already has a project hash section, which is left as is.
+    ProjectHashExists,
+    /// solc isn't installed, so no JSON ABI could be produced.
+    SolcMissing,
+    /// Activation gas couldn't be estimated to compare against `--max-gas`.
+    ActivationGasUnknown,
+    /// The estimated gas exceeds `--max-gas`, but `--force` was set.
+    MaxGasExceeded,
+    /// The gas oracle failed, so the node's fees are used instead.
+    GasOracleFailed,
+    /// The nonce file is behind the chain.
+    StaleNonceFile,
+    /// A deployment tx recorded by a previous run reverted or was dropped.
+    PreviousDeployFailed,
+    /// The build was passed extra cargo args, which may change the WASM.
+    ExtraCargoArgs,
+    /// The base fee exceeds `--max-acceptable-base-fee-gwei`, but `--force` was set.
+    BaseFeeExceeded,
+    /// The CacheManager's minimum bid couldn't be queried, so a zero bid is sent.
+    MinBidUnknown,
+    /// The endpoint is on a different chain than `--chain` names.
+    ChainMismatch,
+    /// A verified deployment's prelude differs from the local one only in its version byte.
+    PreludeVersionMismatch,
+    /// `verify --check-activation` found no activation of the codehash since the deployment.
+    ActivationNotFound,
+    /// The nonce file is ahead of the chain's pending nonce.
+    NonceFileAhead,
+}
+
+impl Warning {
+    const ALL: &'static [Warning] = &[
+        Warning::ProgramSize,
+        Warning::NotStylusProject,
+        Warning::ProjectHashExists,
+        Warning::SolcMissing,
+        Warning::ActivationGasUnknown,
+        Warning::MaxGasExceeded,
+        Warning::GasOracleFailed,
+        Warning::StaleNonceFile,
+        Warning::PreviousDeployFailed,
+        Warning::ExtraCargoArgs,
+        Warning::BaseFeeExceeded,
+        Warning::MinBidUnknown,
+        Warning::ChainMismatch,
+        Warning::PreludeVersionMismatch,
+        Warning::ActivationNotFound,
+        Warning::NonceFileAhead,
+    ];
+
+    /// The warning's stable code, like `STYLUS_W001`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Warning::ProgramSize => "STYLUS_W001",
+            Warning::NotStylusProject => "STYLUS_W002",
+            Warning::ProjectHashExists => "STYLUS_W003",
+            Warning::SolcMissing => "STYLUS_W004",
+            Warning::ActivationGasUnknown => "STYLUS_W005",
+            Warning::MaxGasExceeded => "STYLUS_W006",
+            Warning::GasOracleFailed => "STYLUS_W007",
+            Warning::StaleNonceFile => "STYLUS_W008",
+            Warning::PreviousDeployFailed => "STYLUS_W009",
+            Warning::ExtraCargoArgs => "STYLUS_W010",
+            Warning::BaseFeeExceeded => "STYLUS_W011",
+            Warning::MinBidUnknown => "STYLUS_W012",
+            Warning::ChainMismatch => "STYLUS_W013",
+            Warning::PreludeVersionMismatch => "STYLUS_W014",
+            Warning::ActivationNotFound => "STYLUS_W015",
+            Warning::NonceFileAhead => "STYLUS_W016",
+        }
+    }
+}
+
+/// Parses a warning code passed to `--allow` or `--deny`.
+pub fn parse_code(code: &str) -> Result<Warning, String> {
+    Warning::ALL
+        .iter()
+        .copied()
+        .find(|warning| warning.code().eq_ignore_ascii_case(code))
+        .ok_or_else(|| format!("unknown warning code {code}"))
+}
+
+#[derive(Default)]
+struct Registry {
+    allowed: Vec<Warning>,
+    denied: Vec<Warning>,
+    /// Every warning emitted during this run.
+    emitted: Vec<Warning>,
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+/// Silences the `allowed` warnings and turns the `denied` ones into errors. Denial wins when a
+/// code is passed to both.
+pub fn configure(allowed: Vec<Warning>, denied: Vec<Warning>) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.allowed = allowed;
+    registry.denied = denied;
+}
+
+/// Prints a warning to stderr and records it for `--fail-on-warnings`. Fails instead when the
+/// warning was passed to `--deny`, so that callers stop before acting on it.
+pub fn warn(warning: Warning, msg: String) -> Result<()> {
+    let line = REGISTRY.lock().unwrap().record(warning, &msg)?;
+    if let Some(line) = line {
+        eprintln!("{}", crate::output::stamp(line));
+    }
+    Ok(())
+}
+
+/// Fails if any warning was emitted when `fail_on_warnings` is set, for use with
+/// `--fail-on-warnings`.
+pub fn enforce(fail_on_warnings: bool) -> Result<()> {
+    REGISTRY.lock().unwrap().enforce(fail_on_warnings)
+}
+
+impl Registry {
+    /// Records a warning, returning the line to print unless it's allowed.
+    fn record(&mut self, warning: Warning, msg: &str) -> Result<Option<String>> {
+        if self.denied.contains(&warning) {
+            bail!("denied warning {}: {msg}", warning.code());
+        }
+        if self.allowed.contains(&warning) {
+            return Ok(None);
+        }
+        self.emitted.push(warning);
+        Ok(Some(format!(
+            "{} {}",
+            format!("warning[{}]:", warning.code()).yellow(),
+            msg.grey()
+        )))
+    }
+
+    fn enforce(&self, fail_on_warnings: bool) -> Result<()> {
+        if !fail_on_warnings {
+            return Ok(());
+        }
+        match self.emitted.len() {
+            0 => Ok(()),
+            1 => bail!("1 warning emitted with --fail-on-warnings set"),
+            n => bail!("{n} warnings emitted with --fail-on-warnings set"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!(parse_code("STYLUS_W001"), Ok(Warning::ProgramSize));
+        assert_eq!(parse_code("Stylus_w016"), Ok(Warning::NonceFileAhead));
+        assert!(parse_code("STYLUS_W999").is_err());
+        assert!(parse_code("ProgramSize").is_err());
+        assert!(parse_code("").is_err());
+    }
+
+    #[test]
+    fn test_allow_and_deny() -> Result<()> {
+        let mut registry = Registry {
+            allowed: vec![Warning::ProgramSize, Warning::SolcMissing],
+            denied: vec![Warning::SolcMissing, Warning::ChainMismatch],
+            emitted: vec![],
+        };
+        // Denial wins over allowing the same code.
+        let err = registry
+            .record(Warning::SolcMissing, "no solc")
+            .unwrap_err();
+        assert!(err.to_string().contains("STYLUS_W004"));
+        assert!(registry
+            .record(Warning::ChainMismatch, "wrong chain")
+            .is_err());
+
+        // Allowed warnings are silent and don't fail --fail-on-warnings.
+        assert_eq!(registry.record(Warning::ProgramSize, "big")?, None);
+        registry.enforce(true)?;
+
+        assert!(registry
+            .record(Warning::GasOracleFailed, "oracle down")?
+            .is_some());
+        registry.enforce(false)?;
+        assert!(registry.enforce(true).is_err());
+        Ok(())
+    }
+}
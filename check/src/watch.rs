@@ -0,0 +1,107 @@
+// Copyright 2023-2024, Offchain Labs, Inc.
+// For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
+
+use crate::{
+    constants::{LOCAL_DEV_CHAIN_IDS, WATCH_POLL_INTERVAL},
+    deploy,
+    macros::*,
+    project, DeployConfig,
+};
+use cargo_stylus_util::{color::Color, sys};
+use ethers::providers::Middleware;
+use eyre::{bail, Result, WrapErr};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Modification times and sizes of the files a deployment is built from.
+type Snapshot = BTreeMap<PathBuf, (SystemTime, u64)>;
+
+/// Deploys the program, then rebuilds and redeploys it each time its sources change.
+/// Only runs against local dev nodes.
+pub async fn watch_and_deploy(cfg: DeployConfig) -> Result<()> {
+    let common_cfg = &cfg.check_config.common_cfg;
+    let provider = common_cfg.provider()?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .wrap_err("failed to get chain id")?
+        .as_u64();
+    if !LOCAL_DEV_CHAIN_IDS.contains(&chain_id) {
+        bail!("refusing to watch and deploy to chain {chain_id}, which is not a local dev node");
+    }
+    if !is_localhost(common_cfg.endpoint())
+        && !sys::confirm(&format!(
+            "{} is not localhost, rebuild and redeploy to it on every change?",
+            common_cfg.endpoint().yellow()
+        ))?
+    {
+        bail!("not enabling watch mode");
+    }
+
+    let root = Path::new(".");
+    let patterns = &common_cfg.source_files_for_project_hash;
+    let use_gitignore = !common_cfg.no_ignore;
+    let mut last = snapshot(root, patterns.clone(), use_gitignore)?;
+    loop {
+        if let Err(error) = deploy::deploy(cfg.clone()).await {
+            egreyln!("{}: {error:#}", "failed to deploy".red());
+        }
+        greyln!("watching for changes...");
+        loop {
+            tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+            let next = snapshot(root, patterns.clone(), use_gitignore)?;
+            if next != last {
+                last = next;
+                break;
+            }
+        }
+        greyln!("change detected, redeploying");
+    }
+}
+
+fn snapshot(
+    root: &Path,
+    source_file_patterns: Vec<String>,
+    use_gitignore: bool,
+) -> Result<Snapshot> {
+    let mut snapshot = Snapshot::new();
+    for path in project::all_paths(root, source_file_patterns, use_gitignore)? {
+        // files may disappear between listing and reading during an editor save
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        snapshot.insert(path, (modified, metadata.len()));
+    }
+    Ok(snapshot)
+}
+
+fn is_localhost(endpoint: &str) -> bool {
+    let authority = endpoint.split("://").last().unwrap_or_default();
+    let authority = authority.split('/').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_localhost() {
+        assert!(is_localhost("http://127.0.0.1:8547"));
+        assert!(is_localhost("http://localhost:8547/rpc"));
+        assert!(is_localhost("ws://localhost"));
+        assert!(is_localhost("http://[::1]:8547"));
+        assert!(!is_localhost("https://sepolia-rollup.arbitrum.io/rpc"));
+        assert!(!is_localhost("http://localhost.example.com:8547"));
+        assert!(!is_localhost("http://[::2]:8547"));
+    }
+}
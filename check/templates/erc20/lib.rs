@@ -0,0 +1,168 @@
+//! An ERC-20 token.
+//!
+//! Stylus programs have no constructor, so the initial supply is minted to whoever first calls
+//! `initialize`, e.g. with `cargo stylus deploy --constructor-signature "initialize(uint256)"
+//! --allow-separate-init-tx`. Anyone can call it before that tx lands, so check afterwards that
+//! the supply went to the deployer.
+
+#![cfg_attr(not(any(feature = "export-abi", test)), no_main)]
+extern crate alloc;
+
+use alloc::string::String;
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::sol;
+use stylus_sdk::{evm, msg, prelude::*};
+
+const NAME: &str = "MyToken";
+const SYMBOL: &str = "MTK";
+const DECIMALS: u8 = 18;
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+
+    error InsufficientBalance(address from, uint256 have, uint256 want);
+    error InsufficientAllowance(address owner, address spender, uint256 have, uint256 want);
+    error AlreadyInitialized();
+}
+
+#[derive(SolidityError)]
+pub enum Erc20Error {
+    InsufficientBalance(InsufficientBalance),
+    InsufficientAllowance(InsufficientAllowance),
+    AlreadyInitialized(AlreadyInitialized),
+}
+
+sol_storage! {
+    #[entrypoint]
+    pub struct Erc20 {
+        mapping(address => uint256) balances;
+        mapping(address => mapping(address => uint256)) allowances;
+        uint256 total_supply;
+        bool initialized;
+    }
+}
+
+impl Erc20 {
+    fn move_tokens(&mut self, from: Address, to: Address, value: U256) -> Result<(), Erc20Error> {
+        let mut from_balance = self.balances.setter(from);
+        let have = from_balance.get();
+        if have < value {
+            return Err(Erc20Error::InsufficientBalance(InsufficientBalance {
+                from,
+                have,
+                want: value,
+            }));
+        }
+        from_balance.set(have - value);
+        let mut to_balance = self.balances.setter(to);
+        let balance = to_balance.get();
+        to_balance.set(balance + value);
+        evm::log(Transfer { from, to, value });
+        Ok(())
+    }
+}
+
+#[public]
+impl Erc20 {
+    /// Mints the initial supply to the caller. Can only be called once.
+    pub fn initialize(&mut self, supply: U256) -> Result<(), Erc20Error> {
+        if self.initialized.get() {
+            return Err(Erc20Error::AlreadyInitialized(AlreadyInitialized {}));
+        }
+        self.initialized.set(true);
+        let to = msg::sender();
+        self.balances.setter(to).set(supply);
+        self.total_supply.set(supply);
+        evm::log(Transfer {
+            from: Address::ZERO,
+            to,
+            value: supply,
+        });
+        Ok(())
+    }
+
+    pub fn name(&self) -> String {
+        NAME.into()
+    }
+
+    pub fn symbol(&self) -> String {
+        SYMBOL.into()
+    }
+
+    pub fn decimals(&self) -> u8 {
+        DECIMALS
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        self.total_supply.get()
+    }
+
+    pub fn balance_of(&self, owner: Address) -> U256 {
+        self.balances.get(owner)
+    }
+
+    pub fn allowance(&self, owner: Address, spender: Address) -> U256 {
+        self.allowances.getter(owner).get(spender)
+    }
+
+    pub fn transfer(&mut self, to: Address, value: U256) -> Result<bool, Erc20Error> {
+        self.move_tokens(msg::sender(), to, value)?;
+        Ok(true)
+    }
+
+    pub fn approve(&mut self, spender: Address, value: U256) -> bool {
+        let owner = msg::sender();
+        self.allowances.setter(owner).insert(spender, value);
+        evm::log(Approval {
+            owner,
+            spender,
+            value,
+        });
+        true
+    }
+
+    pub fn transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> Result<bool, Erc20Error> {
+        let spender = msg::sender();
+        let mut owner_allowances = self.allowances.setter(from);
+        let mut allowance = owner_allowances.setter(spender);
+        let have = allowance.get();
+        if have < value {
+            return Err(Erc20Error::InsufficientAllowance(InsufficientAllowance {
+                owner: from,
+                spender,
+                have,
+                want: value,
+            }));
+        }
+        allowance.set(have - value);
+        self.move_tokens(from, to, value)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy_sol_types::{SolError, SolEvent};
+
+    // Methods that touch storage need a chain, such as a local dev node, to run against.
+    #[test]
+    fn test_abi() {
+        assert_eq!(Transfer::SIGNATURE, "Transfer(address,address,uint256)");
+        assert_eq!(Approval::SIGNATURE, "Approval(address,address,uint256)");
+
+        let error = InsufficientBalance {
+            from: Address::ZERO,
+            have: U256::ZERO,
+            want: U256::from(1),
+        };
+        let data: Vec<u8> = Erc20Error::InsufficientBalance(error).into();
+        assert_eq!(data[..4], InsufficientBalance::SELECTOR);
+    }
+}
@@ -0,0 +1,224 @@
+//! An ERC-721 collection of non-fungible tokens.
+//!
+//! Stylus programs have no constructor, so whoever first calls `initialize`, e.g. with
+//! `cargo stylus deploy --constructor-signature "initialize()" --allow-separate-init-tx`, becomes
+//! the only account allowed to mint. Anyone can call it before that tx lands, so check afterwards
+//! that the deployer is the minter. Tokens are numbered from zero in the order they're minted.
+
+#![cfg_attr(not(any(feature = "export-abi", test)), no_main)]
+extern crate alloc;
+
+use alloc::string::String;
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::sol;
+use stylus_sdk::{evm, msg, prelude::*};
+
+const NAME: &str = "MyCollection";
+const SYMBOL: &str = "MYC";
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+    event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId);
+    event ApprovalForAll(address indexed owner, address indexed operator, bool approved);
+
+    error InvalidToken(uint256 tokenId);
+    error NotOwner(address from, uint256 tokenId, address owner);
+    error NotApproved(address spender, uint256 tokenId);
+    error InvalidReceiver(address to);
+    error NotMinter(address account);
+    error AlreadyInitialized();
+}
+
+#[derive(SolidityError)]
+pub enum Erc721Error {
+    InvalidToken(InvalidToken),
+    NotOwner(NotOwner),
+    NotApproved(NotApproved),
+    InvalidReceiver(InvalidReceiver),
+    NotMinter(NotMinter),
+    AlreadyInitialized(AlreadyInitialized),
+}
+
+sol_storage! {
+    #[entrypoint]
+    pub struct Erc721 {
+        mapping(uint256 => address) owners;
+        mapping(address => uint256) balances;
+        mapping(uint256 => address) token_approvals;
+        mapping(address => mapping(address => bool)) operator_approvals;
+        uint256 total_supply;
+        address minter;
+    }
+}
+
+impl Erc721 {
+    fn owner(&self, token_id: U256) -> Result<Address, Erc721Error> {
+        let owner = self.owners.get(token_id);
+        if owner.is_zero() {
+            return Err(Erc721Error::InvalidToken(InvalidToken { tokenId: token_id }));
+        }
+        Ok(owner)
+    }
+
+    fn may_spend(&self, spender: Address, owner: Address, token_id: U256) -> bool {
+        spender == owner
+            || self.token_approvals.get(token_id) == spender
+            || self.operator_approvals.getter(owner).get(spender)
+    }
+}
+
+#[public]
+impl Erc721 {
+    /// Makes the caller the minter. Can only be called once.
+    pub fn initialize(&mut self) -> Result<(), Erc721Error> {
+        if !self.minter.get().is_zero() {
+            return Err(Erc721Error::AlreadyInitialized(AlreadyInitialized {}));
+        }
+        self.minter.set(msg::sender());
+        Ok(())
+    }
+
+    /// Mints the next token to `to`, returning its ID. Only the minter may call this.
+    pub fn mint(&mut self, to: Address) -> Result<U256, Erc721Error> {
+        let minter = self.minter.get();
+        if minter.is_zero() || msg::sender() != minter {
+            return Err(Erc721Error::NotMinter(NotMinter {
+                account: msg::sender(),
+            }));
+        }
+        if to.is_zero() {
+            return Err(Erc721Error::InvalidReceiver(InvalidReceiver { to }));
+        }
+        let token_id = self.total_supply.get();
+        self.total_supply.set(token_id + U256::from(1));
+        self.owners.insert(token_id, to);
+        let balance = self.balances.get(to);
+        self.balances.insert(to, balance + U256::from(1));
+        evm::log(Transfer {
+            from: Address::ZERO,
+            to,
+            tokenId: token_id,
+        });
+        Ok(token_id)
+    }
+
+    pub fn name(&self) -> String {
+        NAME.into()
+    }
+
+    pub fn symbol(&self) -> String {
+        SYMBOL.into()
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        self.total_supply.get()
+    }
+
+    pub fn balance_of(&self, owner: Address) -> U256 {
+        self.balances.get(owner)
+    }
+
+    pub fn owner_of(&self, token_id: U256) -> Result<Address, Erc721Error> {
+        self.owner(token_id)
+    }
+
+    pub fn approve(&mut self, approved: Address, token_id: U256) -> Result<(), Erc721Error> {
+        let owner = self.owner(token_id)?;
+        let sender = msg::sender();
+        if sender != owner && !self.operator_approvals.getter(owner).get(sender) {
+            return Err(Erc721Error::NotApproved(NotApproved {
+                spender: sender,
+                tokenId: token_id,
+            }));
+        }
+        self.token_approvals.insert(token_id, approved);
+        evm::log(Approval {
+            owner,
+            approved,
+            tokenId: token_id,
+        });
+        Ok(())
+    }
+
+    pub fn get_approved(&self, token_id: U256) -> Result<Address, Erc721Error> {
+        self.owner(token_id)?;
+        Ok(self.token_approvals.get(token_id))
+    }
+
+    pub fn set_approval_for_all(&mut self, operator: Address, approved: bool) {
+        let owner = msg::sender();
+        self.operator_approvals
+            .setter(owner)
+            .insert(operator, approved);
+        evm::log(ApprovalForAll {
+            owner,
+            operator,
+            approved,
+        });
+    }
+
+    pub fn is_approved_for_all(&self, owner: Address, operator: Address) -> bool {
+        self.operator_approvals.getter(owner).get(operator)
+    }
+
+    /// Moves a token without checking that a contract receiving it can handle NFTs.
+    pub fn transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        token_id: U256,
+    ) -> Result<(), Erc721Error> {
+        let owner = self.owner(token_id)?;
+        if owner != from {
+            return Err(Erc721Error::NotOwner(NotOwner {
+                from,
+                tokenId: token_id,
+                owner,
+            }));
+        }
+        if to.is_zero() {
+            return Err(Erc721Error::InvalidReceiver(InvalidReceiver { to }));
+        }
+        let spender = msg::sender();
+        if !self.may_spend(spender, owner, token_id) {
+            return Err(Erc721Error::NotApproved(NotApproved {
+                spender,
+                tokenId: token_id,
+            }));
+        }
+        self.token_approvals.delete(token_id);
+        let balance = self.balances.get(from);
+        self.balances.insert(from, balance - U256::from(1));
+        let balance = self.balances.get(to);
+        self.balances.insert(to, balance + U256::from(1));
+        self.owners.insert(token_id, to);
+        evm::log(Transfer {
+            from,
+            to,
+            tokenId: token_id,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy_sol_types::{SolError, SolEvent};
+
+    // Methods that touch storage need a chain, such as a local dev node, to run against.
+    #[test]
+    fn test_abi() {
+        assert_eq!(Transfer::SIGNATURE, "Transfer(address,address,uint256)");
+        assert_eq!(
+            ApprovalForAll::SIGNATURE,
+            "ApprovalForAll(address,address,bool)"
+        );
+
+        let error = InvalidToken {
+            tokenId: U256::from(7),
+        };
+        let data: Vec<u8> = Erc721Error::InvalidToken(error).into();
+        assert_eq!(data[..4], InvalidToken::SELECTOR);
+    }
+}
@@ -33,6 +33,9 @@ enum Subcommands {
     #[command(alias = "x")]
     /// Export a Solidity ABI.
     ExportAbi,
+    /// Activate an already deployed contract.
+    #[command(alias = "a")]
+    Activate,
     /// Cache a contract.
     #[command(alias = "c")]
     Cache,
@@ -42,6 +45,9 @@ enum Subcommands {
     /// Deploy a contract.
     #[command(alias = "d")]
     Deploy,
+    /// Replace a stuck transaction with one paying higher fees.
+    #[command()]
+    Bump,
     /// Replay a transaction in gdb.
     #[command(alias = "r")]
     Replay,
@@ -54,6 +60,33 @@ enum Subcommands {
     /// Run cargo stylus commands in a Docker container for reproducibility.
     #[command()]
     Reproducible,
+    /// Compare a local build against one made in the reproducible Docker image.
+    #[command()]
+    ReproReport,
+    /// Decode a program's deployment calldata locally.
+    #[command()]
+    DecodeCalldata,
+    /// Bundle a program's compressed WASM, ABI and build info into a zip archive.
+    #[command()]
+    Package,
+    /// Build the project once so later builds reuse the target directory.
+    #[command()]
+    Warmup,
+    /// Find the transaction that activated a deployed program.
+    #[command()]
+    FindActivation,
+    /// Print the Stylus version a deployed program targets.
+    #[command()]
+    InspectVersion,
+    /// Print the contract version a deployed program embeds.
+    #[command()]
+    Version,
+    /// Print the version, subcommands and signers this cargo stylus supports.
+    #[command()]
+    Capabilities,
+    /// Print the JSON Schema of a command's JSON output.
+    #[command(hide = true)]
+    JsonSchema,
     /// Generate C code.
     #[command()]
     CGen,
@@ -71,13 +104,25 @@ const COMMANDS: &[Binary] = &[
         apis: &[
             "new",
             "export-abi",
+            "activate",
             "cache",
             "check",
             "deploy",
+            "bump",
             "verify",
             "reproducible",
+            "repro-report",
+            "decode-calldata",
+            "package",
+            "warmup",
+            "find-activation",
+            "inspect-version",
+            "version",
+            "capabilities",
+            "json-schema",
             "n",
             "x",
+            "a",
             "c",
             "d",
             "v",
@@ -128,18 +173,10 @@ fn main() -> Result<()> {
     };
 
     let Some(bin) = COMMANDS.iter().find(|x| x.apis.contains(&arg.as_str())) else {
-        // see if custom extension exists
+        // see if a custom extension exists on the PATH, mirroring cargo's plugin model
         let custom = format!("cargo-stylus-{arg}");
-        if sys::command_exists(&custom) {
-            let mut command = sys::new_command(&custom);
-            command.arg(arg).args(args);
-
-            // Execute command conditionally based on the platform
-            #[cfg(unix)]
-            let err = command.exec(); // Unix-specific execution
-            #[cfg(windows)]
-            let err = command.status(); // Windows-specific execution
-            bail!("failed to invoke {:?}: {:?}", custom.red(), err);
+        if sys::find_executable(&custom).is_some() {
+            return exec(&custom, &arg, args);
         }
 
         eprintln!("Unknown subcommand {}.", arg.red());
@@ -161,14 +198,21 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    // should never return
+    exec(name, &arg, args)
+}
+
+/// Runs a subcommand binary, forwarding its exit code.
+fn exec(name: &str, arg: &str, args: impl Iterator<Item = String>) -> Result<()> {
     let mut command = sys::new_command(name);
     command.arg(arg).args(args);
 
     // Execute command conditionally based on the platform
     #[cfg(unix)]
-    let err = command.exec(); // Unix-specific execution
+    let err = command.exec(); // Unix-specific execution, should never return
     #[cfg(windows)]
-    let err = command.status(); // Windows-specific execution
+    let err = match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(err) => err,
+    };
     bail!("failed to invoke {:?}: {:?}", name.red(), err);
 }
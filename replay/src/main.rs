@@ -3,7 +3,7 @@
 
 use crate::trace::Trace;
 use alloy_primitives::TxHash;
-use cargo_stylus_util::{color::Color, sys};
+use cargo_stylus_util::{color::Color, rpc::RpcPolicy, sys};
 use clap::{Args, Parser};
 use eyre::{bail, eyre, Context, Result};
 // Conditional import for Unix-specific `CommandExt`
@@ -105,7 +105,7 @@ async fn main_impl(args: Opts) -> Result<()> {
 }
 
 async fn trace(args: TraceArgs) -> Result<()> {
-    let provider = sys::new_provider(&args.endpoint)?;
+    let provider = sys::new_provider(&args.endpoint, &RpcPolicy::default())?;
     let trace = Trace::new(provider, args.tx).await?;
     println!("{}", trace.json);
     Ok(())
@@ -144,7 +144,7 @@ async fn replay(args: ReplayArgs) -> Result<()> {
         bail!("failed to exec gdb {:?}", err);
     }
 
-    let provider = sys::new_provider(&args.endpoint)?;
+    let provider = sys::new_provider(&args.endpoint, &RpcPolicy::default())?;
     let trace = Trace::new(provider, args.tx).await?;
 
     build_so(&args.project, args.stable_rust)?;
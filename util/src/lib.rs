@@ -2,5 +2,6 @@
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
 pub mod color;
+pub mod rpc;
 pub mod sys;
 pub mod text;
@@ -1,19 +1,42 @@
 // Copyright 2023-2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
-use ethers::{prelude::*, providers::Provider};
-use eyre::{Context, Result};
+use crate::rpc::{RpcClient, RpcPolicy};
+use ethers::providers::Provider;
+use eyre::{bail, Context, Result};
 use std::{
+    env,
     ffi::OsStr,
     fs::File,
     io::{self, Write},
     path::PathBuf,
     process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
     time::Duration,
 };
 
-pub fn new_provider(url: &str) -> Result<Provider<Http>> {
-    let mut provider = Provider::<Http>::try_from(url).wrap_err("failed to init http provider")?;
+/// Whether this process has been forbidden from reaching the network.
+static NETWORK_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Makes every later attempt to reach the network fail, including cargo's when it's run
+/// as a subprocess.
+pub fn disable_network() {
+    NETWORK_DISABLED.store(true, Ordering::Relaxed);
+    env::set_var("CARGO_NET_OFFLINE", "true");
+}
+
+/// Fails if the network has been disabled, naming what needed it.
+pub fn ensure_network(purpose: &str) -> Result<()> {
+    if NETWORK_DISABLED.load(Ordering::Relaxed) {
+        bail!("network access is disabled by --disable-network, but {purpose} needs it");
+    }
+    Ok(())
+}
+
+pub fn new_provider(url: &str, policy: &RpcPolicy) -> Result<Provider<RpcClient>> {
+    ensure_network("connecting to the RPC endpoint")?;
+    let client = RpcClient::new(url, policy.clone()).wrap_err("failed to init http provider")?;
+    let mut provider = Provider::new(client);
     provider.set_interval(Duration::from_millis(250));
     Ok(provider)
 }
@@ -34,6 +57,20 @@ pub fn command_exists<S: AsRef<OsStr>>(program: S) -> bool {
         .unwrap_or_default()
 }
 
+/// Finds an executable on the PATH without running it.
+pub fn find_executable<S: AsRef<OsStr>>(program: S) -> Option<PathBuf> {
+    let program = program.as_ref();
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).find_map(|dir| {
+        let path = dir.join(program);
+        if path.is_file() {
+            return Some(path);
+        }
+        let exe = path.with_extension(env::consts::EXE_EXTENSION);
+        exe.is_file().then_some(exe)
+    })
+}
+
 pub fn host_arch() -> Result<String> {
     rustc_host::from_cli().wrap_err_with(|| "failed to get host arch")
 }
@@ -45,3 +82,12 @@ pub fn file_or_stdout(path: Option<PathBuf>) -> Result<Box<dyn Write>> {
         None => Box::new(io::stdout().lock()),
     })
 }
+
+/// Asks a yes or no question on the terminal, defaulting to no.
+pub fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -1,10 +1,113 @@
 // Copyright 2024, Offchain Labs, Inc.
 // For licensing, see https://github.com/OffchainLabs/cargo-stylus/blob/main/licenses/COPYRIGHT.md
 
-use eyre::Result;
+use ethers::types::{H256, U256};
+use eyre::{bail, eyre, Result};
+use std::env;
 
 pub fn decode0x<T: AsRef<str>>(text: T) -> Result<Vec<u8>> {
     let text = text.as_ref();
     let text = text.strip_prefix("0x").unwrap_or(text);
     Ok(hex::decode(text)?)
 }
+
+/// Replaces every occurrence of a secret so the text can be safely logged.
+pub fn redact<T: AsRef<str>>(text: T, secret: &str) -> String {
+    let text = text.as_ref();
+    if secret.is_empty() {
+        return text.to_string();
+    }
+    text.replace(secret, "<redacted>")
+}
+
+/// Expands each `${VAR}` in the text to the value of that environment variable, also returning
+/// the values substituted so that they can be redacted wherever the result is shown.
+pub fn expand_env(template: &str) -> Result<(String, Vec<String>)> {
+    let mut expanded = String::new();
+    let mut values = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let var = &rest[start + 2..];
+        let Some(end) = var.find('}') else {
+            bail!("unterminated ${{ in {template}");
+        };
+        let name = &var[..end];
+        let value =
+            env::var(name).map_err(|_| eyre!("{template} uses ${{{name}}}, which isn't set"))?;
+        expanded.push_str(&value);
+        values.push(value);
+        rest = &var[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok((expanded, values))
+}
+
+/// Parses an amount of gwei, which may have up to 9 decimals, into wei.
+pub fn parse_gwei(text: &str) -> Result<U256, String> {
+    match ethers::utils::parse_units(text, "gwei") {
+        Ok(wei) => Ok(wei.into()),
+        Err(e) => Err(format!("invalid amount of gwei {text}: {e}")),
+    }
+}
+
+/// Parses 32 bytes given as hex, with or without a `0x` prefix.
+pub fn parse_h256(text: &str) -> Result<H256, String> {
+    let bytes = decode0x(text).map_err(|e| format!("invalid hex {text}: {e}"))?;
+    if bytes.len() != 32 {
+        return Err(format!("expected 32 bytes of hex, got {}", bytes.len()));
+    }
+    Ok(H256::from_slice(&bytes))
+}
+
+/// Parses an HTTP header given as `Name: Value`. The value is kept as is, so that any `${VAR}`
+/// in it can be expanded later.
+pub fn parse_header(text: &str) -> Result<(String, String), String> {
+    let Some((name, value)) = text.split_once(':') else {
+        return Err(format!("invalid header {text:?}, expected \"Name: Value\""));
+    };
+    let name = name.trim();
+    if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+        return Err(format!("invalid header name {name:?} in {text:?}"));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// Parses an integer given in decimal, or in hex when prefixed with `0x`.
+pub fn parse_u256(text: &str) -> Result<U256, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).map_err(|e| e.to_string()),
+        None => U256::from_dec_str(text).map_err(|e| e.to_string()),
+    };
+    parsed.map_err(|e| format!("invalid number {text}: {e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_env() -> Result<()> {
+        // Named for this test alone, so no other test sees it change.
+        env::set_var("STYLUS_TEST_EXPAND_ENV_KEY", "hunter2");
+        let (expanded, values) = expand_env("https://rpc.example/${STYLUS_TEST_EXPAND_ENV_KEY}/x")?;
+        assert_eq!(expanded, "https://rpc.example/hunter2/x");
+        assert_eq!(values, ["hunter2"]);
+        assert_eq!(expand_env("no variables")?, ("no variables".into(), vec![]));
+
+        let err = expand_env("Bearer ${STYLUS_TEST_UNSET_VAR}").unwrap_err();
+        assert!(err.to_string().contains("isn't set"));
+        assert!(expand_env("Bearer ${STYLUS_TEST_EXPAND_ENV_KEY").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = parse_header("  Authorization :  Bearer ${TOKEN} ").unwrap();
+        assert_eq!(header, ("Authorization".into(), "Bearer ${TOKEN}".into()));
+        let header = parse_header("X-Api-Key:a:b").unwrap();
+        assert_eq!(header, ("X-Api-Key".into(), "a:b".into()));
+        assert!(parse_header("Authorization Bearer token").is_err());
+        assert!(parse_header("Bad Name: value").is_err());
+    }
+}